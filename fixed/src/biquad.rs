@@ -0,0 +1,53 @@
+use crate::q15::{self, Q15};
+
+/// A 2nd order IIR section in direct form I:
+///
+/// `y[n] = b0*x[n] + b1*x[n-1] + b2*x[n-2] - a1*y[n-1] - a2*y[n-2]`
+///
+/// Coefficients are Q2.30 with `a0` normalised to 1.
+#[derive(Copy, Clone, Debug)]
+pub struct Biquad {
+    num: [i32; 3],
+    den: [i32; 2],
+    x: [i32; 2],
+    y: [i32; 2],
+}
+
+impl Biquad {
+    /// Creates a section from Q2.30 numerator `[b0, b1, b2]` and denominator `[a1, a2]` coefficients.
+    pub const fn new(num: [i32; 3], den: [i32; 2]) -> Self {
+        Biquad {
+            num,
+            den,
+            x: [0; 2],
+            y: [0; 2],
+        }
+    }
+
+    /// Filters one Q8.23 state value.
+    pub fn update_state(&mut self, input: i32) -> i32 {
+        let acc = q15::mul_coef(self.num[0], input)
+            + q15::mul_coef(self.num[1], self.x[0])
+            + q15::mul_coef(self.num[2], self.x[1])
+            - q15::mul_coef(self.den[0], self.y[0])
+            - q15::mul_coef(self.den[1], self.y[1]);
+        let output = q15::acc_to_state(acc);
+
+        self.x[1] = self.x[0];
+        self.x[0] = input;
+        self.y[1] = self.y[0];
+        self.y[0] = output;
+
+        output
+    }
+
+    /// Filters one Q15 sample.
+    pub fn update(&mut self, input: Q15) -> Q15 {
+        q15::from_state(self.update_state(q15::to_state(input)))
+    }
+
+    pub fn reset(&mut self) {
+        self.x = [0; 2];
+        self.y = [0; 2];
+    }
+}
@@ -0,0 +1,82 @@
+use crate::q15::Q15;
+
+/// Samples the detector keeps reporting a flex for after the envelope drops to zero
+const HOLD_SAMPLES: u32 = 75;
+
+/// Squares a filtered signal into an envelope and detects flexes in it, like `wfpi::myo::Flappy`.
+#[derive(Copy, Clone, Debug)]
+pub struct EnvelopeDetector {
+    /// Envelope threshold in Q30 (the square of a Q15 sample)
+    threshold: i32,
+    emitting: bool,
+    active: bool,
+    zero_run: u32,
+}
+
+impl EnvelopeDetector {
+    /// Creates a detector with a threshold on the Q30 envelope.
+    pub const fn new(threshold: i32) -> Self {
+        EnvelopeDetector {
+            threshold,
+            emitting: false,
+            active: false,
+            zero_run: 0,
+        }
+    }
+
+    /// Creates a detector with a threshold given in squared ADC counts, as used by `Flappy`.
+    pub fn from_adc_threshold(counts_squared: u32) -> Self {
+        Self::new(adc_threshold(counts_squared))
+    }
+
+    pub fn set_threshold(&mut self, threshold: i32) {
+        self.threshold = threshold;
+    }
+
+    /// Changes the threshold, given in squared ADC counts, without disturbing a flex in progress.
+    pub fn set_adc_threshold(&mut self, counts_squared: u32) {
+        self.threshold = adc_threshold(counts_squared);
+    }
+
+    /// Takes a filtered Q15 sample. Returns whether the muscle is flexed, along with the
+    /// thresholded Q30 envelope (zero while relaxed).
+    pub fn update(&mut self, filtered: Q15) -> (bool, i32) {
+        let envelope = i32::from(filtered) * i32::from(filtered);
+        let envelope = if envelope > self.threshold {
+            envelope
+        } else {
+            0
+        };
+
+        let result = self.emitting;
+
+        if envelope > 0 {
+            if !self.active {
+                self.active = true;
+                self.emitting = true;
+            }
+            self.zero_run = 0;
+        } else if self.active {
+            // The first zero starts the count, so release happens on the zero after the hold
+            self.zero_run += 1;
+            if self.zero_run > HOLD_SAMPLES + 1 {
+                self.active = false;
+                self.emitting = false;
+                self.zero_run = 0;
+            }
+        }
+
+        (result, envelope)
+    }
+
+    pub fn reset(&mut self) {
+        self.emitting = false;
+        self.active = false;
+        self.zero_run = 0;
+    }
+}
+
+fn adc_threshold(counts_squared: u32) -> i32 {
+    // One ADC count is 1 << 6 in Q15, so a squared count is 1 << 12 in Q30
+    (u64::from(counts_squared) << 12).min(core::i32::MAX as u64) as i32
+}
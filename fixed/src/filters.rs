@@ -0,0 +1,236 @@
+use crate::biquad::Biquad;
+use crate::q15::{self, Q15};
+use crate::q30;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum NotchFrequency {
+    Freq50Hz = 50,
+    Freq60Hz = 60,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum SampleFrequency {
+    Freq500Hz = 500,
+    Freq1000Hz = 1000,
+}
+
+// Coefficients as Q2.30, taken from the floating point filters.
+// num: [b0, b1, b2], den: [a1, a2], indexed by [sampleFreqInd]
+
+// 2nd order butterworth lowpass filter, cutoff frequency 150Hz
+const LPF_NUM: [[i32; 3]; 2] = [
+    [q30!(0.3913), q30!(0.7827), q30!(0.3913)],
+    [q30!(0.1311), q30!(0.2622), q30!(0.1311)],
+];
+const LPF_DEN: [[i32; 2]; 2] = [[q30!(0.3695), q30!(0.1958)], [q30!(-0.7478), q30!(0.2722)]];
+
+// 2nd order butterworth highpass filter, cutoff frequency 20Hz
+const HPF_NUM: [[i32; 3]; 2] = [
+    [q30!(0.8371), q30!(-1.6742), q30!(0.8371)],
+    [q30!(0.9150), q30!(-1.8299), q30!(0.9150)],
+];
+const HPF_DEN: [[i32; 2]; 2] = [[q30!(-1.6475), q30!(0.7009)], [q30!(-1.8227), q30!(0.8372)]];
+
+// anti-hum filter: two cascaded sections followed by an output gain
+const AHF_NUM_50HZ: [[[i32; 3]; 2]; 2] = [
+    [
+        [q30!(0.9522), q30!(-1.5407), q30!(0.9522)],
+        [q30!(0.8158), q30!(-0.8045), q30!(0.0855)],
+    ],
+    [
+        [q30!(0.5869), q30!(-1.1146), q30!(0.5869)],
+        [q30!(1.0499), q30!(-2.0000), q30!(1.0499)],
+    ],
+];
+const AHF_DEN_50HZ: [[[i32; 2]; 2]; 2] = [
+    [
+        [q30!(-1.5395), q30!(0.9056)],
+        [q30!(-1.1187), q30!(0.3129)],
+    ],
+    [
+        [q30!(-1.8844), q30!(0.9893)],
+        [q30!(-1.8991), q30!(0.9892)],
+    ],
+];
+const AHF_GAIN_50HZ: [i32; 2] = [q30!(1.3422), q30!(1.4399)];
+
+const AHF_NUM_60HZ: [[[i32; 3]; 2]; 2] = [
+    [
+        [q30!(0.9528), q30!(-1.3891), q30!(0.9528)],
+        [q30!(0.8272), q30!(-0.7225), q30!(0.0264)],
+    ],
+    [
+        [q30!(0.5824), q30!(-1.0810), q30!(0.5824)],
+        [q30!(1.0736), q30!(-2.0000), q30!(1.0736)],
+    ],
+];
+const AHF_DEN_60HZ: [[[i32; 2]; 2]; 2] = [
+    [
+        [q30!(-1.3880), q30!(0.9066)],
+        [q30!(-0.9739), q30!(0.2371)],
+    ],
+    [
+        [q30!(-1.8407), q30!(0.9894)],
+        [q30!(-1.8584), q30!(0.9891)],
+    ],
+];
+const AHF_GAIN_60HZ: [i32; 2] = [q30!(1.3430), q30!(1.4206)];
+
+fn sample_index(sample_freq: SampleFrequency) -> usize {
+    match sample_freq {
+        SampleFrequency::Freq500Hz => 0,
+        SampleFrequency::Freq1000Hz => 1,
+    }
+}
+
+/// 2nd order butterworth low-pass filter at 150Hz
+#[derive(Copy, Clone, Debug)]
+pub struct LowPass(Biquad);
+
+impl LowPass {
+    pub fn new(sample_freq: SampleFrequency) -> Self {
+        let i = sample_index(sample_freq);
+        LowPass(Biquad::new(LPF_NUM[i], LPF_DEN[i]))
+    }
+
+    pub fn update_state(&mut self, input: i32) -> i32 {
+        self.0.update_state(input)
+    }
+
+    pub fn update(&mut self, input: Q15) -> Q15 {
+        self.0.update(input)
+    }
+
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+/// 2nd order butterworth high-pass filter at 20Hz
+#[derive(Copy, Clone, Debug)]
+pub struct HighPass(Biquad);
+
+impl HighPass {
+    pub fn new(sample_freq: SampleFrequency) -> Self {
+        let i = sample_index(sample_freq);
+        HighPass(Biquad::new(HPF_NUM[i], HPF_DEN[i]))
+    }
+
+    pub fn update_state(&mut self, input: i32) -> i32 {
+        self.0.update_state(input)
+    }
+
+    pub fn update(&mut self, input: Q15) -> Q15 {
+        self.0.update(input)
+    }
+
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+/// 4th order anti-hum filter for 50Hz or 60Hz power line noise
+#[derive(Copy, Clone, Debug)]
+pub struct NotchFilter {
+    sections: [Biquad; 2],
+    gain: i32,
+}
+
+impl NotchFilter {
+    pub fn new(sample_freq: SampleFrequency, hum_freq: NotchFrequency) -> Self {
+        let i = sample_index(sample_freq);
+        let (num, den, gain) = match hum_freq {
+            NotchFrequency::Freq50Hz => (AHF_NUM_50HZ[i], AHF_DEN_50HZ[i], AHF_GAIN_50HZ[i]),
+            NotchFrequency::Freq60Hz => (AHF_NUM_60HZ[i], AHF_DEN_60HZ[i], AHF_GAIN_60HZ[i]),
+        };
+        NotchFilter {
+            sections: [Biquad::new(num[0], den[0]), Biquad::new(num[1], den[1])],
+            gain,
+        }
+    }
+
+    pub fn update_state(&mut self, input: i32) -> i32 {
+        let stage_in = self.sections[0].update_state(input);
+        let stage_out = self.sections[1].update_state(stage_in);
+        q15::acc_to_state(q15::mul_coef(self.gain, stage_out))
+    }
+
+    pub fn update(&mut self, input: Q15) -> Q15 {
+        q15::from_state(self.update_state(q15::to_state(input)))
+    }
+
+    pub fn reset(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.reset();
+        }
+    }
+}
+
+/// The anti-hum, low-pass and high-pass filters in sequence, as in `wfpi::myo::EMGFilters`.
+#[derive(Copy, Clone, Debug)]
+pub struct EmgFilters {
+    sample_freq: SampleFrequency,
+    notch_filter_enabled: bool,
+    lowpass_filter_enabled: bool,
+    highpass_filter_enabled: bool,
+
+    lpf: LowPass,
+    hpf: HighPass,
+    ahf: NotchFilter,
+}
+
+impl EmgFilters {
+    pub fn new(
+        sample_freq: SampleFrequency,
+        notch_freq: NotchFrequency,
+        enable_notch_filter: bool,
+        enable_lowpass_filter: bool,
+        enable_highpass_filter: bool,
+    ) -> Self {
+        EmgFilters {
+            sample_freq,
+            notch_filter_enabled: enable_notch_filter,
+            lowpass_filter_enabled: enable_lowpass_filter,
+            highpass_filter_enabled: enable_highpass_filter,
+
+            lpf: LowPass::new(sample_freq),
+            hpf: HighPass::new(sample_freq),
+            ahf: NotchFilter::new(sample_freq, notch_freq),
+        }
+    }
+
+    /// Retunes the anti-hum filter to a different power line frequency, resetting its state.
+    pub fn set_notch_frequency(&mut self, notch_freq: NotchFrequency) {
+        self.ahf = NotchFilter::new(self.sample_freq, notch_freq);
+    }
+
+    /// Filters one Q15 sample. Intermediate results between the filters keep full state precision.
+    pub fn update(&mut self, input: Q15) -> Q15 {
+        let mut output = q15::to_state(input);
+
+        if self.notch_filter_enabled {
+            output = self.ahf.update_state(output);
+        }
+        if self.lowpass_filter_enabled {
+            output = self.lpf.update_state(output);
+        }
+        if self.highpass_filter_enabled {
+            output = self.hpf.update_state(output);
+        }
+
+        q15::from_state(output)
+    }
+
+    /// Filters one raw 10-bit ADC reading.
+    pub fn update_adc(&mut self, raw: u16) -> Q15 {
+        self.update(q15::from_adc(raw))
+    }
+
+    pub fn reset(&mut self) {
+        self.lpf.reset();
+        self.hpf.reset();
+        self.ahf.reset();
+    }
+}
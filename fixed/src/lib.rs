@@ -0,0 +1,21 @@
+//! Fixed-point, allocation-free versions of the EMG filters and flex detector used by `wfpi`,
+//! for microcontrollers without a floating point unit.
+//!
+//! Samples are passed around as Q15 (`i16`, full scale is [-1, 1)). Filter coefficients are
+//! stored as Q2.30 (`i32`, range [-2, 2)), and filter states as Q8.23 (`i32`), which keeps 8
+//! bits of headroom for the gain of the notch filters and 8 extra bits of precision for the
+//! feedback paths. Products are accumulated in `i64`.
+//!
+//! The filters follow <https://github.com/oymotion/EMGFilters>, like the floating point
+//! versions in `wfpi::myo`.
+
+#![no_std]
+
+mod biquad;
+mod detector;
+mod filters;
+pub mod q15;
+
+pub use biquad::Biquad;
+pub use detector::EnvelopeDetector;
+pub use filters::{EmgFilters, HighPass, LowPass, NotchFilter, NotchFrequency, SampleFrequency};
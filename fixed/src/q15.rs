@@ -0,0 +1,75 @@
+//! Conversions between the fixed-point formats used in this crate.
+
+/// Q15: 1 sign bit and 15 fractional bits
+pub type Q15 = i16;
+
+/// Fractional bits of filter coefficients (Q2.30)
+pub const COEF_SHIFT: u32 = 30;
+/// Fractional bits of filter states (Q8.23)
+pub const STATE_SHIFT: u32 = 23;
+
+/// Number of bits a 10-bit ADC reading is shifted up by to fill a Q15 sample
+const ADC_SHIFT: u32 = 6;
+/// Mid-scale of a 10-bit ADC
+const ADC_MIDPOINT: i16 = 512;
+
+/// Converts a floating point coefficient to Q2.30. Usable in constants.
+#[macro_export]
+macro_rules! q30 {
+    ($x:expr) => {
+        (($x) as f64 * 1_073_741_824f64) as i32
+    };
+}
+
+/// Centres a 10-bit ADC reading on zero and scales it to Q15.
+pub fn from_adc(raw: u16) -> Q15 {
+    ((raw & 0x3FF) as i16 - ADC_MIDPOINT) << ADC_SHIFT
+}
+
+/// Converts a Q15 sample back to ADC counts relative to mid-scale.
+pub fn to_adc_counts(sample: Q15) -> i16 {
+    sample >> ADC_SHIFT
+}
+
+/// Widens a Q15 sample to a Q8.23 state.
+pub fn to_state(sample: Q15) -> i32 {
+    i32::from(sample) << (STATE_SHIFT - 15)
+}
+
+/// Narrows a Q8.23 state to a Q15 sample, rounding and saturating.
+pub fn from_state(state: i32) -> Q15 {
+    let shift = STATE_SHIFT - 15;
+    let rounded = (i64::from(state) + (1 << (shift - 1))) >> shift;
+    saturate_i16(rounded)
+}
+
+/// Multiplies a Q2.30 coefficient by a Q8.23 state, giving an unrounded product in Q.53.
+pub fn mul_coef(coef: i32, state: i32) -> i64 {
+    i64::from(coef) * i64::from(state)
+}
+
+/// Rounds a Q.53 accumulator back down to a Q8.23 state, saturating.
+pub fn acc_to_state(acc: i64) -> i32 {
+    let rounded = (acc + (1 << (COEF_SHIFT - 1))) >> COEF_SHIFT;
+    saturate_i32(rounded)
+}
+
+fn saturate_i16(value: i64) -> i16 {
+    if value > i64::from(core::i16::MAX) {
+        core::i16::MAX
+    } else if value < i64::from(core::i16::MIN) {
+        core::i16::MIN
+    } else {
+        value as i16
+    }
+}
+
+fn saturate_i32(value: i64) -> i32 {
+    if value > i64::from(core::i32::MAX) {
+        core::i32::MAX
+    } else if value < i64::from(core::i32::MIN) {
+        core::i32::MIN
+    } else {
+        value as i32
+    }
+}
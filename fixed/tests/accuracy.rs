@@ -0,0 +1,301 @@
+//! Compares the fixed-point filters and detector against straightforward floating point
+//! implementations of the same difference equations, as used in `wfpi::myo::emg_filters`.
+
+use wfpi_fixed::q15;
+use wfpi_fixed::{
+    EmgFilters, EnvelopeDetector, HighPass, LowPass, NotchFilter, NotchFrequency, SampleFrequency,
+};
+
+/// Largest tolerated difference between the fixed and float outputs, in ADC counts
+const TOLERANCE: f32 = 0.5;
+/// Samples skipped at the start, while both filters settle from their initial states
+const SETTLE: usize = 500;
+
+struct Float2nd {
+    num: [f32; 3],
+    den: [f32; 3],
+    states: [f32; 2],
+}
+
+impl Float2nd {
+    fn new(num: [f32; 3], den: [f32; 3]) -> Self {
+        Float2nd {
+            num,
+            den,
+            states: [0f32; 2],
+        }
+    }
+
+    fn update(&mut self, input: f32) -> f32 {
+        let tmp =
+            (input - self.den[1] * self.states[0] - self.den[2] * self.states[1]) / self.den[0];
+        let output =
+            self.num[0] * tmp + self.num[1] * self.states[0] + self.num[2] * self.states[1];
+        self.states[1] = self.states[0];
+        self.states[0] = tmp;
+        output
+    }
+}
+
+struct Float4th {
+    num: [f32; 6],
+    den: [f32; 6],
+    gain: f32,
+    states: [f32; 4],
+}
+
+impl Float4th {
+    fn new(num: [f32; 6], den: [f32; 6], gain: f32) -> Self {
+        Float4th {
+            num,
+            den,
+            gain,
+            states: [0f32; 4],
+        }
+    }
+
+    fn update(&mut self, input: f32) -> f32 {
+        let stage_in = self.num[0] * input + self.states[0];
+        self.states[0] = (self.num[1] * input + self.states[1]) - self.den[1] * stage_in;
+        self.states[1] = self.num[2] * input - self.den[2] * stage_in;
+        let stage_out = self.num[3] * stage_in + self.states[2];
+        self.states[2] = (self.num[4] * stage_in + self.states[3]) - self.den[4] * stage_out;
+        self.states[3] = self.num[5] * stage_in - self.den[5] * stage_out;
+        self.gain * stage_out
+    }
+}
+
+fn float_lowpass(sample_freq: SampleFrequency) -> Float2nd {
+    match sample_freq {
+        SampleFrequency::Freq500Hz => {
+            Float2nd::new([0.3913, 0.7827, 0.3913], [1.0000, 0.3695, 0.1958])
+        }
+        SampleFrequency::Freq1000Hz => {
+            Float2nd::new([0.1311, 0.2622, 0.1311], [1.0000, -0.7478, 0.2722])
+        }
+    }
+}
+
+fn float_highpass(sample_freq: SampleFrequency) -> Float2nd {
+    match sample_freq {
+        SampleFrequency::Freq500Hz => {
+            Float2nd::new([0.8371, -1.6742, 0.8371], [1.0000, -1.6475, 0.7009])
+        }
+        SampleFrequency::Freq1000Hz => {
+            Float2nd::new([0.9150, -1.8299, 0.9150], [1.0000, -1.8227, 0.8372])
+        }
+    }
+}
+
+fn float_notch(sample_freq: SampleFrequency, hum_freq: NotchFrequency) -> Float4th {
+    match (hum_freq, sample_freq) {
+        (NotchFrequency::Freq50Hz, SampleFrequency::Freq500Hz) => Float4th::new(
+            [0.9522, -1.5407, 0.9522, 0.8158, -0.8045, 0.0855],
+            [1.0000, -1.5395, 0.9056, 1.0000, -1.1187, 0.3129],
+            1.3422,
+        ),
+        (NotchFrequency::Freq50Hz, SampleFrequency::Freq1000Hz) => Float4th::new(
+            [0.5869, -1.1146, 0.5869, 1.0499, -2.0000, 1.0499],
+            [1.0000, -1.8844, 0.9893, 1.0000, -1.8991, 0.9892],
+            1.4399,
+        ),
+        (NotchFrequency::Freq60Hz, SampleFrequency::Freq500Hz) => Float4th::new(
+            [0.9528, -1.3891, 0.9528, 0.8272, -0.7225, 0.0264],
+            [1.0000, -1.3880, 0.9066, 1.0000, -0.9739, 0.2371],
+            1.3430,
+        ),
+        (NotchFrequency::Freq60Hz, SampleFrequency::Freq1000Hz) => Float4th::new(
+            [0.5824, -1.0810, 0.5824, 1.0736, -2.0000, 1.0736],
+            [1.0000, -1.8407, 0.9894, 1.0000, -1.8584, 0.9891],
+            1.4206,
+        ),
+    }
+}
+
+/// A repeatable EMG-like test signal in raw 10-bit ADC readings: mid-scale offset, mains hum,
+/// and bursts of broadband noise standing in for contractions.
+fn test_signal(len: usize, sample_freq: SampleFrequency) -> Vec<u16> {
+    let fs = sample_freq as u32 as f32;
+    let mut seed = 0x1234_5678u32;
+    (0..len)
+        .map(|n| {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let noise = ((seed >> 16) as f32 / 65_536f32 - 0.5) * 2f32;
+            let t = n as f32 / fs;
+            let burst = if (n / 400) % 2 == 1 { 150f32 } else { 5f32 };
+            let hum = 40f32 * (2f32 * std::f32::consts::PI * 60f32 * t).sin();
+            let value = 512f32 + hum + burst * noise;
+            value.max(0f32).min(1023f32) as u16
+        })
+        .collect()
+}
+
+fn counts(raw: u16) -> f32 {
+    f32::from(raw) - 512f32
+}
+
+fn fixed_counts(sample: q15::Q15) -> f32 {
+    f32::from(sample) / 64f32
+}
+
+fn assert_close(name: &str, float: &[f32], fixed: &[f32]) {
+    let max_error = float
+        .iter()
+        .zip(fixed.iter())
+        .skip(SETTLE)
+        .map(|(a, b)| (a - b).abs())
+        .fold(0f32, f32::max);
+    assert!(
+        max_error < TOLERANCE,
+        "{}: max error {} counts exceeds {}",
+        name,
+        max_error,
+        TOLERANCE
+    );
+}
+
+const SAMPLE_FREQS: [SampleFrequency; 2] =
+    [SampleFrequency::Freq500Hz, SampleFrequency::Freq1000Hz];
+
+#[test]
+fn lowpass_matches_float() {
+    for &sample_freq in SAMPLE_FREQS.iter() {
+        let signal = test_signal(4000, sample_freq);
+        let mut float = float_lowpass(sample_freq);
+        let mut fixed = LowPass::new(sample_freq);
+        let float_out: Vec<f32> = signal.iter().map(|x| float.update(counts(*x))).collect();
+        let fixed_out: Vec<f32> = signal
+            .iter()
+            .map(|x| fixed_counts(fixed.update(q15::from_adc(*x))))
+            .collect();
+        assert_close("lowpass", &float_out, &fixed_out);
+    }
+}
+
+#[test]
+fn highpass_matches_float() {
+    for &sample_freq in SAMPLE_FREQS.iter() {
+        let signal = test_signal(4000, sample_freq);
+        let mut float = float_highpass(sample_freq);
+        let mut fixed = HighPass::new(sample_freq);
+        let float_out: Vec<f32> = signal.iter().map(|x| float.update(counts(*x))).collect();
+        let fixed_out: Vec<f32> = signal
+            .iter()
+            .map(|x| fixed_counts(fixed.update(q15::from_adc(*x))))
+            .collect();
+        assert_close("highpass", &float_out, &fixed_out);
+    }
+}
+
+#[test]
+fn notch_matches_float() {
+    for &sample_freq in SAMPLE_FREQS.iter() {
+        for &hum_freq in [NotchFrequency::Freq50Hz, NotchFrequency::Freq60Hz].iter() {
+            let signal = test_signal(4000, sample_freq);
+            let mut float = float_notch(sample_freq, hum_freq);
+            let mut fixed = NotchFilter::new(sample_freq, hum_freq);
+            let float_out: Vec<f32> = signal.iter().map(|x| float.update(counts(*x))).collect();
+            let fixed_out: Vec<f32> = signal
+                .iter()
+                .map(|x| fixed_counts(fixed.update(q15::from_adc(*x))))
+                .collect();
+            assert_close("notch", &float_out, &fixed_out);
+        }
+    }
+}
+
+#[test]
+fn filter_chain_matches_float() {
+    let sample_freq = SampleFrequency::Freq1000Hz;
+    let signal = test_signal(4000, sample_freq);
+
+    let mut notch = float_notch(sample_freq, NotchFrequency::Freq60Hz);
+    let mut lowpass = float_lowpass(sample_freq);
+    let mut highpass = float_highpass(sample_freq);
+    let mut fixed = EmgFilters::new(sample_freq, NotchFrequency::Freq60Hz, true, true, true);
+
+    let float_out: Vec<f32> = signal
+        .iter()
+        .map(|x| highpass.update(lowpass.update(notch.update(counts(*x)))))
+        .collect();
+    let fixed_out: Vec<f32> = signal
+        .iter()
+        .map(|x| fixed_counts(fixed.update_adc(*x)))
+        .collect();
+    assert_close("filter chain", &float_out, &fixed_out);
+}
+
+#[test]
+fn detector_agrees_with_float() {
+    const THRESHOLD: i32 = 100;
+    let sample_freq = SampleFrequency::Freq1000Hz;
+    let signal = test_signal(8000, sample_freq);
+
+    let mut notch = float_notch(sample_freq, NotchFrequency::Freq60Hz);
+    let mut lowpass = float_lowpass(sample_freq);
+    let mut highpass = float_highpass(sample_freq);
+    let mut fixed_filters =
+        EmgFilters::new(sample_freq, NotchFrequency::Freq60Hz, true, true, true);
+    let mut detector = EnvelopeDetector::from_adc_threshold(THRESHOLD as u32);
+
+    // The float side follows `Flappy`: square the filtered counts, threshold, then hold
+    // the flex until the envelope has been zero for 75 samples.
+    let mut integral = 0i64;
+    let mut emitting = false;
+    let mut remain_flag = false;
+    let mut time_millis = 0u32;
+    let mut time_begin_zero = 0u32;
+
+    let mut disagreements = 0;
+    let mut float_onsets = 0;
+    let mut fixed_onsets = 0;
+    let mut last = (false, false);
+    for x in signal.iter() {
+        let filtered = highpass.update(lowpass.update(notch.update(counts(*x)))) as i32;
+        let envelope = filtered.pow(2);
+        let envelope = if envelope > THRESHOLD { envelope } else { 0 };
+
+        let float_flexed = emitting;
+        let integral_eve = integral;
+        integral += i64::from(envelope);
+        if integral_eve == 0 && envelope > 0 {
+            emitting = true;
+        }
+        if integral_eve == integral && integral_eve != 0 {
+            time_millis += 1;
+            if remain_flag {
+                time_begin_zero = time_millis;
+                remain_flag = false;
+            } else if time_millis - time_begin_zero > 75 {
+                integral = 0;
+                emitting = false;
+            }
+        } else {
+            remain_flag = true;
+        }
+
+        let (fixed_flexed, _) = detector.update(fixed_filters.update_adc(*x));
+        if fixed_flexed != float_flexed {
+            disagreements += 1;
+        }
+        if float_flexed && !last.0 {
+            float_onsets += 1;
+        }
+        if fixed_flexed && !last.1 {
+            fixed_onsets += 1;
+        }
+        last = (float_flexed, fixed_flexed);
+    }
+
+    // `Flappy` truncates the filtered signal to whole counts before squaring it, so samples
+    // close to the threshold can fall either way and shift the end of a hold by a few
+    // samples. Every contraction must still be detected exactly once.
+    assert!(float_onsets > 0);
+    assert_eq!(float_onsets, fixed_onsets);
+    assert!(
+        disagreements * 20 < signal.len(),
+        "detector disagreed on {} of {} samples",
+        disagreements,
+        signal.len()
+    );
+}
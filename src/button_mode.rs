@@ -0,0 +1,109 @@
+//! Turns whether a source is active (a flex, say) into whether a button is pressed, for
+//! players who can't hold or repeat an input the way a game expects.
+
+use std::time::Duration;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ButtonMode {
+    /// Pressed while the input is active
+    Momentary,
+    /// Each activation flips the button between pressed and released
+    Toggle,
+    /// Repeatedly pressed and released while the input is active, once per `interval`
+    Turbo { interval: Duration },
+    /// Each activation presses the button for `length`, however long the input lasts
+    Pulse { length: Duration },
+    /// Like `Toggle`, but a latched button is released after `timeout` if nothing
+    /// releases it sooner
+    Sticky { timeout: Duration },
+}
+
+impl Default for ButtonMode {
+    fn default() -> Self {
+        ButtonMode::Momentary
+    }
+}
+
+/// Applies a `ButtonMode` to a stream of inputs.
+pub struct ButtonBehaviour {
+    mode: ButtonMode,
+    active: bool,
+    pressed: bool,
+    /// When the button was last pressed by an activation (not by turbo repeating)
+    since: Duration,
+}
+
+impl ButtonBehaviour {
+    pub fn new(mode: ButtonMode) -> Self {
+        Self {
+            mode,
+            active: false,
+            pressed: false,
+            since: Duration::from_secs(0),
+        }
+    }
+
+    pub fn mode(&self) -> ButtonMode {
+        self.mode
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Takes whether the input is active at time `now`, and returns whether the button
+    /// should be pressed.
+    pub fn update(&mut self, active: bool, now: Duration) -> bool {
+        let activated = active && !self.active;
+        self.active = active;
+
+        match self.mode {
+            ButtonMode::Momentary => self.pressed = active,
+            ButtonMode::Toggle => {
+                if activated {
+                    self.pressed = !self.pressed;
+                }
+            }
+            ButtonMode::Turbo { interval } => {
+                if activated {
+                    self.since = now;
+                }
+                // Pressed for the first half of each interval
+                let held = now.checked_sub(self.since).unwrap_or_default();
+                let interval = interval.as_nanos().max(1);
+                self.pressed = active && held.as_nanos() % interval < (interval + 1) / 2;
+            }
+            ButtonMode::Pulse { length } => {
+                if activated {
+                    self.since = now;
+                    self.pressed = true;
+                } else if self.pressed && now.checked_sub(self.since).unwrap_or_default() >= length
+                {
+                    self.pressed = false;
+                }
+            }
+            ButtonMode::Sticky { timeout } => {
+                if activated {
+                    self.pressed = !self.pressed;
+                    self.since = now;
+                } else if self.pressed && now.checked_sub(self.since).unwrap_or_default() >= timeout
+                {
+                    self.pressed = false;
+                }
+            }
+        }
+        self.pressed
+    }
+
+    /// Moves time on to `now` with the input unchanged, for the modes that press or
+    /// release by themselves. Returns whether the button should be pressed.
+    pub fn tick(&mut self, now: Duration) -> bool {
+        self.update(self.active, now)
+    }
+
+    /// Forgets the input and releases the button.
+    pub fn reset(&mut self) {
+        self.active = false;
+        self.pressed = false;
+    }
+}
@@ -0,0 +1,34 @@
+//! Time sources for the parts of the output logic that depend on how long things take,
+//! so that they can run against a manually advanced clock on the host.
+
+use std::time::{Duration, Instant};
+
+pub trait Clock {
+    /// Time elapsed since some fixed starting point. Never runs backwards.
+    fn now(&self) -> Duration;
+}
+
+/// Wall-clock time since the clock was created.
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
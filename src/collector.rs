@@ -0,0 +1,944 @@
+//! Collapses the signals from the EEG and myo threads into outputs on the XAC, and keeps
+//! the recent history the TUI draws from.
+
+use embedded_hal::digital::v2::OutputPin;
+use rppal::gpio;
+use std::time::Duration;
+
+use termion::event::Key;
+
+use crate::button_mode::ButtonMode;
+use crate::clock::{Clock, SystemClock};
+use crate::eeg::EegBand;
+use crate::error::*;
+use crate::mapping::{Input, Mapper, Mapping, Transform};
+use crate::morse::{MorseConfig, MorseDecoder, MorseStatus, TextOutput};
+use crate::myo::{self, Side};
+use crate::pointer::{
+    MouseButton, Pointer, PointerAction, PointerConfig, PointerOutput, PointerStatus, SpeedSource,
+};
+use crate::scanning::{ScanConfig, ScanEvent, ScanMethod, ScanStatus, Scanner};
+use crate::scheduler::Scheduler;
+use crate::shaping::{Curve, Shaping};
+use crate::springboard::{OutputValue, Springboard};
+use crate::watchdog::Watchdog;
+
+/// Number of readings kept for each chart
+const DATA_AMOUNT: usize = 200;
+
+/// The buttons pressed by flexing the left and right myo channels
+const MYO_LEFT_BUTTON: &str = "left";
+const MYO_RIGHT_BUTTON: &str = "right";
+/// How flexing each myo channel presses its button. For a player who can't hold a flex,
+/// `ButtonMode::Toggle` or `ButtonMode::Sticky` latches the button instead.
+const MYO_LEFT_MODE: ButtonMode = ButtonMode::Momentary;
+const MYO_RIGHT_MODE: ButtonMode = ButtonMode::Momentary;
+/// The analog output driven by EEG attention
+const EEG_ANALOG: &str = "trigger";
+/// How attention is shaped before it reaches `EEG_ANALOG`. Only attention from 20-80 is
+/// counted, and the output ramps towards each reading between them, sweeping its full
+/// range in no less than two seconds, so that eSense's jumps don't come through as steps.
+const EEG_SHAPING: Shaping = Shaping {
+    input_min: 20f64,
+    input_max: 80f64,
+    deadzone: 0f64,
+    curve: Curve::Linear,
+    output_min: 0f64,
+    output_max: 100f64,
+    smoothing: None,
+    slew_rate: Some(50f64),
+};
+
+/// What drives the outputs outside the pointer, scanning and text modes. By default
+/// attention pulls the trigger and each myo channel presses its button.
+fn default_mappings() -> Vec<Mapping> {
+    vec![
+        Mapping {
+            input: Input::Attention,
+            transforms: vec![Transform::Shape(EEG_SHAPING)],
+            output: EEG_ANALOG.to_string(),
+        },
+        Mapping {
+            input: Input::Flexed(Side::Left),
+            transforms: vec![Transform::Mode(MYO_LEFT_MODE)],
+            output: MYO_LEFT_BUTTON.to_string(),
+        },
+        Mapping {
+            input: Input::Flexed(Side::Right),
+            transforms: vec![Transform::Mode(MYO_RIGHT_MODE)],
+            output: MYO_RIGHT_BUTTON.to_string(),
+        },
+    ]
+}
+
+/// How the myos and attention drive the pointer in pointer mode. Holding a left flex moves
+/// the pointer in a slowly turning direction, and a right flex clicks; attention sets how
+/// fast it moves.
+const POINTER: PointerConfig = PointerConfig {
+    left: PointerAction::Sweep,
+    right: PointerAction::Click(MouseButton::Left),
+    min_speed: 50f64,
+    max_speed: 600f64,
+    acceleration: Duration::from_secs(1),
+    acceleration_curve: Curve::Exponential(2f64),
+    speed_source: SpeedSource::Attention,
+    emg_full_speed: 400,
+    dwell: None,
+    sweep_period: Duration::from_secs(4),
+};
+
+/// The outputs scanned through in scanning mode, and how. With `ScanMethod::Auto` either
+/// myo selects; with `ScanMethod::Step` the left myo steps and the right myo selects.
+fn default_scanning() -> ScanConfig {
+    ScanConfig {
+        outputs: vec![
+            MYO_LEFT_BUTTON.to_string(),
+            MYO_RIGHT_BUTTON.to_string(),
+            EEG_ANALOG.to_string(),
+        ],
+        method: ScanMethod::Auto,
+        interval: Duration::from_millis(1500),
+        auto_repeat: None,
+    }
+}
+
+/// The myo channel that types Morse code in text mode, and how its flexes are read
+const MORSE_SIDE: Side = Side::Left;
+const MORSE: MorseConfig = MorseConfig {
+    unit: Duration::from_millis(250),
+    min_unit: Duration::from_millis(80),
+    max_unit: Duration::from_millis(1500),
+    adaptation: 0.2f64,
+    min_flex: Duration::from_millis(30),
+    letter_gap: 2f64,
+    word_gap: 5f64,
+};
+
+/// How long each source may go without sending data before the outputs it drives are
+/// released. The Mindwave reports once a second; the myo channels many times a second.
+const EEG_STALE_AFTER: Duration = Duration::from_secs(3);
+const MYO_STALE_AFTER: Duration = Duration::from_millis(500);
+/// How long an output may go without being updated before it is released
+const OUTPUT_STALE_AFTER: Duration = Duration::from_secs(5);
+
+/// The collector's settings. By default, the ones above.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollectorConfig {
+    /// Number of readings kept for each chart
+    pub data_amount: usize,
+    pub eeg_stale_after: Duration,
+    pub myo_stale_after: Duration,
+    pub output_stale_after: Duration,
+    pub mappings: Vec<Mapping>,
+    pub scanning: ScanConfig,
+    pub pointer: PointerConfig,
+    /// The myo channel that types Morse code in text mode
+    pub morse_side: Side,
+    pub morse: MorseConfig,
+}
+
+impl Default for CollectorConfig {
+    fn default() -> Self {
+        Self {
+            data_amount: DATA_AMOUNT,
+            eeg_stale_after: EEG_STALE_AFTER,
+            myo_stale_after: MYO_STALE_AFTER,
+            output_stale_after: OUTPUT_STALE_AFTER,
+            mappings: default_mappings(),
+            scanning: default_scanning(),
+            pointer: POINTER,
+            morse_side: MORSE_SIDE,
+            morse: MORSE,
+        }
+    }
+}
+
+/// Where the collector's data comes from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    Eeg,
+    MyoLeft,
+    MyoRight,
+}
+
+impl Source {
+    pub fn name(self) -> &'static str {
+        match self {
+            Source::Eeg => "EEG",
+            Source::MyoLeft => "left myo",
+            Source::MyoRight => "right myo",
+        }
+    }
+
+    /// Whether `input` comes from the source
+    fn provides(self, input: Input) -> bool {
+        match (self, input) {
+            (Source::Eeg, Input::Attention)
+            | (Source::Eeg, Input::Meditation)
+            | (Source::Eeg, Input::Band(_)) => true,
+            (Source::MyoLeft, Input::Flexed(side)) | (Source::MyoLeft, Input::Level(side)) => {
+                side == Side::Left
+            }
+            (Source::MyoRight, Input::Flexed(side)) | (Source::MyoRight, Input::Level(side)) => {
+                side == Side::Right
+            }
+            _ => false,
+        }
+    }
+}
+
+pub enum DeviceSignal {
+    Eeg(u8, u8, u8),
+    Myo1(bool, i32),
+    Myo2(bool, i32),
+    /// The power in each `EegBand`
+    EegPower([u32; 8]),
+    /// The ADC's analog channel
+    Analog(u16),
+    Fatigue(myo::Side, myo::FatigueReport),
+    Quality(myo::Side, myo::QualityReport),
+}
+
+/// Everything the TUI shows.
+#[derive(Clone, Debug, Default)]
+pub struct UiState {
+    /// Attention, meditation and signal quality over time
+    pub eeg_data: Vec<(f64, [u16; 3])>,
+    pub myo_left_data: Vec<(f64, f64)>,
+    pub myo_right_data: Vec<(f64, f64)>,
+    /// What each output is set to
+    pub sending: Vec<(String, OutputValue)>,
+    pub current_time: f64,
+    /// Whether the outputs are overridden from the keyboard
+    pub override_output: bool,
+    pub fatigue: [myo::FatigueReport; 2],
+    pub quality: [myo::QualityReport; 2],
+    /// How many analog output writes read back wrong
+    pub mismatches: u32,
+    /// The pointer, while in pointer mode
+    pub pointer: Option<PointerStatus>,
+    /// The scan, while in scanning mode
+    pub scan: Option<ScanStatus>,
+    /// The Morse decoder, while in text mode
+    pub morse: Option<MorseStatus>,
+}
+
+pub struct Collector<P = gpio::OutputPin, C = SystemClock>
+where
+    P: OutputPin,
+    WfpiError: From<P::Error>,
+{
+    output: Springboard<P>,
+    scheduler: Scheduler,
+    clock: C,
+    config: CollectorConfig,
+    mapper: Mapper,
+
+    sources: Watchdog<Source>,
+    outputs: Watchdog<String>,
+
+    last_data: [u16; 3],
+    eeg_data: Vec<(f64, [u16; 3])>,
+
+    myo_left_data: Vec<(f64, f64)>,
+    myo_right_data: Vec<(f64, f64)>,
+
+    /// The chart time of the latest signal, half a step on from the one before
+    current_time: f64,
+    /// Whether any signal has been charted yet, so that the first is at time 0
+    charted: bool,
+
+
+    override_output: bool,
+
+    pointer: Pointer,
+    mouse: Option<Box<dyn PointerOutput>>,
+    pointer_mode: bool,
+
+    scanner: Scanner,
+    scanning: bool,
+
+    morse: MorseDecoder,
+    keyboard: Option<Box<dyn TextOutput>>,
+    text_mode: bool,
+
+    /// Whether each myo is flexed, for the scanner and the Morse decoder
+    switches: (bool, bool),
+
+    fatigue: [myo::FatigueReport; 2],
+    quality: [myo::QualityReport; 2],
+}
+
+impl<P, E> Collector<P>
+where
+    P: OutputPin<Error = E>,
+    WfpiError: From<E>,
+{
+    pub fn new(output: Springboard<P>) -> Self {
+        Self::with_clock(output, SystemClock::new())
+    }
+}
+
+impl<P, C, E> Collector<P, C>
+where
+    P: OutputPin<Error = E>,
+    C: Clock,
+    WfpiError: From<E>,
+{
+    pub fn with_clock(output: Springboard<P>, clock: C) -> Self {
+        let configs: Vec<_> = output
+            .buttons()
+            .iter()
+            .map(|button| button.config().clone())
+            .collect();
+
+        Self {
+            scheduler: Scheduler::new(&configs),
+            output,
+            clock,
+            config: CollectorConfig::default(),
+            mapper: Mapper::new(&[]),
+            sources: Watchdog::new(),
+            outputs: Watchdog::new(),
+            last_data: [0; 3],
+            eeg_data: vec![],
+            myo_left_data: vec![],
+            myo_right_data: vec![],
+            current_time: 0f64,
+            charted: false,
+            override_output: false,
+            pointer: Pointer::new(POINTER),
+            mouse: None,
+            pointer_mode: false,
+            scanner: Scanner::new(default_scanning()),
+            scanning: false,
+            morse: MorseDecoder::new(MORSE),
+            keyboard: None,
+            text_mode: false,
+            switches: (false, false),
+            fatigue: [myo::FatigueReport::default(); 2],
+            quality: [myo::QualityReport::default(); 2],
+        }
+        .with_config(CollectorConfig::default())
+    }
+
+    /// Runs with the settings in `config` in place of the default ones.
+    pub fn with_config(mut self, config: CollectorConfig) -> Self {
+        self.apply_config(config);
+        self
+    }
+
+    /// Switches to the settings in `config` while running. The mappings, scan, pointer and
+    /// Morse settings that haven't changed carry on as they were, so buttons stay held and
+    /// latched; outputs driven by a mapping that's gone or changed are released. If
+    /// `config` names an output the springboard doesn't have, nothing changes and the
+    /// error is returned.
+    pub fn reconfigure(&mut self, config: CollectorConfig) -> Result<()> {
+        let names = config
+            .mappings
+            .iter()
+            .map(|mapping| &mapping.output)
+            .chain(config.scanning.outputs.iter());
+        for name in names {
+            if self.output.button(name).is_none() && self.output.analog(name).is_none() {
+                return Err(WfpiError::UnknownOutput {
+                    name: name.to_string(),
+                });
+            }
+        }
+
+        let now = self.clock.now();
+        if config.scanning != self.config.scanning {
+            if let Some(name) = self.scanner.selected().map(str::to_string) {
+                if let Err(e) = self.release(&name) {
+                    log!("Failed to release {}: {}", name, e);
+                }
+            }
+            self.scanner = Scanner::new(config.scanning.clone());
+            self.scanner.reset(now);
+        }
+        if config.pointer != self.config.pointer {
+            self.pointer = Pointer::new(config.pointer);
+        }
+        if config.morse != self.config.morse {
+            self.morse = MorseDecoder::new(config.morse);
+        }
+        let dropped = self.mapper.remap(&config.mappings);
+        self.config = config;
+        self.watch();
+        if self.is_mapping() {
+            for name in dropped {
+                if let Err(e) = self.release(&name) {
+                    log!("Failed to release {}: {}", name, e);
+                }
+            }
+        }
+
+        let data_amount = self.config.data_amount;
+        for data in &mut [&mut self.myo_left_data, &mut self.myo_right_data] {
+            let excess = data.len().saturating_sub(data_amount);
+            data.drain(..excess);
+        }
+        let excess = self.eeg_data.len().saturating_sub(data_amount);
+        self.eeg_data.drain(..excess);
+        Ok(())
+    }
+
+    fn apply_config(&mut self, config: CollectorConfig) {
+        self.scanner = Scanner::new(config.scanning.clone());
+        self.pointer = Pointer::new(config.pointer);
+        self.morse = MorseDecoder::new(config.morse);
+        self.config = config;
+        self.set_mappings();
+    }
+
+    /// Drives the outputs with `mappings` in place of the default ones.
+    pub fn with_mappings(mut self, mappings: &[Mapping]) -> Self {
+        self.config.mappings = mappings.to_vec();
+        self.set_mappings();
+        self
+    }
+
+    /// Starts driving the outputs with the mappings in the config
+    fn set_mappings(&mut self) {
+        self.mapper = Mapper::new(&self.config.mappings);
+        self.watch();
+    }
+
+    /// Watches the sources and mapped outputs with the timeouts in the config. The ones
+    /// already watched keep when they were last updated.
+    fn watch(&mut self) {
+        self.sources.watch(Source::Eeg, self.config.eeg_stale_after);
+        self.sources
+            .watch(Source::MyoLeft, self.config.myo_stale_after);
+        self.sources
+            .watch(Source::MyoRight, self.config.myo_stale_after);
+        let outputs = self.mapper.outputs(|_| true);
+        self.outputs.retain(|name| outputs.contains(name));
+        for name in outputs {
+            self.outputs.watch(name, self.config.output_stale_after);
+        }
+    }
+
+    pub fn config(&self) -> &CollectorConfig {
+        &self.config
+    }
+
+    /// Lets the myos and attention drive `mouse` in pointer mode.
+    pub fn with_pointer(mut self, mouse: Box<dyn PointerOutput>) -> Self {
+        self.mouse = Some(mouse);
+        self
+    }
+
+    /// Lets a myo type Morse code on `keyboard` in text mode.
+    pub fn with_keyboard(mut self, keyboard: Box<dyn TextOutput>) -> Self {
+        self.keyboard = Some(keyboard);
+        self
+    }
+
+    /// Records a signal from one of the devices and forwards it to the XAC, unless the
+    /// outputs are overridden from the keyboard. If writing to the XAC fails, every output
+    /// is put into its safe state before the error is returned.
+    pub fn handle_signal(&mut self, data: DeviceSignal) -> Result<()> {
+        let result = self.forward_signal(data);
+        if result.is_err() {
+            self.safe_state();
+        }
+        result
+    }
+
+    /// Releases every button and returns the analog outputs to rest. Errors are logged,
+    /// since there's nothing more to fall back to.
+    pub fn safe_state(&mut self) {
+        if let Err(e) = self.output.safe_state() {
+            log!("Failed to release outputs: {}", e);
+        }
+        if let Err(e) = self.sync_scheduler() {
+            log!("Failed to release outputs: {}", e);
+        }
+        self.reset_mappings();
+    }
+
+    /// Releases the outputs of any source that has stopped sending data, and any output
+    /// that has stopped being updated, and makes the presses and releases that have come
+    /// due. Call regularly, whether or not data arrives, and no later than `next_due`.
+    /// Moves the pointer in pointer mode, the highlight in scanning mode, and ends Morse
+    /// letters and words in text mode. Does nothing while the outputs are overridden from
+    /// the keyboard.
+    pub fn tick(&mut self) {
+        if self.override_output {
+            return;
+        }
+        let now = self.clock.now();
+
+        for source in self.sources.expire(now) {
+            let age = self.sources.age(source, now).unwrap_or_default();
+            log!(
+                "No {} data for {:?}, releasing its outputs",
+                source.name(),
+                age
+            );
+            for name in self.mapper.outputs(|input| source.provides(input)) {
+                if let Err(e) = self.release(&name) {
+                    log!("Failed to release {}: {}", name, e);
+                }
+            }
+            match source {
+                Source::Eeg => self.pointer.set_attention(0f64),
+                Source::MyoLeft => {
+                    self.pointer.set_channel(Side::Left, false, 0);
+                    self.switches.0 = false;
+                }
+                Source::MyoRight => {
+                    self.pointer.set_channel(Side::Right, false, 0);
+                    self.switches.1 = false;
+                }
+            }
+        }
+
+        if self.scanning {
+            if let Err(e) = self.scan(now) {
+                log!("Failed to write to XAC, outputs released: {}", e);
+                self.safe_state();
+            }
+            return;
+        }
+
+        if self.text_mode {
+            self.type_text(now);
+            return;
+        }
+
+        if self.pointer_mode {
+            if let Some(mouse) = self.mouse.as_mut() {
+                if let Err(e) = self.pointer.update(now, mouse.as_mut()) {
+                    log!("Failed to move pointer: {}", e);
+                }
+            }
+            return;
+        }
+        for name in self.outputs.expire(now) {
+            log!(
+                "Output {} not updated for {:?}, releasing it",
+                name,
+                self.config.output_stale_after
+            );
+            if let Err(e) = self.release(&name) {
+                log!("Failed to release {}: {}", name, e);
+            }
+        }
+
+        let mut result = Ok(());
+        for (name, value) in self.mapper.tick(now) {
+            result = result.and_then(|_| self.write(&name, value, now));
+        }
+        if let Err(e) = result.and_then(|_| self.write_due(now)) {
+            log!("Failed to write to XAC, outputs released: {}", e);
+            self.safe_state();
+        }
+    }
+
+    /// How long until a held back press or release is due, if any are waiting.
+    pub fn next_due(&self) -> Option<Duration> {
+        let now = self.clock.now();
+        self.scheduler
+            .next_due()
+            .map(|due| due.checked_sub(now).unwrap_or_default())
+    }
+
+    /// Whether `source` has stopped sending data.
+    pub fn is_stale(&self, source: Source) -> bool {
+        self.sources.is_stale(source)
+    }
+
+    fn release(&mut self, name: &str) -> Result<()> {
+        if let Err(e) = self.output.release(name) {
+            log!("Failed to release {}: {}", name, e);
+        }
+        let scheduled = match self.output.button(name).map(|button| button.is_pressed()) {
+            Some(pressed) => self.scheduler.set(name, pressed, self.clock.now()),
+            None => Ok(()),
+        };
+        self.mapper.reset_output(name);
+        if let Some(value) = self.output.analog(name).map(|analog| analog.value()) {
+            self.mapper.hold(name, value, self.clock.now());
+        }
+        scheduled
+    }
+
+    /// Drops the presses and releases waiting to be made, and notes the buttons as they
+    /// are, for when they've been written without going through the scheduler.
+    fn sync_scheduler(&mut self) -> Result<()> {
+        let now = self.clock.now();
+        for button in self.output.buttons() {
+            self.scheduler
+                .set(&button.config().name, button.is_pressed(), now)?;
+        }
+        Ok(())
+    }
+
+    /// Feeds the myo switches to the scanner and carries out the selections.
+    fn scan(&mut self, now: Duration) -> Result<()> {
+        let (step, select) = match self.scanner.config().method {
+            ScanMethod::Auto => (false, self.switches.0 || self.switches.1),
+            ScanMethod::Step => self.switches,
+        };
+        for event in self.scanner.update(step, select, now) {
+            match event {
+                ScanEvent::Press(name) if self.output.button(&name).is_some() => {
+                    self.press(&name, true, now)?
+                }
+                ScanEvent::Release(name) if self.output.button(&name).is_some() => {
+                    self.press(&name, false, now)?
+                }
+                ScanEvent::Press(name) => {
+                    self.output.update_analog(&name, 100f64)?;
+                    self.mapper.hold(&name, 100f64, now);
+                }
+                ScanEvent::Release(name) => self.release(&name)?,
+            }
+        }
+        if let Some(name) = self.scanner.selected() {
+            self.outputs.feed(name, now);
+        }
+        self.write_due(now)
+    }
+
+    /// Feeds the Morse channel to the decoder and types what it decodes.
+    fn type_text(&mut self, now: Duration) {
+        let flexed = match self.config.morse_side {
+            Side::Left => self.switches.0,
+            Side::Right => self.switches.1,
+            Side::Analog => false,
+        };
+        for key in self.morse.update(flexed, now) {
+            if let Some(keyboard) = self.keyboard.as_mut() {
+                if let Err(e) = keyboard.type_key(key) {
+                    log!("Failed to type {:?}: {}", key, e);
+                }
+            }
+        }
+    }
+
+    /// Runs a reading of `input` through the mappings and writes the outputs it drives.
+    fn map(&mut self, input: Input, value: f64, now: Duration) -> Result<()> {
+        for (name, value) in self.mapper.update(input, value, now) {
+            self.write(&name, value, now)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the output called `name` to a mapping's `value`, pressing a button if it's
+    /// over 50.
+    fn write(&mut self, name: &str, value: f64, now: Duration) -> Result<()> {
+        if self.output.button(name).is_some() {
+            self.press(name, value > 50f64, now)?;
+        } else {
+            self.output.update_analog(name, value)?;
+        }
+        self.outputs.feed(name, now);
+        Ok(())
+    }
+
+    /// Starts the mappings over from the outputs as they are, releasing latched and
+    /// repeating buttons.
+    fn reset_mappings(&mut self) {
+        self.mapper.reset();
+        let now = self.clock.now();
+        for analog in self.output.analogs() {
+            self.mapper.hold(analog.name(), analog.value(), now);
+        }
+    }
+
+    /// Whether the mappings drive the outputs, rather than the keyboard or another mode
+    fn is_mapping(&self) -> bool {
+        !(self.override_output || self.pointer_mode || self.scanning || self.text_mode)
+    }
+
+    /// Asks for the button called `name` to be pressed or released, and makes whichever
+    /// presses and releases are due.
+    fn press(&mut self, name: &str, pressed: bool, now: Duration) -> Result<()> {
+        self.scheduler.request(name, pressed)?;
+        self.write_due(now)
+    }
+
+    /// Makes the presses and releases that are due at `now`.
+    fn write_due(&mut self, now: Duration) -> Result<()> {
+        for (name, pressed) in self.scheduler.take_due(now) {
+            self.output.update_button(&name, pressed)?;
+        }
+        Ok(())
+    }
+
+    /// Notes that `source` sent data at `now`.
+    fn feed(&mut self, source: Source, now: Duration) {
+        if self.sources.feed(source, now) {
+            log!("{} data resumed", source.name());
+        }
+    }
+
+    fn forward_signal(&mut self, data: DeviceSignal) -> Result<()> {
+        let now = self.clock.now();
+        // The previous signal has been shown at its own time by now
+        if self.charted {
+            self.current_time += 0.5f64;
+        }
+        self.charted = true;
+        match data {
+            DeviceSignal::Eeg(attention, meditation, signal_quality) => {
+                self.feed(Source::Eeg, now);
+
+                self.last_data[0] = u16::from(attention);
+                self.last_data[1] = u16::from(meditation);
+                self.last_data[2] = u16::from(signal_quality);
+
+                self.eeg_data.push((self.current_time, self.last_data));
+                if self.eeg_data.len() > self.config.data_amount {
+                    self.eeg_data.remove(0);
+                }
+
+                if self.pointer_mode {
+                    self.pointer.set_attention(f64::from(attention));
+                } else if self.is_mapping() {
+                    self.map(Input::Attention, f64::from(attention), now)?;
+                    self.map(Input::Meditation, f64::from(meditation), now)?;
+                }
+            }
+            DeviceSignal::Myo1(state, val) => {
+                self.feed(Source::MyoLeft, now);
+                if self.myo_left_data.len() > self.config.data_amount {
+                    self.myo_left_data.remove(0);
+                }
+                self.myo_left_data.push((self.current_time, val as f64));
+
+                self.switches.0 = state;
+                if self.pointer_mode {
+                    self.pointer.set_channel(Side::Left, state, val);
+                } else if self.scanning {
+                    self.scan(now)?;
+                } else if self.text_mode {
+                    self.type_text(now);
+                } else if self.is_mapping() {
+                    self.map(
+                        Input::Flexed(Side::Left),
+                        if state { 100f64 } else { 0f64 },
+                        now,
+                    )?;
+                    self.map(Input::Level(Side::Left), f64::from(val), now)?;
+                }
+            }
+            DeviceSignal::Myo2(state, val) => {
+                self.feed(Source::MyoRight, now);
+                if self.myo_right_data.len() > self.config.data_amount {
+                    self.myo_right_data.remove(0);
+                }
+                self.myo_right_data.push((self.current_time, val as f64));
+
+                self.switches.1 = state;
+                if self.pointer_mode {
+                    self.pointer.set_channel(Side::Right, state, val);
+                } else if self.scanning {
+                    self.scan(now)?;
+                } else if self.text_mode {
+                    self.type_text(now);
+                } else if self.is_mapping() {
+                    self.map(
+                        Input::Flexed(Side::Right),
+                        if state { 100f64 } else { 0f64 },
+                        now,
+                    )?;
+                    self.map(Input::Level(Side::Right), f64::from(val), now)?;
+                }
+            }
+            DeviceSignal::EegPower(power) => {
+                self.feed(Source::Eeg, now);
+                if self.is_mapping() {
+                    for &band in EegBand::ALL.iter() {
+                        self.map(Input::Band(band), f64::from(power[band as usize]), now)?;
+                    }
+                }
+            }
+            DeviceSignal::Analog(value) => {
+                if self.pointer_mode {
+                    self.pointer.set_analog(f64::from(value));
+                } else if self.is_mapping() {
+                    self.map(Input::Analog, f64::from(value), now)?;
+                }
+            }
+            DeviceSignal::Fatigue(side, report) => {
+                self.fatigue[side as usize] = report;
+            }
+            DeviceSignal::Quality(side, report) => {
+                self.quality[side as usize] = report;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles the keys for overriding the outputs by hand: `m` toggles the override, and
+    /// while it's on `z`/`x` press and release the left button, `c`/`v` the right button,
+    /// and `b`/`n` pull and release the trigger. `p` toggles pointer mode, if there's a
+    /// pointer to drive, which releases the outputs and hands the devices to the pointer,
+    /// `s` toggles scanning mode, in which the myos select outputs from a scan, and `t`
+    /// toggles text mode, in which a myo types Morse code, if there's a keyboard to type on.
+    pub fn handle_key(&mut self, key: Key) {
+        match key {
+            Key::Char('z') => {
+                if self.override_output {
+                    self.output
+                        .update_button(MYO_LEFT_BUTTON, true)
+                        .unwrap_or_else(|e| log!("Error updating left button: {:?}", e));
+                }
+            }
+            Key::Char('x') => {
+                if self.override_output {
+                    self.output
+                        .update_button(MYO_LEFT_BUTTON, false)
+                        .unwrap_or_else(|e| log!("Error updating left button: {:?}", e));
+                }
+            }
+            Key::Char('c') => {
+                if self.override_output {
+                    self.output
+                        .update_button(MYO_RIGHT_BUTTON, true)
+                        .unwrap_or_else(|e| log!("Error updating right button: {:?}", e));
+                }
+            }
+            Key::Char('v') => {
+                if self.override_output {
+                    self.output
+                        .update_button(MYO_RIGHT_BUTTON, false)
+                        .unwrap_or_else(|e| log!("Error updating right button: {:?}", e));
+                }
+            }
+            Key::Char('b') => {
+                if self.override_output {
+                    if let Err(e) = self.output.update_analog(EEG_ANALOG, 100f64) {
+                        log!("Error updating trigger: {:?}", e);
+                    }
+                    self.mapper.hold(EEG_ANALOG, 100f64, self.clock.now());
+                }
+            }
+            Key::Char('n') => {
+                if self.override_output {
+                    if let Err(e) = self.output.update_analog(EEG_ANALOG, 0f64) {
+                        log!("Error updating trigger: {:?}", e)
+                    }
+                    self.mapper.hold(EEG_ANALOG, 0f64, self.clock.now());
+                }
+            }
+            Key::Char('p') => {
+                if self.mouse.is_some() {
+                    self.set_scanning(false);
+                    self.set_text_mode(false);
+                    self.set_pointer_mode(!self.pointer_mode);
+                }
+            }
+            Key::Char('s') => {
+                self.set_pointer_mode(false);
+                self.set_text_mode(false);
+                self.set_scanning(!self.scanning);
+            }
+            Key::Char('t') => {
+                if self.keyboard.is_some() {
+                    self.set_pointer_mode(false);
+                    self.set_scanning(false);
+                    self.set_text_mode(!self.text_mode);
+                }
+            }
+            Key::Char('m') => {
+                self.set_pointer_mode(false);
+                self.set_scanning(false);
+                self.set_text_mode(false);
+                self.override_output = !self.override_output;
+                if let Err(e) = self.sync_scheduler() {
+                    log!("Error updating buttons: {}", e);
+                }
+                // Latched and repeating buttons start over once the myos take back over
+                self.reset_mappings();
+            }
+            _ => (),
+        };
+    }
+
+    fn set_pointer_mode(&mut self, on: bool) {
+        if on == self.pointer_mode {
+            return;
+        }
+        self.pointer_mode = on;
+        self.pointer.reset();
+        if on {
+            self.override_output = false;
+            self.safe_state();
+            log!("Pointer mode on");
+        } else {
+            log!("Pointer mode off");
+        }
+    }
+
+    fn set_scanning(&mut self, on: bool) {
+        if on == self.scanning {
+            return;
+        }
+        self.scanning = on;
+        self.switches = (false, false);
+        self.scanner.reset(self.clock.now());
+        self.safe_state();
+        if on {
+            self.override_output = false;
+            log!("Scanning mode on");
+        } else {
+            log!("Scanning mode off");
+        }
+    }
+
+    fn set_text_mode(&mut self, on: bool) {
+        if on == self.text_mode {
+            return;
+        }
+        self.text_mode = on;
+        // The learned timing is kept for next time
+        self.morse.reset();
+        if on {
+            self.override_output = false;
+            self.safe_state();
+            log!("Text mode on");
+        } else {
+            log!("Text mode off");
+        }
+    }
+
+    pub fn ui_state(&self) -> UiState {
+        UiState {
+            eeg_data: self.eeg_data.clone(),
+            myo_left_data: self.myo_left_data.clone(),
+            myo_right_data: self.myo_right_data.clone(),
+            sending: self.output.values(),
+            current_time: self.current_time,
+            override_output: self.override_output,
+            fatigue: self.fatigue,
+            quality: self.quality,
+            mismatches: self.output.mismatches(),
+            pointer: if self.pointer_mode {
+                Some(self.pointer.status(self.clock.now()))
+            } else {
+                None
+            },
+            scan: if self.scanning {
+                Some(self.scanner.status())
+            } else {
+                None
+            },
+            morse: if self.text_mode {
+                Some(self.morse.status())
+            } else {
+                None
+            },
+        }
+    }
+
+    pub fn output(&self) -> &Springboard<P> {
+        &self.output
+    }
+}
@@ -0,0 +1,852 @@
+//! The configuration file read at startup, so that the devices, pipelines and mappings can
+//! be set up without rebuilding. Anything the file leaves out keeps its default, which is
+//! what the constants in each module say; `wfpi --print-default-config` prints them all.
+//!
+//! The file is read again on SIGHUP or when it's written. Pipelines, mappings, scanning and
+//! timeouts switch over while running; the devices are only opened at startup, so changes
+//! to them wait for a restart.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::Deserialize;
+
+use crate::button_mode::ButtonMode;
+use crate::collector::CollectorConfig;
+use crate::eeg;
+use crate::emg_process::StageConfig;
+use crate::error::*;
+use crate::mapping::{Input, Mapping, Transform};
+use crate::myo::{MainsFrequency, MyoConfig, NotchFrequency, Side};
+use crate::pointer::{MouseButton, PointerAction, SpeedSource};
+use crate::scanning::ScanMethod;
+use crate::shaping::{Curve, Shaping};
+use crate::springboard::{
+    ActiveLevel, AnalogConfig, AnalogDevice, ButtonConfig, PressTiming, Wiring, XacJack,
+};
+
+/// Where the configuration is read from when no other file is named
+pub const DEFAULT_PATH: &str = "wfpi.toml";
+
+/// The default configuration, with every setting written out and explained
+pub const DEFAULT_CONFIG: &str = include_str!("../wfpi.toml");
+
+/// How often the TUI redraws
+const TICK_RATE: Duration = Duration::from_millis(250);
+/// How long the collector waits for a signal before checking whether it should stop
+const SIGNAL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The highest BCM GPIO number on the Pi's header
+const MAX_PIN: u8 = 27;
+/// The GPIO pins taken by the I2C bus (2 and 3) and SPI (7 to 11)
+const RESERVED_PINS: &[u8] = &[2, 3, 7, 8, 9, 10, 11];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// How often the TUI redraws
+    pub tick_rate: Duration,
+    /// How long the collector waits for a signal before checking whether it should stop
+    pub signal_timeout: Duration,
+    /// The baud rate of the Mindwave's serial port
+    pub eeg_baud_rate: u32,
+    pub myo: MyoConfig,
+    pub wiring: Wiring,
+    pub collector: CollectorConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tick_rate: TICK_RATE,
+            signal_timeout: SIGNAL_TIMEOUT,
+            eeg_baud_rate: eeg::BAUDRATE,
+            myo: MyoConfig::default(),
+            wiring: Wiring::default(),
+            collector: CollectorConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads the configuration in the file at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Reads a configuration from the text of a file. Errors name the key at fault.
+    pub fn parse(text: &str) -> Result<Self> {
+        let file: File = toml::from_str(text)?;
+        file.into_config()
+    }
+
+    /// The keys whose settings differ in `other` but are only read at startup, when the
+    /// devices are opened and the TUI starts.
+    pub fn needs_restart(&self, other: &Config) -> Vec<&'static str> {
+        let mut keys = vec![];
+        if self.tick_rate != other.tick_rate {
+            keys.push("tui.tick_rate_ms");
+        }
+        if self.eeg_baud_rate != other.eeg_baud_rate {
+            keys.push("eeg.baud_rate");
+        }
+        if self.myo.spi_clock_speed != other.myo.spi_clock_speed {
+            keys.push("myo.spi_clock_speed");
+        }
+        if self.myo.mains_frequency != other.myo.mains_frequency {
+            keys.push("myo.mains_frequency");
+        }
+        if self.myo.notch_harmonics != other.myo.notch_harmonics {
+            keys.push("myo.notch_harmonics");
+        }
+        if self.myo.adapt_threshold_to_fatigue != other.myo.adapt_threshold_to_fatigue {
+            keys.push("myo.adapt_threshold_to_fatigue");
+        }
+        if (self.myo.fatigue_threshold_drop - other.myo.fatigue_threshold_drop).abs()
+            > std::f32::EPSILON
+        {
+            keys.push("myo.fatigue_threshold_drop");
+        }
+        if self.myo.suggest_breaks != other.myo.suggest_breaks {
+            keys.push("myo.suggest_breaks");
+        }
+        if self.wiring != other.wiring {
+            keys.push("outputs");
+        }
+        keys
+    }
+}
+
+/// Notices when a file is written, by its modification time.
+pub struct FileWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl FileWatcher {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            modified: modified(path),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file has been written or created since the last call. Removing it
+    /// doesn't count.
+    pub fn changed(&mut self) -> bool {
+        let modified = modified(&self.path);
+        let changed = modified.is_some() && modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// The configuration as written in the file, before it's checked
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct File {
+    tui: TuiSection,
+    collector: CollectorSection,
+    eeg: EegSection,
+    myo: MyoSection,
+    outputs: OutputsSection,
+    scanning: ScanningSection,
+    pointer: PointerSection,
+    morse: MorseSection,
+    mappings: Option<Vec<MappingEntry>>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TuiSection {
+    tick_rate_ms: Option<u64>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CollectorSection {
+    data_amount: Option<usize>,
+    signal_timeout_ms: Option<u64>,
+    eeg_stale_after_ms: Option<u64>,
+    myo_stale_after_ms: Option<u64>,
+    output_stale_after_ms: Option<u64>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct EegSection {
+    baud_rate: Option<u32>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MyoSection {
+    spi_clock_speed: Option<u32>,
+    left_pipeline: Option<Vec<StageConfig>>,
+    right_pipeline: Option<Vec<StageConfig>>,
+    mains_frequency: Option<MainsEntry>,
+    notch_harmonics: Option<bool>,
+    adapt_threshold_to_fatigue: Option<bool>,
+    fatigue_threshold_drop: Option<f32>,
+    suggest_breaks: Option<bool>,
+}
+
+/// The mains frequency in Hz, or "auto"
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MainsEntry {
+    Hz(u32),
+    Named(String),
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct OutputsSection {
+    i2c_bus: Option<u8>,
+    spi_clock_speed: Option<u32>,
+    buttons: Option<Vec<ButtonEntry>>,
+    analog: Option<Vec<AnalogEntry>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ButtonEntry {
+    name: String,
+    pin: u8,
+    active_level: Option<ActiveLevel>,
+    jack: XacJack,
+    min_press_ms: Option<u64>,
+    min_release_ms: Option<u64>,
+    min_period_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AnalogEntry {
+    name: String,
+    device: Option<AnalogDevice>,
+    rest: Option<f64>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ScanningSection {
+    outputs: Option<Vec<String>>,
+    method: Option<ScanMethod>,
+    interval_ms: Option<u64>,
+    auto_repeat_ms: Option<u64>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PointerSection {
+    left: Option<PointerActionEntry>,
+    right: Option<PointerActionEntry>,
+    min_speed: Option<f64>,
+    max_speed: Option<f64>,
+    acceleration_ms: Option<u64>,
+    acceleration_curve: Option<Curve>,
+    speed_source: Option<SpeedSource>,
+    emg_full_speed: Option<i32>,
+    dwell_ms: Option<u64>,
+    sweep_period_ms: Option<u64>,
+}
+
+/// A `PointerAction`, with the clicked button as a key of its own
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum PointerActionEntry {
+    Move { x: f64, y: f64 },
+    Sweep,
+    Click { button: MouseButton },
+    Nothing,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MorseSection {
+    side: Option<Side>,
+    unit_ms: Option<u64>,
+    min_unit_ms: Option<u64>,
+    max_unit_ms: Option<u64>,
+    adaptation: Option<f64>,
+    min_flex_ms: Option<u64>,
+    letter_gap: Option<f64>,
+    word_gap: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MappingEntry {
+    input: Input,
+    #[serde(default)]
+    transforms: Vec<TransformEntry>,
+    output: String,
+}
+
+/// A `Transform`, with the button modes spelled out as transforms of their own
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TransformEntry {
+    Threshold {
+        on: f64,
+        off: Option<f64>,
+    },
+    Invert,
+    Scale {
+        input_min: f64,
+        input_max: f64,
+        output_min: Option<f64>,
+        output_max: Option<f64>,
+    },
+    Curve {
+        curve: Curve,
+    },
+    Latch,
+    Momentary,
+    Toggle,
+    Turbo {
+        interval_ms: u64,
+    },
+    Pulse {
+        length_ms: u64,
+    },
+    Sticky {
+        timeout_ms: u64,
+    },
+    Shape {
+        input_min: Option<f64>,
+        input_max: Option<f64>,
+        deadzone: Option<f64>,
+        curve: Option<Curve>,
+        output_min: Option<f64>,
+        output_max: Option<f64>,
+        smoothing_ms: Option<u64>,
+        slew_rate: Option<f64>,
+    },
+}
+
+/// Overwrites `setting` with `value`, if the file has one
+fn set<T>(setting: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *setting = value;
+    }
+}
+
+fn ms(value: Option<u64>) -> Option<Duration> {
+    value.map(Duration::from_millis)
+}
+
+fn check(ok: bool, key: &str, reason: &str) -> Result<()> {
+    if ok {
+        Ok(())
+    } else {
+        Err(WfpiError::InvalidConfig {
+            key: key.to_string(),
+            reason: reason.to_string(),
+        })
+    }
+}
+
+/// Checks that `wiring` has a button or analog output called `name`
+fn check_output(wiring: &Wiring, name: &str, key: &str) -> Result<()> {
+    let exists = wiring.buttons.iter().any(|button| button.name == name)
+        || wiring
+            .analog_outputs
+            .iter()
+            .any(|analog| analog.name == name);
+    if exists {
+        Ok(())
+    } else {
+        Err(WfpiError::InvalidConfig {
+            key: key.to_string(),
+            reason: format!("no button or analog output named {}", name),
+        })
+    }
+}
+
+impl File {
+    fn into_config(self) -> Result<Config> {
+        let mut config = Config::default();
+
+        set(&mut config.tick_rate, ms(self.tui.tick_rate_ms));
+        check(
+            config.tick_rate > Duration::default(),
+            "tui.tick_rate_ms",
+            "must be more than 0",
+        )?;
+
+        let collector = self.collector;
+        set(&mut config.collector.data_amount, collector.data_amount);
+        set(&mut config.signal_timeout, ms(collector.signal_timeout_ms));
+        set(
+            &mut config.collector.eeg_stale_after,
+            ms(collector.eeg_stale_after_ms),
+        );
+        set(
+            &mut config.collector.myo_stale_after,
+            ms(collector.myo_stale_after_ms),
+        );
+        set(
+            &mut config.collector.output_stale_after,
+            ms(collector.output_stale_after_ms),
+        );
+        check(
+            config.collector.data_amount > 0,
+            "collector.data_amount",
+            "must be at least 1",
+        )?;
+        for &(value, key) in &[
+            (config.signal_timeout, "collector.signal_timeout_ms"),
+            (
+                config.collector.eeg_stale_after,
+                "collector.eeg_stale_after_ms",
+            ),
+            (
+                config.collector.myo_stale_after,
+                "collector.myo_stale_after_ms",
+            ),
+            (
+                config.collector.output_stale_after,
+                "collector.output_stale_after_ms",
+            ),
+        ] {
+            check(value > Duration::default(), key, "must be more than 0")?;
+        }
+
+        set(&mut config.eeg_baud_rate, self.eeg.baud_rate);
+        check(
+            config.eeg_baud_rate > 0,
+            "eeg.baud_rate",
+            "must be more than 0",
+        )?;
+
+        set(&mut config.myo.spi_clock_speed, self.myo.spi_clock_speed);
+        set(&mut config.myo.left_pipeline, self.myo.left_pipeline);
+        set(&mut config.myo.right_pipeline, self.myo.right_pipeline);
+        check(
+            config.myo.spi_clock_speed > 0,
+            "myo.spi_clock_speed",
+            "must be more than 0",
+        )?;
+        if let Some(mains) = self.myo.mains_frequency {
+            config.myo.mains_frequency = match mains {
+                MainsEntry::Hz(50) => MainsFrequency::Fixed(NotchFrequency::Freq50Hz),
+                MainsEntry::Hz(60) => MainsFrequency::Fixed(NotchFrequency::Freq60Hz),
+                MainsEntry::Named(ref name) if name == "auto" => MainsFrequency::Auto,
+                _ => {
+                    return Err(WfpiError::InvalidConfig {
+                        key: "myo.mains_frequency".to_string(),
+                        reason: "must be 50, 60 or \"auto\"".to_string(),
+                    })
+                }
+            };
+        }
+        set(&mut config.myo.notch_harmonics, self.myo.notch_harmonics);
+        set(
+            &mut config.myo.adapt_threshold_to_fatigue,
+            self.myo.adapt_threshold_to_fatigue,
+        );
+        set(
+            &mut config.myo.fatigue_threshold_drop,
+            self.myo.fatigue_threshold_drop,
+        );
+        set(&mut config.myo.suggest_breaks, self.myo.suggest_breaks);
+        check(
+            config.myo.fatigue_threshold_drop >= 0f32 && config.myo.fatigue_threshold_drop <= 1f32,
+            "myo.fatigue_threshold_drop",
+            "must be a fraction from 0 to 1",
+        )?;
+        check(
+            !config.myo.left_pipeline.is_empty(),
+            "myo.left_pipeline",
+            "needs at least one stage",
+        )?;
+        check(
+            !config.myo.right_pipeline.is_empty(),
+            "myo.right_pipeline",
+            "needs at least one stage",
+        )?;
+
+        let outputs = self.outputs;
+        set(&mut config.wiring.i2c_bus, outputs.i2c_bus);
+        set(&mut config.wiring.spi_clock_speed, outputs.spi_clock_speed);
+        check(
+            config.wiring.spi_clock_speed > 0,
+            "outputs.spi_clock_speed",
+            "must be more than 0",
+        )?;
+        if let Some(buttons) = outputs.buttons {
+            config.wiring.buttons = buttons.into_iter().map(ButtonEntry::into_config).collect();
+        }
+        if let Some(analogs) = outputs.analog {
+            config.wiring.analog_outputs =
+                analogs.into_iter().map(AnalogEntry::into_config).collect();
+        }
+        check_wiring(&config.wiring)?;
+
+        let scanning = &mut config.collector.scanning;
+        // The default outputs may have been renamed or left out, so they're checked too
+        set(&mut scanning.outputs, self.scanning.outputs);
+        for (i, name) in scanning.outputs.iter().enumerate() {
+            check_output(&config.wiring, name, &format!("scanning.outputs[{}]", i))?;
+        }
+        set(&mut scanning.method, self.scanning.method);
+        set(&mut scanning.interval, ms(self.scanning.interval_ms));
+        if self.scanning.auto_repeat_ms.is_some() {
+            scanning.auto_repeat = ms(self.scanning.auto_repeat_ms);
+        }
+        check(
+            scanning.interval > Duration::default(),
+            "scanning.interval_ms",
+            "must be more than 0",
+        )?;
+        check(
+            scanning.auto_repeat != Some(Duration::default()),
+            "scanning.auto_repeat_ms",
+            "must be more than 0",
+        )?;
+
+        self.pointer.apply(&mut config.collector)?;
+        self.morse.apply(&mut config.collector)?;
+
+        if let Some(entries) = self.mappings {
+            let mut mappings = Vec::with_capacity(entries.len());
+            for (i, entry) in entries.into_iter().enumerate() {
+                mappings.push(entry.into_config(&config.wiring, &format!("mappings[{}]", i))?);
+            }
+            config.collector.mappings = mappings;
+        } else {
+            for (i, mapping) in config.collector.mappings.iter().enumerate() {
+                let key = format!("mappings[{}].output", i);
+                check_output(&config.wiring, &mapping.output, &key)?;
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+impl PointerSection {
+    fn apply(self, collector: &mut CollectorConfig) -> Result<()> {
+        let pointer = &mut collector.pointer;
+        set(
+            &mut pointer.left,
+            self.left.map(PointerActionEntry::into_config),
+        );
+        set(
+            &mut pointer.right,
+            self.right.map(PointerActionEntry::into_config),
+        );
+        set(&mut pointer.min_speed, self.min_speed);
+        set(&mut pointer.max_speed, self.max_speed);
+        set(&mut pointer.acceleration, ms(self.acceleration_ms));
+        set(&mut pointer.acceleration_curve, self.acceleration_curve);
+        set(&mut pointer.speed_source, self.speed_source);
+        set(&mut pointer.emg_full_speed, self.emg_full_speed);
+        if self.dwell_ms.is_some() {
+            pointer.dwell = ms(self.dwell_ms);
+        }
+        set(&mut pointer.sweep_period, ms(self.sweep_period_ms));
+        check(
+            pointer.min_speed >= 0f64,
+            "pointer.min_speed",
+            "must not be negative",
+        )?;
+        check(
+            pointer.max_speed >= pointer.min_speed,
+            "pointer.max_speed",
+            "must not be below `min_speed`",
+        )?;
+        check(
+            pointer.emg_full_speed > 0,
+            "pointer.emg_full_speed",
+            "must be more than 0",
+        )?;
+        check(
+            pointer.dwell != Some(Duration::default()),
+            "pointer.dwell_ms",
+            "must be more than 0",
+        )
+    }
+}
+
+impl PointerActionEntry {
+    fn into_config(self) -> PointerAction {
+        match self {
+            PointerActionEntry::Move { x, y } => PointerAction::Move { x, y },
+            PointerActionEntry::Sweep => PointerAction::Sweep,
+            PointerActionEntry::Click { button } => PointerAction::Click(button),
+            PointerActionEntry::Nothing => PointerAction::Nothing,
+        }
+    }
+}
+
+impl MorseSection {
+    fn apply(self, collector: &mut CollectorConfig) -> Result<()> {
+        set(&mut collector.morse_side, self.side);
+        check(
+            collector.morse_side != Side::Analog,
+            "morse.side",
+            "the myo channels are left and right",
+        )?;
+        let morse = &mut collector.morse;
+        set(&mut morse.unit, ms(self.unit_ms));
+        set(&mut morse.min_unit, ms(self.min_unit_ms));
+        set(&mut morse.max_unit, ms(self.max_unit_ms));
+        set(&mut morse.adaptation, self.adaptation);
+        set(&mut morse.min_flex, ms(self.min_flex_ms));
+        set(&mut morse.letter_gap, self.letter_gap);
+        set(&mut morse.word_gap, self.word_gap);
+        check(
+            morse.min_unit > Duration::default(),
+            "morse.min_unit_ms",
+            "must be more than 0",
+        )?;
+        check(
+            morse.max_unit >= morse.min_unit,
+            "morse.max_unit_ms",
+            "must not be below `min_unit_ms`",
+        )?;
+        check(
+            morse.unit >= morse.min_unit && morse.unit <= morse.max_unit,
+            "morse.unit_ms",
+            "must be from `min_unit_ms` to `max_unit_ms`",
+        )?;
+        check(
+            morse.adaptation >= 0f64 && morse.adaptation <= 1f64,
+            "morse.adaptation",
+            "must be a fraction from 0 to 1",
+        )?;
+        check(
+            morse.letter_gap > 0f64,
+            "morse.letter_gap",
+            "must be more than 0",
+        )?;
+        check(
+            morse.word_gap > morse.letter_gap,
+            "morse.word_gap",
+            "must be above `letter_gap`",
+        )
+    }
+}
+
+impl ButtonEntry {
+    fn into_config(self) -> ButtonConfig {
+        let mut timing = PressTiming::XAC;
+        set(&mut timing.min_press, ms(self.min_press_ms));
+        set(&mut timing.min_release, ms(self.min_release_ms));
+        set(&mut timing.min_period, ms(self.min_period_ms));
+        ButtonConfig {
+            name: self.name,
+            pin: self.pin,
+            active_level: self.active_level.unwrap_or(ActiveLevel::Low),
+            jack: self.jack,
+            timing,
+        }
+    }
+}
+
+impl AnalogEntry {
+    fn into_config(self) -> AnalogConfig {
+        AnalogConfig {
+            name: self.name,
+            device: self.device.unwrap_or(AnalogDevice::Probe),
+            rest: self.rest.unwrap_or(0f64),
+        }
+    }
+}
+
+/// Checks that every output has a name of its own, and every button a usable pin of its
+/// own.
+fn check_wiring(wiring: &Wiring) -> Result<()> {
+    let mut names = vec![];
+    let mut pins = vec![];
+    for (i, button) in wiring.buttons.iter().enumerate() {
+        let key = format!("outputs.buttons[{}]", i);
+        check(
+            !button.name.is_empty(),
+            &format!("{}.name", key),
+            "must not be empty",
+        )?;
+        check(
+            !names.contains(&button.name.as_str()),
+            &format!("{}.name", key),
+            "another output has the same name",
+        )?;
+        names.push(button.name.as_str());
+        check(
+            button.pin <= MAX_PIN,
+            &format!("{}.pin", key),
+            "BCM GPIO numbers go up to 27",
+        )?;
+        check(
+            !RESERVED_PINS.contains(&button.pin),
+            &format!("{}.pin", key),
+            "GPIO 2 and 3 are the I2C bus and 7 to 11 are SPI",
+        )?;
+        check(
+            !pins.contains(&button.pin),
+            &format!("{}.pin", key),
+            "another button uses the same pin",
+        )?;
+        pins.push(button.pin);
+    }
+    for (i, analog) in wiring.analog_outputs.iter().enumerate() {
+        let key = format!("outputs.analog[{}]", i);
+        check(
+            !analog.name.is_empty(),
+            &format!("{}.name", key),
+            "must not be empty",
+        )?;
+        check(
+            !names.contains(&analog.name.as_str()),
+            &format!("{}.name", key),
+            "another output has the same name",
+        )?;
+        names.push(analog.name.as_str());
+        check(
+            analog.rest >= 0f64 && analog.rest <= 100f64,
+            &format!("{}.rest", key),
+            "must be from 0 to 100",
+        )?;
+    }
+    Ok(())
+}
+
+impl MappingEntry {
+    fn into_config(self, wiring: &Wiring, key: &str) -> Result<Mapping> {
+        match self.input {
+            Input::Flexed(Side::Analog) | Input::Level(Side::Analog) => {
+                return Err(WfpiError::InvalidConfig {
+                    key: format!("{}.input", key),
+                    reason: "the myo channels are left and right; the ADC's analog channel is \
+                             input \"analog\""
+                        .to_string(),
+                })
+            }
+            _ => (),
+        }
+        let mut transforms = Vec::with_capacity(self.transforms.len());
+        for (i, transform) in self.transforms.into_iter().enumerate() {
+            transforms.push(transform.into_config(&format!("{}.transforms[{}]", key, i))?);
+        }
+        check_output(wiring, &self.output, &format!("{}.output", key))?;
+        Ok(Mapping {
+            input: self.input,
+            transforms,
+            output: self.output,
+        })
+    }
+}
+
+impl TransformEntry {
+    fn into_config(self, key: &str) -> Result<Transform> {
+        let positive = |value: u64, field: &str| {
+            check(
+                value > 0,
+                &format!("{}.{}", key, field),
+                "must be more than 0",
+            )
+        };
+        Ok(match self {
+            TransformEntry::Threshold { on, off } => {
+                let off = off.unwrap_or(on);
+                check(off <= on, &format!("{}.off", key), "must not be above `on`")?;
+                Transform::Threshold { on, off }
+            }
+            TransformEntry::Invert => Transform::Invert,
+            TransformEntry::Scale {
+                input_min,
+                input_max,
+                output_min,
+                output_max,
+            } => Transform::Scale {
+                input_min,
+                input_max,
+                output_min: output_min.unwrap_or(0f64),
+                output_max: output_max.unwrap_or(100f64),
+            },
+            TransformEntry::Curve { curve } => Transform::Curve(curve),
+            TransformEntry::Latch => Transform::Latch,
+            TransformEntry::Momentary => Transform::Mode(ButtonMode::Momentary),
+            TransformEntry::Toggle => Transform::Mode(ButtonMode::Toggle),
+            TransformEntry::Turbo { interval_ms } => {
+                positive(interval_ms, "interval_ms")?;
+                Transform::Mode(ButtonMode::Turbo {
+                    interval: Duration::from_millis(interval_ms),
+                })
+            }
+            TransformEntry::Pulse { length_ms } => {
+                positive(length_ms, "length_ms")?;
+                Transform::Mode(ButtonMode::Pulse {
+                    length: Duration::from_millis(length_ms),
+                })
+            }
+            TransformEntry::Sticky { timeout_ms } => {
+                positive(timeout_ms, "timeout_ms")?;
+                Transform::Mode(ButtonMode::Sticky {
+                    timeout: Duration::from_millis(timeout_ms),
+                })
+            }
+            TransformEntry::Shape {
+                input_min,
+                input_max,
+                deadzone,
+                curve,
+                output_min,
+                output_max,
+                smoothing_ms,
+                slew_rate,
+            } => {
+                let mut shaping = Shaping::IDENTITY;
+                set(&mut shaping.input_min, input_min);
+                set(&mut shaping.input_max, input_max);
+                set(&mut shaping.deadzone, deadzone);
+                set(&mut shaping.curve, curve);
+                set(&mut shaping.output_min, output_min);
+                set(&mut shaping.output_max, output_max);
+                shaping.smoothing = ms(smoothing_ms);
+                shaping.slew_rate = slew_rate;
+                check(
+                    shaping.input_min < shaping.input_max,
+                    &format!("{}.input_max", key),
+                    "must be above `input_min`",
+                )?;
+                check(
+                    shaping.deadzone >= 0f64 && shaping.deadzone < 1f64,
+                    &format!("{}.deadzone", key),
+                    "must be a fraction from 0 up to 1",
+                )?;
+                for &(value, field) in &[
+                    (shaping.output_min, "output_min"),
+                    (shaping.output_max, "output_max"),
+                ] {
+                    check(
+                        value >= 0f64 && value <= 100f64,
+                        &format!("{}.{}", key, field),
+                        "must be from 0 to 100",
+                    )?;
+                }
+                if let Some(smoothing_ms) = smoothing_ms {
+                    positive(smoothing_ms, "smoothing_ms")?;
+                }
+                check(
+                    slew_rate.map_or(true, |rate| rate > 0f64),
+                    &format!("{}.slew_rate", key),
+                    "must be more than 0",
+                )?;
+                Transform::Shape(shaping)
+            }
+        })
+    }
+}
@@ -7,33 +7,72 @@
 //! This doesn't occur if an interrupt happens (ie Ctrl-C) unless a crate like `simple-signal` is used
 //! to intercept these signals.
 
-use crate::Result;
+use crate::error::*;
 
+use embedded_hal::serial;
 use rppal::uart::{Parity, Uart};
+use serde::Deserialize;
 use std::time::Duration;
 use std::time::Instant;
 
-const BAUDRATE: u32 = 57_600;
+/// The baud rate the Mindwave's dongle talks at, unless configured otherwise
+pub const BAUDRATE: u32 = 57_600;
+/// How long to wait before looking for the rest of a packet again: about a byte at 57600 baud
+const BYTE_WAIT: Duration = Duration::from_micros(200);
+
+/// The EEG frequency bands the Mindwave reports the power of, in the order it sends them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EegBand {
+    Delta = 0,
+    Theta = 1,
+    LowAlpha = 2,
+    HighAlpha = 3,
+    LowBeta = 4,
+    HighBeta = 5,
+    LowGamma = 6,
+    MidGamma = 7,
+}
+
+impl EegBand {
+    pub const ALL: [EegBand; 8] = [
+        EegBand::Delta,
+        EegBand::Theta,
+        EegBand::LowAlpha,
+        EegBand::HighAlpha,
+        EegBand::LowBeta,
+        EegBand::HighBeta,
+        EegBand::LowGamma,
+        EegBand::MidGamma,
+    ];
+}
 
-pub struct Mindwave {
+pub struct Mindwave<S = Uart> {
     debug: bool,
     new_packet: bool,
     payload_data: [u8; 64],
     poor_quality: u8,
     attention: u8,
     meditation: u8,
+    /// The power in each `EegBand`, if the last packet had it
+    eeg_power: Option<[u32; 8]>,
     last_received_packet: Instant,
     timeout: Duration,
 
-    uart: Uart,
+    serial: S,
 }
 
 impl Mindwave {
     /// Initialize the Mindwave interface and opens a serial port at 57600 bauds.
     pub fn init() -> Result<Self> {
+        Self::open(BAUDRATE)
+    }
+
+    /// Initialize the Mindwave interface and opens a serial port at `baud_rate`.
+    pub fn open(baud_rate: u32) -> Result<Self> {
         // Connect to the primary UART and configure it according to the Arduino defaults:
         // 8 data bits, no parity, 1 stop bit (https://www.arduino.cc/reference/en/language/functions/communication/serial/begin/)
-        let mut uart = Uart::new(BAUDRATE, Parity::None, 8, 1)?;
+        let mut uart = Uart::new(baud_rate, Parity::None, 8, 1)?;
 
         // Flush the input
         let input_len = uart.input_len()?;
@@ -42,18 +81,33 @@ impl Mindwave {
             uart.read(&mut buffer)?;
         }
 
-        Ok(Self {
+        // Reads through embedded-hal return `WouldBlock` instead of waiting for data
+        uart.set_read_mode(0, Duration::default())?;
+
+        Ok(Self::new(uart))
+    }
+}
+
+impl<S, E> Mindwave<S>
+where
+    S: serial::Read<u8, Error = E>,
+    WfpiError: From<E>,
+{
+    /// Creates a Mindwave interface on an already configured 57600 baud serial port.
+    pub fn new(serial: S) -> Self {
+        Self {
             debug: false,
             new_packet: false,
             payload_data: [0; 64],
             poor_quality: 250,
             attention: 0,
             meditation: 0,
+            eeg_power: None,
             last_received_packet: Instant::now(),
             timeout: Duration::from_secs(5),
 
-            uart,
-        })
+            serial,
+        }
     }
 
     /// Listens for new brainwave data and parses it.
@@ -94,6 +148,7 @@ impl Mindwave {
         self.poor_quality = 200;
         self.attention = 0;
         self.meditation = 0;
+        self.eeg_power = None;
 
         let mut i = 0;
         while i < (payload_length as usize) {
@@ -115,6 +170,17 @@ impl Mindwave {
                     i += 3;
                 }
                 0x83 => {
+                    // A length byte, then a 3-byte big-endian value for each band
+                    if i + 26 <= payload_length as usize {
+                        let mut power = [0u32; 8];
+                        let values = &self.payload_data[i + 2..i + 26];
+                        for (band, bytes) in power.iter_mut().zip(values.chunks(3)) {
+                            *band = u32::from(bytes[0]) << 16
+                                | u32::from(bytes[1]) << 8
+                                | u32::from(bytes[2]);
+                        }
+                        self.eeg_power = Some(power);
+                    }
                     i += 25;
                 }
                 _ => {}
@@ -185,6 +251,13 @@ impl Mindwave {
         self.meditation
     }
 
+    /// Returns the power in each `EegBand` from the last packet, if it had them. The values
+    /// have no units or fixed range.
+    #[inline]
+    pub fn get_eeg_power(&self) -> Option<[u32; 8]> {
+        self.eeg_power
+    }
+
     /// Returns a number from 0 to 200 with the quality of the signal. Quality goes from 0 (good quality) to 200 (bad).
     #[inline]
     pub fn get_poor_quality(&self) -> u8 {
@@ -200,26 +273,23 @@ impl Mindwave {
     /// Read data from serial UART (non-blocking).
     /// If no data is available, returns 0
     fn read_first_byte(&mut self) -> Result<u8> {
-        // Set the UART to read in non-blocking mode (0 minimum length, 0 timeout)
-        self.uart.set_read_mode(0, Duration::default())?;
-
-        let mut buffer = [0u8; 1];
-        if self.uart.read(&mut buffer)? > 0 {
-            Ok(buffer[0])
-        } else {
-            Ok(0)
+        match self.serial.read() {
+            Ok(byte) => Ok(byte),
+            Err(nb::Error::WouldBlock) => Ok(0),
+            Err(nb::Error::Other(err)) => Err(err.into()),
         }
     }
 
     /// Read data from serial UART (blocking)
     fn read_one_byte(&mut self) -> Result<u8> {
-        self.uart.set_read_mode(1, Duration::default())?; // TODO: determine appropriate timeout?
-        let mut buffer = [0u8; 1];
-        if self.uart.read(&mut buffer)? > 0 {
-            Ok(buffer[0])
-        } else {
-            println!("!!!ERROR!!! read_one_byte did not read a byte");
-            Ok(0)
+        // TODO: determine appropriate timeout?
+        loop {
+            match self.serial.read() {
+                Ok(byte) => return Ok(byte),
+                // The port doesn't wait for data itself, so wait here without spinning
+                Err(nb::Error::WouldBlock) => std::thread::sleep(BYTE_WAIT),
+                Err(nb::Error::Other(err)) => return Err(err.into()),
+            }
         }
     }
 }
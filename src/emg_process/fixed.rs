@@ -0,0 +1,119 @@
+//! `Stage` adapters for the fixed-point filters in `wfpi-fixed`, so that they can be run
+//! side by side with the floating point versions before moving to hardware without an FPU.
+
+use crate::myo::{NotchFrequency, SampleFrequency};
+
+use super::Stage;
+
+/// One ADC count in Q15
+const COUNTS_PER_Q15: f64 = 64f64;
+/// Samples the detector holds a flex for after the envelope drops to zero, plus one
+const DETECTOR_LATENCY: f64 = 76f64;
+
+fn fixed_sample_freq(sample_freq: SampleFrequency) -> wfpi_fixed::SampleFrequency {
+    match sample_freq {
+        SampleFrequency::Freq500Hz => wfpi_fixed::SampleFrequency::Freq500Hz,
+        SampleFrequency::Freq1000Hz => wfpi_fixed::SampleFrequency::Freq1000Hz,
+    }
+}
+
+fn fixed_notch_freq(notch_freq: NotchFrequency) -> wfpi_fixed::NotchFrequency {
+    match notch_freq {
+        NotchFrequency::Freq50Hz => wfpi_fixed::NotchFrequency::Freq50Hz,
+        NotchFrequency::Freq60Hz => wfpi_fixed::NotchFrequency::Freq60Hz,
+    }
+}
+
+fn to_adc(sample: f64) -> u16 {
+    sample.max(0f64).min(1023f64) as u16
+}
+
+/// Fixed-point anti-hum, low-pass and high-pass filters. Takes raw ADC readings and outputs
+/// filtered ADC counts, like `EMGFilters`.
+pub struct FixedEmgFilters {
+    filters: wfpi_fixed::EmgFilters,
+}
+
+impl FixedEmgFilters {
+    pub fn new(
+        sample_freq: SampleFrequency,
+        notch_freq: NotchFrequency,
+        enable_notch_filter: bool,
+        enable_lowpass_filter: bool,
+        enable_highpass_filter: bool,
+    ) -> Self {
+        FixedEmgFilters {
+            filters: wfpi_fixed::EmgFilters::new(
+                fixed_sample_freq(sample_freq),
+                fixed_notch_freq(notch_freq),
+                enable_notch_filter,
+                enable_lowpass_filter,
+                enable_highpass_filter,
+            ),
+        }
+    }
+}
+
+impl Stage for FixedEmgFilters {
+    fn process(&mut self, sample: f64) -> f64 {
+        f64::from(self.filters.update_adc(to_adc(sample))) / COUNTS_PER_Q15
+    }
+
+    fn reset(&mut self) {
+        self.filters.reset();
+    }
+
+    fn set_notch_frequency(&mut self, notch_freq: NotchFrequency) {
+        self.filters.set_notch_frequency(fixed_notch_freq(notch_freq));
+    }
+}
+
+/// Fixed-point filters and flex detector, behaving like `Flappy`: while flexed, outputs the
+/// envelope in squared ADC counts (never less than the threshold); zero otherwise.
+pub struct FixedFlappy {
+    filters: FixedEmgFilters,
+    detector: wfpi_fixed::EnvelopeDetector,
+    threshold: u32,
+}
+
+impl FixedFlappy {
+    pub fn new(sample_freq: SampleFrequency, notch_freq: NotchFrequency, threshold: u32) -> Self {
+        FixedFlappy {
+            filters: FixedEmgFilters::new(sample_freq, notch_freq, true, true, true),
+            detector: wfpi_fixed::EnvelopeDetector::from_adc_threshold(threshold),
+            threshold,
+        }
+    }
+}
+
+impl Stage for FixedFlappy {
+    fn process(&mut self, sample: f64) -> f64 {
+        let filtered = self.filters.filters.update_adc(to_adc(sample));
+        let (flexed, envelope) = self.detector.update(filtered);
+        if flexed {
+            // The envelope is Q30; one squared ADC count is 1 << 12
+            let envelope = f64::from(envelope) / (COUNTS_PER_Q15 * COUNTS_PER_Q15);
+            envelope.max(f64::from(self.threshold)).max(1f64)
+        } else {
+            0f64
+        }
+    }
+
+    fn reset(&mut self) {
+        self.filters.reset();
+        self.detector = wfpi_fixed::EnvelopeDetector::from_adc_threshold(self.threshold);
+    }
+
+    fn latency(&self) -> f64 {
+        DETECTOR_LATENCY
+    }
+
+    fn set_notch_frequency(&mut self, notch_freq: NotchFrequency) {
+        self.filters.set_notch_frequency(notch_freq);
+    }
+
+    fn set_threshold_scale(&mut self, scale: f64) {
+        let threshold = (f64::from(self.threshold) * scale).max(0f64) as u32;
+        self.detector.set_adc_threshold(threshold);
+    }
+}
@@ -1,8 +1,12 @@
+mod fixed;
 mod moving_avg;
 mod peak_to_peak;
+mod stage;
 
+pub use fixed::*;
 pub use moving_avg::*;
 pub use peak_to_peak::*;
+pub use stage::*;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum EmgOptions {
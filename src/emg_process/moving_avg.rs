@@ -1,3 +1,5 @@
+use super::Stage;
+
 pub struct MovingAverage {
     data: Vec<f64>,
     data_sum: f64,
@@ -44,4 +46,24 @@ impl MovingAverage {
     pub fn is_empty(&self) -> bool {
         self.data.is_empty()
     }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.data_sum = 0f64;
+        self.data_avg = 0f64;
+    }
+}
+
+impl Stage for MovingAverage {
+    fn process(&mut self, sample: f64) -> f64 {
+        self.insert(sample)
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+
+    fn latency(&self) -> f64 {
+        (self.max_length as f64 - 1f64) / 2f64
+    }
 }
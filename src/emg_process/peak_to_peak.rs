@@ -1,3 +1,5 @@
+use super::Stage;
+
 pub struct PkPk {
     data: Vec<f64>,
     max_values: Vec<f64>,
@@ -86,6 +88,28 @@ impl PkPk {
     }
 }
 
+impl Stage for PkPk {
+    fn process(&mut self, sample: f64) -> f64 {
+        self.get_pkpk(sample).pkpk
+    }
+
+    fn reset(&mut self) {
+        let cur_max = -100_000f64;
+        let cur_min = 100_000f64;
+        self.data.clear();
+        self.max_values = vec![cur_max];
+        self.min_values = vec![cur_min];
+        self.cur_length = 0;
+        self.cur_max = cur_max;
+        self.cur_min = cur_min;
+    }
+
+    fn latency(&self) -> f64 {
+        // The window spans twice the longest peak gap, so a change reaches its middle after one
+        self.max_pk_gap as f64
+    }
+}
+
 fn get_queue_max_min(queue: &[f64]) -> (f64, f64) {
     let mut max = -100_000f64;
     let mut min = 100_000f64;
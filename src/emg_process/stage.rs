@@ -0,0 +1,276 @@
+//! A common interface for the signal processing steps in `myo` and `emg_process`, so that
+//! a channel's processing can be described as data and assembled into a `Chain`.
+
+use serde::Deserialize;
+
+use crate::myo::{
+    EMGFilters, Filter2nd, Filter4th, FilterType, Flappy, NotchFrequency, SampleFrequency,
+};
+
+use super::{FixedEmgFilters, FixedFlappy, MovingAverage, PkPk};
+
+/// A single step in a signal processing pipeline: one sample in, one sample out.
+pub trait Stage {
+    fn process(&mut self, sample: f64) -> f64;
+
+    /// Returns the stage to the state it was created in.
+    fn reset(&mut self);
+
+    /// Roughly how many samples it takes for a change at the input to show up at the output.
+    /// For IIR filters this is the group delay in the passband.
+    fn latency(&self) -> f64 {
+        0f64
+    }
+
+    /// Retunes any anti-hum filtering to a different power line frequency.
+    fn set_notch_frequency(&mut self, _notch_freq: NotchFrequency) {}
+
+    /// Scales any detection threshold relative to the value it was configured with.
+    fn set_threshold_scale(&mut self, _scale: f64) {}
+}
+
+/// A sequence of stages run one after the other.
+#[derive(Default)]
+pub struct Chain {
+    stages: Vec<Box<dyn Stage>>,
+    outputs: Vec<f64>,
+}
+
+impl Chain {
+    pub fn builder() -> ChainBuilder {
+        ChainBuilder::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// The most recent output of the stage at `index`, for inspecting intermediate results.
+    pub fn output_of(&self, index: usize) -> Option<f64> {
+        self.outputs.get(index).cloned()
+    }
+}
+
+impl Stage for Chain {
+    fn process(&mut self, sample: f64) -> f64 {
+        let mut value = sample;
+        for (stage, output) in self.stages.iter_mut().zip(self.outputs.iter_mut()) {
+            value = stage.process(value);
+            *output = value;
+        }
+        value
+    }
+
+    fn reset(&mut self) {
+        for stage in self.stages.iter_mut() {
+            stage.reset();
+        }
+        for output in self.outputs.iter_mut() {
+            *output = 0f64;
+        }
+    }
+
+    fn latency(&self) -> f64 {
+        self.stages.iter().map(|stage| stage.latency()).sum()
+    }
+
+    fn set_notch_frequency(&mut self, notch_freq: NotchFrequency) {
+        for stage in self.stages.iter_mut() {
+            stage.set_notch_frequency(notch_freq);
+        }
+    }
+
+    fn set_threshold_scale(&mut self, scale: f64) {
+        for stage in self.stages.iter_mut() {
+            stage.set_threshold_scale(scale);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ChainBuilder {
+    stages: Vec<Box<dyn Stage>>,
+}
+
+impl ChainBuilder {
+    /// Appends a stage to the end of the chain.
+    pub fn stage<S: Stage + 'static>(mut self, stage: S) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Appends an already boxed stage to the end of the chain.
+    pub fn boxed(mut self, stage: Box<dyn Stage>) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    /// Appends a stage for each entry of `config`, in order.
+    pub fn config(
+        mut self,
+        config: &[StageConfig],
+        sample_freq: SampleFrequency,
+        notch_freq: NotchFrequency,
+    ) -> Self {
+        for stage in config {
+            self.stages.push(stage.build(sample_freq, notch_freq));
+        }
+        self
+    }
+
+    pub fn build(self) -> Chain {
+        let outputs = vec![0f64; self.stages.len()];
+        Chain {
+            stages: self.stages,
+            outputs,
+        }
+    }
+}
+
+/// A description of a stage, from which the stage itself can be built.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum StageConfig {
+    /// OYMotion's anti-hum, low-pass and high-pass filters in one
+    EmgFilters {
+        notch: bool,
+        lowpass: bool,
+        highpass: bool,
+        harmonics: bool,
+    },
+    /// 4th order anti-hum notch at the power line frequency
+    Notch,
+    /// 2nd order Butterworth low-pass at 150Hz
+    LowPass,
+    /// 2nd order Butterworth high-pass at 20Hz
+    HighPass,
+    MovingAverage { length: usize },
+    /// Peak-to-peak amplitude over a window matched to the given frequency range
+    PeakToPeak {
+        min_frequency: usize,
+        max_frequency: usize,
+    },
+    /// Absolute value
+    Rectify,
+    Square,
+    /// Zero below `level`, unchanged at or above it
+    Threshold { level: f64 },
+    /// OYMotion's filter and flex detector; non-zero exactly while flexed
+    Flappy { threshold: i32, harmonics: bool },
+    /// Fixed-point version of `EmgFilters`, without the harmonic notches
+    FixedEmgFilters {
+        notch: bool,
+        lowpass: bool,
+        highpass: bool,
+    },
+    /// Fixed-point version of `Flappy`, without the harmonic notches
+    FixedFlappy { threshold: u32 },
+}
+
+impl StageConfig {
+    pub fn build(&self, sample_freq: SampleFrequency, notch_freq: NotchFrequency) -> Box<dyn Stage> {
+        let sample_rate = sample_freq as usize;
+        match *self {
+            StageConfig::EmgFilters {
+                notch,
+                lowpass,
+                highpass,
+                harmonics,
+            } => {
+                let mut filters =
+                    EMGFilters::new(sample_freq, notch_freq, notch, lowpass, highpass);
+                filters.set_harmonic_filters(harmonics);
+                Box::new(filters)
+            }
+            StageConfig::Notch => Box::new(Filter4th::new(sample_freq, notch_freq)),
+            StageConfig::LowPass => Box::new(Filter2nd::new(FilterType::LowPass, sample_freq)),
+            StageConfig::HighPass => Box::new(Filter2nd::new(FilterType::HighPass, sample_freq)),
+            StageConfig::MovingAverage { length } => Box::new(MovingAverage::new(length)),
+            StageConfig::PeakToPeak {
+                min_frequency,
+                max_frequency,
+            } => Box::new(PkPk::new(sample_rate, min_frequency, max_frequency)),
+            StageConfig::Rectify => Box::new(Rectify),
+            StageConfig::Square => Box::new(Square),
+            StageConfig::Threshold { level } => Box::new(Threshold::new(level)),
+            StageConfig::Flappy {
+                threshold,
+                harmonics,
+            } => {
+                let mut flappy = Flappy::new(notch_freq, harmonics);
+                flappy.set_threshold(threshold);
+                Box::new(flappy)
+            }
+            StageConfig::FixedEmgFilters {
+                notch,
+                lowpass,
+                highpass,
+            } => Box::new(FixedEmgFilters::new(
+                sample_freq,
+                notch_freq,
+                notch,
+                lowpass,
+                highpass,
+            )),
+            StageConfig::FixedFlappy { threshold } => {
+                Box::new(FixedFlappy::new(sample_freq, notch_freq, threshold))
+            }
+        }
+    }
+}
+
+pub struct Rectify;
+
+impl Stage for Rectify {
+    fn process(&mut self, sample: f64) -> f64 {
+        sample.abs()
+    }
+
+    fn reset(&mut self) {}
+}
+
+pub struct Square;
+
+impl Stage for Square {
+    fn process(&mut self, sample: f64) -> f64 {
+        sample * sample
+    }
+
+    fn reset(&mut self) {}
+}
+
+pub struct Threshold {
+    base_level: f64,
+    level: f64,
+}
+
+impl Threshold {
+    pub fn new(level: f64) -> Self {
+        Threshold {
+            base_level: level,
+            level,
+        }
+    }
+}
+
+impl Stage for Threshold {
+    fn process(&mut self, sample: f64) -> f64 {
+        if sample >= self.level {
+            sample
+        } else {
+            0f64
+        }
+    }
+
+    fn reset(&mut self) {
+        self.level = self.base_level;
+    }
+
+    fn set_threshold_scale(&mut self, scale: f64) {
+        self.level = self.base_level * scale;
+    }
+}
@@ -16,6 +16,18 @@ pub enum WfpiError {
     I2CErr { err: rppal::i2c::Error },
     #[fail(display = "i/o error: {}", err)]
     IoError { err: std::io::Error },
+    #[fail(display = "no output named {}", name)]
+    UnknownOutput { name: String },
+    #[fail(display = "wiper value {} is out of range (0 to {})", value, max)]
+    WiperOutOfRange { value: u8, max: u8 },
+    #[fail(display = "wiper read back as {} after writing {}", actual, expected)]
+    WiperMismatch { expected: u8, actual: u8 },
+    #[fail(display = "invalid calibration: {}", reason)]
+    InvalidCalibration { reason: String },
+    #[fail(display = "invalid configuration: {}", err)]
+    ConfigSyntax { err: toml::de::Error },
+    #[fail(display = "invalid configuration at {}: {}", key, reason)]
+    InvalidConfig { key: String, reason: String },
     #[fail(display = "generic error: {}", err)]
     GenericError { err: failure::Error },
 }
@@ -62,6 +74,18 @@ impl From<std::io::Error> for WfpiError {
     }
 }
 
+impl From<toml::de::Error> for WfpiError {
+    fn from(err: toml::de::Error) -> WfpiError {
+        WfpiError::ConfigSyntax { err }
+    }
+}
+
+impl From<std::convert::Infallible> for WfpiError {
+    fn from(err: std::convert::Infallible) -> WfpiError {
+        match err {}
+    }
+}
+
 impl From<failure::Error> for WfpiError {
     fn from(err: failure::Error) -> WfpiError {
         WfpiError::GenericError { err }
@@ -0,0 +1,40 @@
+#![warn(clippy::all)]
+#![allow(dead_code)]
+
+pub use error::*;
+
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    pub static ref LOGS: Mutex<Vec<String>> = Mutex::new(vec![]);
+}
+
+pub fn _log(str: String) {
+    LOGS.lock().unwrap().push(str);
+}
+
+#[macro_export]
+macro_rules! log {
+    ($($arg:tt)*) => ({
+        $crate::_log(format!($($arg)*));
+    })
+}
+
+pub mod button_mode;
+pub mod clock;
+pub mod collector;
+pub mod config;
+pub mod eeg;
+pub mod emg_process;
+pub mod error;
+pub mod mapping;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod morse;
+pub mod myo;
+pub mod pointer;
+pub mod scanning;
+pub mod scheduler;
+pub mod shaping;
+pub mod springboard;
+pub mod watchdog;
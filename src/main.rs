@@ -1,10 +1,10 @@
 #![warn(clippy::all)]
 #![allow(dead_code)]
 
-pub use error::*;
-
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use termion::input::MouseTerminal;
 use termion::raw::IntoRawMode;
@@ -15,32 +15,18 @@ use tui::style::{Color, Modifier, Style};
 use tui::widgets::{Axis, Block, Borders, Chart, Dataset, Marker, Paragraph, Text, Widget};
 use tui::Terminal;
 use itertools::Itertools;
+use embedded_hal::digital::v2::OutputPin;
+use single_value_channel::Updater;
 
 // The simple-signal crate is used to handle incoming signals
 use simple_signal::{self, Signal};
 
 use rppal::system::DeviceInfo;
 
-
-lazy_static::lazy_static! {
-    static ref LOGS: Mutex<Vec<String>> = Mutex::new(vec![]);
-}
-
-pub fn _log(str: String) {
-    LOGS.lock().unwrap().push(str);
-}
-
-macro_rules! log {
-    ($($arg:tt)*) => ({
-        $crate::_log(format!($($arg)*));
-    })
-}
-
-mod eeg;
-mod emg_process;
-mod error;
-mod myo;
-mod springboard;
+use wfpi::collector::{Collector, DeviceSignal, UiState};
+use wfpi::config::{self, Config, FileWatcher};
+use wfpi::springboard::uinput::{VirtualDevice, VirtualKeyboard, VirtualMouse};
+use wfpi::{eeg, log, myo, springboard, Result, WfpiError, LOGS};
 
 mod event {
     use std::io;
@@ -129,10 +115,22 @@ mod event {
     }
 }
 
-enum DeviceSignal {
-    Eeg(u8, u8, u8),
-    Myo1(bool, i32),
-    Myo2(bool, i32),
+/// Name of the thread that owns the XAC's outputs
+const COLLECTOR_THREAD: &str = "collector";
+/// How long a panicking thread waits for the collector to release the outputs
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(1);
+/// The analog output `wfpi calibrate` and `wfpi persist-rest` act on when none is named
+const DEFAULT_ANALOG_OUTPUT: &str = "trigger";
+/// How often the configuration file is checked for changes
+const CONFIG_POLL: Duration = Duration::from_secs(1);
+
+/// Where the configuration is reloaded from, and how a reload reaches the threads
+struct Reload {
+    path: std::path::PathBuf,
+    /// Set on SIGHUP
+    requested: Arc<AtomicBool>,
+    /// The pipelines the myo thread is to switch to
+    myo: Arc<Mutex<Option<myo::MyoConfig>>>,
 }
 
 fn fmin(v1: f64, v2: f64) -> f64 {
@@ -151,8 +149,248 @@ fn fmax(v1: f64, v2: f64) -> f64 {
     }
 }
 
+fn fatigue_color(level: myo::FatigueLevel) -> Color {
+    match level {
+        myo::FatigueLevel::Calibrating => Color::Gray,
+        myo::FatigueLevel::Fresh => Color::Green,
+        myo::FatigueLevel::Tiring => Color::Yellow,
+        myo::FatigueLevel::Fatigued => Color::Red,
+    }
+}
+
+fn quality_color(quality: myo::SignalQuality) -> Color {
+    match quality {
+        myo::SignalQuality::Unknown => Color::Gray,
+        myo::SignalQuality::Good => Color::Green,
+        myo::SignalQuality::Saturated
+        | myo::SignalQuality::Flatline
+        | myo::SignalQuality::Noisy => Color::Red,
+    }
+}
+
+/// Steps the analog output called `name` through its range, asks what the XAC reports at
+/// each step, and stores the result as the calibration for this board.
+fn calibrate(name: &str, config: &Config) -> Result<()> {
+    let mut output = springboard::Springboard::init_with(&config.wiring)?;
+    for line in LOGS.lock().unwrap().drain(..) {
+        println!("{}", line);
+    }
+
+    let stdin = std::io::stdin();
+    let mut gauge = springboard::calibration::OperatorGauge::new(stdin.lock(), std::io::stdout());
+    let path = output.calibrate(name, &mut gauge)?;
+    println!("Saved calibration to {}", path.display());
+    Ok(())
+}
+
+/// Stores the rest value of the analog output called `name` as the value its device
+/// powers up at, for devices that can.
+fn persist_rest(name: &str, config: &Config) -> Result<()> {
+    let mut output = springboard::Springboard::init_with(&config.wiring)?;
+    for line in LOGS.lock().unwrap().drain(..) {
+        println!("{}", line);
+    }
+
+    if output.persist_rest(name, &mut rppal::hal::Delay::new())? {
+        println!("Stored the rest value of {} in its device", name);
+    } else {
+        println!(
+            "The device behind {} can't store a power-on value; wfpi sets it to rest at startup",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Opens the outputs, trying again every few seconds until they open or wfpi is shut down.
+fn connect<T>(open: impl Fn() -> Result<T>, running: &AtomicBool) -> Option<T> {
+    while running.load(Ordering::SeqCst) {
+        match open() {
+            Ok(output) => return Some(output),
+            Err(e) => {
+                log!("failed to connect to XAC: {}", e);
+                log!("sleeping for 5 seconds...");
+                std::thread::sleep(std::time::Duration::from_secs(5));
+            }
+        }
+    }
+    None
+}
+
+/// Forwards the devices' signals and the override keys to `output` until wfpi is shut
+/// down, then releases the outputs.
+fn collect<P, E>(
+    output: springboard::Springboard<P>,
+    rx: &Receiver<DeviceSignal>,
+    ui: &Updater<UiState>,
+    events: &event::Events,
+    mut config: Config,
+    reload: &Reload,
+    running: &AtomicBool,
+) where
+    P: OutputPin<Error = E>,
+    WfpiError: From<E>,
+{
+    let mut collector = Collector::new(output).with_config(config.collector.clone());
+    let mut watcher = FileWatcher::new(&reload.path);
+    let mut polled = Instant::now();
+    match VirtualMouse::create() {
+        Ok(mouse) => collector = collector.with_pointer(Box::new(mouse)),
+        Err(e) => log!("Pointer mode unavailable, no virtual mouse: {}", e),
+    }
+    match VirtualKeyboard::create() {
+        Ok(keyboard) => collector = collector.with_keyboard(Box::new(keyboard)),
+        Err(e) => log!("Text mode unavailable, no virtual keyboard: {}", e),
+    }
+
+    while running.load(Ordering::SeqCst) {
+        // Time out now and then to notice being shut down while the devices are quiet,
+        // and in time to make any held back press or release
+        let timeout = collector
+            .next_due()
+            .map_or(config.signal_timeout, |due| due.min(config.signal_timeout));
+        if let Ok(data) = rx.recv_timeout(timeout) {
+            collector
+                .handle_signal(data)
+                .unwrap_or_else(|e| log!("Failed to write to XAC, outputs released: {}", e));
+        }
+        collector.tick();
+        // After the tick, so that the scan highlight moves on screen without new data
+        ui.update(collector.ui_state()).expect("failed to send");
+
+        if let Ok(event::Event::Input(input)) = events.next_nonblocking() {
+            if input == termion::event::Key::Char('q') {
+                running.store(false, Ordering::SeqCst);
+            } else {
+                collector.handle_key(input);
+            }
+        }
+
+        // Between iterations, so that a new configuration applies all at once
+        if polled.elapsed() >= CONFIG_POLL {
+            polled = Instant::now();
+            if watcher.changed() {
+                reload.requested.store(true, Ordering::SeqCst);
+            }
+        }
+        if reload.requested.swap(false, Ordering::SeqCst) {
+            reload_config(&mut collector, &mut config, reload);
+        }
+    }
+
+    // Dropping the springboard releases the outputs
+    drop(collector);
+}
+
+/// Reads the configuration file again and switches to it. If the file can't be used,
+/// everything carries on as it was.
+fn reload_config<P, E>(collector: &mut Collector<P>, config: &mut Config, reload: &Reload)
+where
+    P: OutputPin<Error = E>,
+    WfpiError: From<E>,
+{
+    let path = reload.path.display();
+    let new = match Config::load(&reload.path) {
+        Ok(new) => new,
+        Err(e) => return log!("Kept the running configuration, {} is unusable: {}", path, e),
+    };
+    // Checks the mappings against the outputs that are open, before anything changes.
+    // Left alone when unchanged, so that held and latched buttons stay as they are.
+    if new.collector != config.collector {
+        if let Err(e) = collector.reconfigure(new.collector.clone()) {
+            return log!("Kept the running configuration, {} is unusable: {}", path, e);
+        }
+        config.collector = new.collector.clone();
+    }
+    config.signal_timeout = new.signal_timeout;
+    if new.myo.left_pipeline != config.myo.left_pipeline
+        || new.myo.right_pipeline != config.myo.right_pipeline
+    {
+        config.myo.left_pipeline = new.myo.left_pipeline.clone();
+        config.myo.right_pipeline = new.myo.right_pipeline.clone();
+        *reload.myo.lock().unwrap() = Some(config.myo.clone());
+    }
+    for key in config.needs_restart(&new) {
+        log!("{} changed in {}, which takes a restart", key, path);
+    }
+    log!("Configuration reloaded from {}", path);
+}
+
+/// Reads the configuration from `path`, or from `config::DEFAULT_PATH` if there's a file
+/// there, and otherwise uses the defaults. Exits with the reason if the file is unusable.
+fn load_config(path: Option<&str>) -> Config {
+    let (path, required) = match path {
+        Some(path) => (path, true),
+        None => (config::DEFAULT_PATH, false),
+    };
+    if !required && !std::path::Path::new(path).exists() {
+        log!("No {}, using the default configuration", path);
+        return Config::default();
+    }
+    match Config::load(path.as_ref()) {
+        Ok(config) => {
+            log!("Configuration read from {}", path);
+            config
+        }
+        Err(e) => {
+            eprintln!("Can't use the configuration in {}: {}", path, e);
+            std::process::exit(2);
+        }
+    }
+}
+
 pub fn main() -> Result<()> {
-    log!("Running wfpi on a {}.", DeviceInfo::new()?.model());
+    // `wfpi calibrate [output]` and `wfpi persist-rest [output]` set up an analog output
+    // instead of running, and `wfpi virtual [gamepad|keyboard]` runs with a virtual device
+    // in place of the XAC. `--config <path>` reads the configuration from another file, and
+    // `--print-default-config` prints the default one to start from.
+    let mut args: Vec<String> = vec![];
+    let mut config_path = None;
+    let mut arg_iter = std::env::args();
+    while let Some(arg) = arg_iter.next() {
+        match arg.as_str() {
+            "--print-default-config" => {
+                print!("{}", config::DEFAULT_CONFIG);
+                return Ok(());
+            }
+            "--config" => match arg_iter.next() {
+                Some(path) => config_path = Some(path),
+                None => {
+                    eprintln!("--config needs the path of a configuration file");
+                    std::process::exit(2);
+                }
+            },
+            _ => args.push(arg),
+        }
+    }
+    let config = load_config(config_path.as_ref().map(String::as_str));
+    let reload = Reload {
+        path: config_path.unwrap_or_else(|| config::DEFAULT_PATH.to_string()).into(),
+        requested: Arc::new(AtomicBool::new(false)),
+        myo: Arc::new(Mutex::new(None)),
+    };
+
+    let command_output = args.get(2).map_or(DEFAULT_ANALOG_OUTPUT, String::as_str);
+    let virtual_device = match args.get(1).map(String::as_str) {
+        Some("calibrate") => return calibrate(command_output, &config),
+        Some("persist-rest") => return persist_rest(command_output, &config),
+        Some("virtual") => match args.get(2).map(String::as_str) {
+            None | Some("gamepad") => Some(VirtualDevice::Gamepad),
+            Some("keyboard") => Some(VirtualDevice::Keyboard),
+            Some(other) => {
+                eprintln!("Unknown virtual device {}: use gamepad or keyboard", other);
+                std::process::exit(2);
+            }
+        },
+        _ => None,
+    };
+
+    match DeviceInfo::new() {
+        Ok(info) => log!("Running wfpi on a {}.", info.model()),
+        // Only the XAC needs a Pi; a virtual device works on any Linux machine
+        Err(e) if virtual_device.is_some() => log!("Running wfpi off a Raspberry Pi: {}", e),
+        Err(e) => return Err(e.into()),
+    }
 
     let stdout = std::io::stdout().into_raw_mode()?;
     let stdout = MouseTerminal::from(stdout);
@@ -161,18 +399,50 @@ pub fn main() -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
     terminal.hide_cursor()?;
 
-    let events = event::Events::new();
+    let events = event::Events::with_config(event::Config {
+        tick_rate: config.tick_rate,
+        ..event::Config::default()
+    });
 
     let running = Arc::new(AtomicBool::new(true));
 
     // When a SIGINT (Ctrl-C) or SIGTERM signal is caught, atomically set `running` to false.
-    simple_signal::set_handler(&[Signal::Int, Signal::Term], {
+    // A SIGHUP asks for the configuration to be read again.
+    simple_signal::set_handler(&[Signal::Int, Signal::Term, Signal::Hup], {
         let running = running.clone();
-        move |_| {
-            running.store(false, Ordering::SeqCst);
+        let requested = reload.requested.clone();
+        move |signals| {
+            if signals.contains(&Signal::Hup) {
+                requested.store(true, Ordering::SeqCst);
+            }
+            if signals.contains(&Signal::Int) || signals.contains(&Signal::Term) {
+                running.store(false, Ordering::SeqCst);
+            }
         }
     });
 
+    // Set once the collector thread has put the XAC's outputs into their safe state
+    let released = Arc::new(AtomicBool::new(false));
+
+    // A panic on any thread shuts everything down. The collector releases the outputs as
+    // it unwinds; for panics elsewhere, give it a moment to notice and do so before the
+    // process goes down.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook({
+        let running = running.clone();
+        let released = released.clone();
+        Box::new(move |info| {
+            default_hook(info);
+            running.store(false, Ordering::SeqCst);
+            if std::thread::current().name() != Some(COLLECTOR_THREAD) {
+                let deadline = Instant::now() + RELEASE_TIMEOUT;
+                while !released.load(Ordering::SeqCst) && Instant::now() < deadline {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+            }
+        })
+    });
+
     // Create a multi-producer, single-consumer FIFO queue for signals
     // This will collect data from the EEG and myo devices on separate threads,
     // and then collapse them down into a single thread for consumption.
@@ -182,8 +452,13 @@ pub fn main() -> Result<()> {
     // the atomic `running` variable. This running variable must be used to shut down
     let eeg_tx = tx.clone();
     let eeg_run = running.clone();
+    let eeg_baud_rate = config.eeg_baud_rate;
     let eeg_join = std::thread::spawn(move || {
-        let mut mindwave = eeg::Mindwave::init().expect("failed to initialize mindwave");
+        // Without the headset, e.g. off a Pi, the other outputs carry on without it
+        let mut mindwave = match eeg::Mindwave::open(eeg_baud_rate) {
+            Ok(mindwave) => mindwave,
+            Err(err) => return log!("failed to initialize mindwave: {}", err),
+        };
         log!("Initialized mindwave");
         while eeg_run.load(Ordering::SeqCst) {
             if let Err(err) = mindwave.update() {
@@ -201,16 +476,31 @@ pub fn main() -> Result<()> {
                     log!("failed to send data"); // This happens if the receiver has already hung up. Not really an error, but we don't want to keep shouting at a hung-up receiver, so we just break the loop.
                     break;
                 }
+                if let Some(power) = mindwave.get_eeg_power() {
+                    if let Err(_err) = eeg_tx.send(DeviceSignal::EegPower(power)) {
+                        log!("failed to send data");
+                        break;
+                    }
+                }
             }
         }
     });
 
     let myo_tx = tx.clone();
     let myo_run = running.clone();
+    let myo_config = config.myo.clone();
+    let myo_reload = reload.myo.clone();
     let myo_join = std::thread::spawn(move || {
-        let mut myo_parser = myo::MyoParser::new().expect("MYO parser failed to initialize");
+        let mut myo_parser = match myo::MyoParser::with_config(&myo_config) {
+            Ok(myo_parser) => myo_parser,
+            Err(err) => return log!("MYO parser failed to initialize: {}", err),
+        };
         log!("Initialized myo");
         while myo_run.load(Ordering::SeqCst) {
+            if let Some(config) = myo_reload.lock().unwrap().take() {
+                myo_parser.set_pipelines(&config.left_pipeline, &config.right_pipeline);
+                log!("Myo pipelines reloaded");
+            }
             match myo_parser.update() {
                 Err(err) => {
                     log!("failed to update myo: {}", err);
@@ -229,174 +519,89 @@ pub fn main() -> Result<()> {
                         log!("failed to send data");
                         break;
                     }
+                    if let Err(_err) = myo_tx.send(DeviceSignal::Analog(myo_parser.get_analog())) {
+                        log!("failed to send data");
+                        break;
+                    }
+                    if let Some(report) = myo_parser.take_fatigue_report(myo::Side::Left) {
+                        if let Err(_err) =
+                            myo_tx.send(DeviceSignal::Fatigue(myo::Side::Left, report))
+                        {
+                            log!("failed to send data");
+                            break;
+                        }
+                    }
+                    if let Some(report) = myo_parser.take_fatigue_report(myo::Side::Right) {
+                        if let Err(_err) =
+                            myo_tx.send(DeviceSignal::Fatigue(myo::Side::Right, report))
+                        {
+                            log!("failed to send data");
+                            break;
+                        }
+                    }
+                    if let Some(report) = myo_parser.take_quality_report(myo::Side::Left) {
+                        if let Err(_err) =
+                            myo_tx.send(DeviceSignal::Quality(myo::Side::Left, report))
+                        {
+                            log!("failed to send data");
+                            break;
+                        }
+                    }
+                    if let Some(report) = myo_parser.take_quality_report(myo::Side::Right) {
+                        if let Err(_err) =
+                            myo_tx.send(DeviceSignal::Quality(myo::Side::Right, report))
+                        {
+                            log!("failed to send data");
+                            break;
+                        }
+                    }
                 }
                 Ok(false) => (), // no new data
             }
         }
     });
 
-    let (mut rx_o, tx_o) = single_value_channel::channel_starting_with((
-        vec![],
-        vec![],
-        vec![],
-        (false, false, 0f64),
-        0f64,
-        false,
-    ));
+    let (mut rx_o, tx_o) = single_value_channel::channel_starting_with(UiState::default());
 
     let collector_running = running.clone();
-    let collector_join = std::thread::spawn(move || {
-        let mut output = {
-            let mut res = springboard::Springboard::init();
-            while res.is_err() && collector_running.load(Ordering::SeqCst) {
-                log!("failed to connect to XAC: {}", res.err().unwrap());
-                log!("sleeping for 5 seconds...");
-                std::thread::sleep(std::time::Duration::from_secs(5));
-                res = springboard::Springboard::init();
-            }
-            res.unwrap()
-        };
-
-        let mut last_data = [0; 3];
-        let mut eeg_data: Vec<(f64, [u16; 3])> = vec![];
-
-        let mut myo_left_data: Vec<(f64, f64)> = vec![];
-        let mut myo_right_data: Vec<(f64, f64)> = vec![];
-
-        let mut current_time = 0f64;
-
-        let mut sending = (false, false, 0f64);
-
-        let mut override_output = false;
-
-        while collector_running.load(Ordering::SeqCst) {
-            let data = rx.recv().unwrap();
-            const EEG_LOWER_BOUND: f64 = 20f64;
-            const EEG_UPPER_BOUND: f64 = 80f64;
-            const DATA_AMOUNT: usize = 200;
-            match data {
-                DeviceSignal::Eeg(attention, meditation, signal_quality) => {
-                    last_data[0] = u16::from(attention);
-                    last_data[1] = u16::from(meditation);
-                    last_data[2] = u16::from(signal_quality);
-
-                    eeg_data.push((current_time, last_data));
-                    if eeg_data.len() > DATA_AMOUNT {
-                        eeg_data.remove(0);
-                    }
-
-                    // Shift attention to only count a range from 20-80
-                    // Values outside that range are compressed to 0 or 100
-                    let attention = f64::from(attention);
-                    let attention = if attention < EEG_LOWER_BOUND {
-                        EEG_LOWER_BOUND
-                    } else if attention > EEG_UPPER_BOUND {
-                        EEG_UPPER_BOUND
-                    } else {
-                        attention
-                    };
-                    let attention = (attention - EEG_LOWER_BOUND)
-                        * (100f64 / (EEG_UPPER_BOUND - EEG_LOWER_BOUND));
-
-                    if !override_output {
-                        sending.2 = attention;
-                        output
-                            .update_trigger(attention)
-                            .expect("failed to write to XAC");
+    let collector_released = released.clone();
+    let collector_join = std::thread::Builder::new()
+        .name(COLLECTOR_THREAD.to_string())
+        .spawn(move || {
+            let running = &collector_running;
+            let wiring = &config.wiring;
+            match virtual_device {
+                None => {
+                    let init = || springboard::Springboard::init_with(wiring);
+                    if let Some(output) = connect(init, running) {
+                        collect(output, &rx, &tx_o, &events, config, &reload, running);
                     }
                 }
-                DeviceSignal::Myo1(state, val) => {
-                    // log!("MYO (Left): {}", val);
-                    if myo_left_data.len() > DATA_AMOUNT {
-                        myo_left_data.remove(0);
-                    }
-                    myo_left_data.push((current_time, val as f64));
-
-                    if !override_output {
-                        sending.0 = state;
-                        output.update_left_btn(state);
+                Some(device) => {
+                    let init = || springboard::Springboard::init_virtual_with(device, wiring);
+                    if let Some(output) = connect(init, running) {
+                        collect(output, &rx, &tx_o, &events, config, &reload, running);
                     }
                 }
-                DeviceSignal::Myo2(state, val) => {
-                    if myo_right_data.len() > DATA_AMOUNT {
-                        myo_right_data.remove(0);
-                    }
-                    myo_right_data.push((current_time, val as f64));
-
-                    if !override_output {
-                        sending.1 = state;
-                        output.update_right_btn(state);
-                    }
-                }
-            }
-            tx_o.update((
-                eeg_data.clone(),
-                myo_left_data.clone(),
-                myo_right_data.clone(),
-                sending,
-                current_time,
-                override_output,
-            ))
-            .expect("failed to send");
-            current_time += 0.5f64;
-
-            if let Ok(event::Event::Input(input)) = events.next_nonblocking() {
-                match input {
-                    termion::event::Key::Char('q') => {
-                        collector_running.store(false, Ordering::SeqCst);
-                    }
-                    termion::event::Key::Char('z') => {
-                        if override_output {
-                            output.update_left_btn(true);
-                            sending.0 = true;
-                        }
-                    }
-                    termion::event::Key::Char('x') => {
-                        if override_output {
-                            output.update_left_btn(false);
-                            sending.0 = false;
-                        }
-                    }
-                    termion::event::Key::Char('c') => {
-                        if override_output {
-                            output.update_right_btn(true);
-                            sending.1 = true;
-                        }
-                    }
-                    termion::event::Key::Char('v') => {
-                        if override_output {
-                            output.update_right_btn(false);
-                            sending.1 = false;
-                        }
-                    }
-                    termion::event::Key::Char('b') => {
-                        if override_output {
-                            if let Err(e) = output.update_trigger(100f64) {
-                                log!("Error updating trigger: {:?}", e);
-                            }
-                            sending.2 = 100f64;
-                        }
-                    }
-                    termion::event::Key::Char('n') => {
-                        if override_output {
-                            if let Err(e) = output.update_trigger(0f64) {
-                                log!("Error updating trigger: {:?}", e)
-                            }
-                            sending.2 = 0f64;
-                        }
-                    }
-                    termion::event::Key::Char('m') => {
-                        override_output = !override_output;
-                    }
-                    _ => (),
-                };
             }
-        }
-    });
+            collector_released.store(true, Ordering::SeqCst);
+        })?;
 
     while running.load(Ordering::SeqCst) {
-        let (eeg_data, myo_left_data, myo_right_data, sending, curr_time, override_output) =
-            rx_o.latest();
+        let UiState {
+            eeg_data,
+            myo_left_data,
+            myo_right_data,
+            sending,
+            current_time: curr_time,
+            override_output,
+            fatigue,
+            quality,
+            mismatches,
+            pointer,
+            scan,
+            morse,
+        } = rx_o.latest();
 
         let myo_left_dataset = myo_left_data.clone(); // TODO: Change me!
         let myo_right_dataset = myo_right_data.clone();
@@ -624,18 +829,55 @@ pub fn main() -> Result<()> {
                     ])
                     .render(&mut f, chunks[1]);
 
-                let events_list = vec![
+                let mut events_list = sending
+                    .iter()
+                    .map(|(name, value)| {
+                        Text::styled(
+                            format!("{}: {}\n", name, value),
+                            Style::default().fg(Color::White),
+                        )
+                    })
+                    .collect_vec();
+                events_list.extend(vec![
                     Text::styled(
-                        format!("Myo (L): {}\n", sending.0),
-                        Style::default().fg(Color::White),
+                        format!("Wiper mismatches: {}\n", mismatches),
+                        Style::default().fg(if *mismatches > 0 {
+                            Color::Red
+                        } else {
+                            Color::White
+                        }),
                     ),
                     Text::styled(
-                        format!("Myo (R): {}\n", sending.1),
-                        Style::default().fg(Color::White),
+                        format!(
+                            "Fatigue (L): {:?} {:.0}%\n",
+                            fatigue[0].level,
+                            fatigue[0].index * 100f32
+                        ),
+                        Style::default().fg(fatigue_color(fatigue[0].level)),
                     ),
                     Text::styled(
-                        format!("EEG: {}\n", sending.2),
-                        Style::default().fg(Color::White),
+                        format!(
+                            "Fatigue (R): {:?} {:.0}%\n",
+                            fatigue[1].level,
+                            fatigue[1].index * 100f32
+                        ),
+                        Style::default().fg(fatigue_color(fatigue[1].level)),
+                    ),
+                    Text::styled(
+                        format!(
+                            "Signal (L): {:?} {:.0}%\n",
+                            quality[0].status,
+                            quality[0].index * 100f32
+                        ),
+                        Style::default().fg(quality_color(quality[0].status)),
+                    ),
+                    Text::styled(
+                        format!(
+                            "Signal (R): {:?} {:.0}%\n",
+                            quality[1].status,
+                            quality[1].index * 100f32
+                        ),
+                        Style::default().fg(quality_color(quality[1].status)),
                     ),
                     Text::styled(
                         format!("Curr time: {}\n", curr_time),
@@ -645,7 +887,64 @@ pub fn main() -> Result<()> {
                         format!("Keyboard Override: {}\n", override_output),
                         Style::default().fg(Color::White),
                     ),
-                ];
+                    Text::styled(
+                        match pointer {
+                            Some(pointer) => format!(
+                                "Pointer: {:.0} px/s, heading {:.0}°{}\n",
+                                pointer.speed,
+                                pointer.heading,
+                                pointer.dwell.map_or(String::new(), |dwell| format!(
+                                    ", dwell {:.0}%",
+                                    dwell * 100f64
+                                ))
+                            ),
+                            None => "Pointer: off\n".to_string(),
+                        },
+                        Style::default().fg(if pointer.is_some() {
+                            Color::Green
+                        } else {
+                            Color::White
+                        }),
+                    ),
+                    Text::styled(
+                        match scan {
+                            Some(scan) => format!(
+                                "Scan: {}\n",
+                                scan.outputs
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(i, name)| if i == scan.highlighted {
+                                        format!("[{}]", name)
+                                    } else {
+                                        name.to_string()
+                                    })
+                                    .join(" ")
+                            ),
+                            None => "Scan: off\n".to_string(),
+                        },
+                        Style::default().fg(match scan {
+                            Some(scan) if scan.selected => Color::Yellow,
+                            Some(_) => Color::Green,
+                            None => Color::White,
+                        }),
+                    ),
+                    Text::styled(
+                        match &morse {
+                            Some(morse) => format!(
+                                "Text: {}_ {} (dot {} ms)\n",
+                                morse.text,
+                                morse.symbols,
+                                morse.unit.as_millis()
+                            ),
+                            None => "Text: off\n".to_string(),
+                        },
+                        Style::default().fg(if morse.is_some() {
+                            Color::Green
+                        } else {
+                            Color::White
+                        }),
+                    ),
+                ]);
                 let logs = LOGS.lock().unwrap();
                 let logs_list = logs.iter().map(|l: &String| Text::styled(
                         format!("{}\n", l), 
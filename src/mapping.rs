@@ -0,0 +1,321 @@
+//! Connects the devices' readings to the outputs through chains of transforms described as
+//! data, so that a new game needs a new table of mappings rather than new code.
+//!
+//! Values flow through a chain on a 0 to 100 scale, as analog outputs take them. A chain
+//! ending at a button presses it while its value is over 50, so on/off inputs read as 0
+//! and 100.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::button_mode::{ButtonBehaviour, ButtonMode};
+use crate::eeg::EegBand;
+use crate::myo::Side;
+use crate::shaping::{Curve, Shaper, Shaping};
+
+/// A reading that can drive an output.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Input {
+    /// eSense attention, from 0 to 100
+    Attention,
+    /// eSense meditation, from 0 to 100
+    Meditation,
+    /// The power in one EEG band, as the Mindwave reports it. These have no fixed range,
+    /// so they usually want a `Transform::Scale` first.
+    Band(EegBand),
+    /// Whether the myo channel on a side is flexed, as 0 or 100
+    Flexed(Side),
+    /// How strongly the myo channel on a side is flexed, as the pipeline measures it
+    Level(Side),
+    /// The ADC's analog channel, from 0 to 1023
+    Analog,
+}
+
+/// One step of a chain.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Transform {
+    /// 100 once the value reaches `on`, and 0 again once it falls to `off`. An `off` below
+    /// `on` keeps a noisy value from flickering across the threshold.
+    Threshold { on: f64, off: f64 },
+    /// 100 minus the value
+    Invert,
+    /// Maps `input_min` to `output_min` and `input_max` to `output_max` in a straight line,
+    /// without going past either output
+    Scale {
+        input_min: f64,
+        input_max: f64,
+        output_min: f64,
+        output_max: f64,
+    },
+    /// A response curve over the whole range
+    Curve(Curve),
+    /// Each rise over 50 flips the value between 0 and 100
+    Latch,
+    /// Presses a button in the given mode while the value is over 50, giving 100 while
+    /// it's pressed. Modes that press or release by themselves carry on between readings.
+    Mode(ButtonMode),
+    /// Rescaling, smoothing and slew limiting
+    Shape(Shaping),
+}
+
+/// Drives the output called `output` from `input` through `transforms`, in order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mapping {
+    pub input: Input,
+    pub transforms: Vec<Transform>,
+    pub output: String,
+}
+
+/// The state a transform keeps between readings
+enum Stage {
+    Stateless,
+    Threshold(bool),
+    Button(ButtonBehaviour),
+    Shape(Shaper),
+}
+
+impl Stage {
+    fn new(transform: Transform) -> Self {
+        match transform {
+            Transform::Threshold { .. } => Stage::Threshold(false),
+            Transform::Latch => Stage::Button(ButtonBehaviour::new(ButtonMode::Toggle)),
+            Transform::Mode(mode) => Stage::Button(ButtonBehaviour::new(mode)),
+            Transform::Shape(shaping) => Stage::Shape(Shaper::new(shaping)),
+            Transform::Invert | Transform::Scale { .. } | Transform::Curve(_) => Stage::Stateless,
+        }
+    }
+
+    fn apply(&mut self, transform: Transform, value: f64, now: Duration) -> f64 {
+        match (transform, self) {
+            (Transform::Threshold { on, off }, Stage::Threshold(high)) => {
+                if value >= on {
+                    *high = true;
+                } else if value <= off {
+                    *high = false;
+                }
+                level(*high)
+            }
+            (Transform::Invert, _) => 100f64 - value,
+            (
+                Transform::Scale {
+                    input_min,
+                    input_max,
+                    output_min,
+                    output_max,
+                },
+                _,
+            ) => {
+                let range = input_max - input_min;
+                let x = if range.abs() < std::f64::EPSILON {
+                    if value >= input_max {
+                        1f64
+                    } else {
+                        0f64
+                    }
+                } else {
+                    ((value - input_min) / range).max(0f64).min(1f64)
+                };
+                output_min + x * (output_max - output_min)
+            }
+            (Transform::Curve(curve), _) => {
+                curve.apply((value / 100f64).max(0f64).min(1f64)) * 100f64
+            }
+            (_, Stage::Button(behaviour)) => level(behaviour.update(value > 50f64, now)),
+            (_, Stage::Shape(shaper)) => shaper.shape(value, now),
+            _ => unreachable!("every stage is made for its transform"),
+        }
+    }
+}
+
+fn level(high: bool) -> f64 {
+    if high {
+        100f64
+    } else {
+        0f64
+    }
+}
+
+/// A fresh stage for each of `transforms`
+fn stages(transforms: &[Transform]) -> Vec<Stage> {
+    transforms.iter().cloned().map(Stage::new).collect()
+}
+
+struct Chain {
+    mapping: Mapping,
+    stages: Vec<Stage>,
+    /// The value the chain last gave its output
+    value: Option<f64>,
+}
+
+impl Chain {
+    fn new(mapping: Mapping) -> Self {
+        Self {
+            stages: stages(&mapping.transforms),
+            mapping,
+            value: None,
+        }
+    }
+
+    /// Runs `value` through the transforms from the one numbered `from`
+    fn run(&mut self, from: usize, mut value: f64, now: Duration) {
+        for (&transform, stage) in self.mapping.transforms[from..]
+            .iter()
+            .zip(self.stages[from..].iter_mut())
+        {
+            value = stage.apply(transform, value, now);
+        }
+        self.value = Some(value);
+    }
+
+    fn reset(&mut self) {
+        self.stages = stages(&self.mapping.transforms);
+        self.value = None;
+    }
+}
+
+/// Runs readings through a set of mappings.
+pub struct Mapper {
+    chains: Vec<Chain>,
+}
+
+impl Mapper {
+    pub fn new(mappings: &[Mapping]) -> Self {
+        Self {
+            chains: mappings.iter().cloned().map(Chain::new).collect(),
+        }
+    }
+
+    /// Switches to `mappings`, carrying on the chains of the ones that haven't changed, and
+    /// returns every output that was driven by a mapping that's gone, once each.
+    pub fn remap(&mut self, mappings: &[Mapping]) -> Vec<String> {
+        let mut old = std::mem::replace(&mut self.chains, vec![]);
+        for mapping in mappings {
+            let chain = match old.iter().position(|chain| chain.mapping == *mapping) {
+                Some(i) => old.remove(i),
+                None => Chain::new(mapping.clone()),
+            };
+            self.chains.push(chain);
+        }
+        let mut dropped = vec![];
+        for chain in old {
+            if !dropped.contains(&chain.mapping.output) {
+                dropped.push(chain.mapping.output);
+            }
+        }
+        dropped
+    }
+
+    pub fn mappings(&self) -> Vec<Mapping> {
+        self.chains
+            .iter()
+            .map(|chain| chain.mapping.clone())
+            .collect()
+    }
+
+    /// Every output driven by a mapping whose input passes `filter`, once each
+    pub fn outputs(&self, filter: impl Fn(Input) -> bool) -> Vec<String> {
+        let mut outputs = vec![];
+        for chain in &self.chains {
+            if filter(chain.mapping.input) && !outputs.contains(&chain.mapping.output) {
+                outputs.push(chain.mapping.output.clone());
+            }
+        }
+        outputs
+    }
+
+    /// The value an output should take from its chains: the highest, when several
+    /// drive it.
+    fn value(&self, output: &str) -> Option<f64> {
+        self.chains
+            .iter()
+            .filter(|chain| chain.mapping.output == output)
+            .filter_map(|chain| chain.value)
+            .fold(None, |highest: Option<f64>, value| {
+                Some(highest.map_or(value, |highest| highest.max(value)))
+            })
+    }
+
+    /// Takes a reading of `input` at `now`, and returns the value of every output driven
+    /// by it.
+    pub fn update(&mut self, input: Input, value: f64, now: Duration) -> Vec<(String, f64)> {
+        let mut outputs = vec![];
+        for chain in self.chains.iter_mut() {
+            if chain.mapping.input == input {
+                chain.run(0, value, now);
+                if !outputs.contains(&chain.mapping.output) {
+                    outputs.push(chain.mapping.output.clone());
+                }
+            }
+        }
+        self.values(outputs)
+    }
+
+    /// Moves time on to `now` for the button modes that press or release by themselves
+    /// and for smoothing and slew limiting still on their way to the last reading, and
+    /// returns the value of every output they drive. Chains that haven't had a reading
+    /// yet are left alone.
+    pub fn tick(&mut self, now: Duration) -> Vec<(String, f64)> {
+        let mut outputs = vec![];
+        for chain in self.chains.iter_mut() {
+            if chain.value.is_none() {
+                continue;
+            }
+            let first_moving =
+                chain
+                    .stages
+                    .iter_mut()
+                    .enumerate()
+                    .find_map(|(i, stage)| match stage {
+                        Stage::Button(behaviour) => Some((i, level(behaviour.tick(now)))),
+                        Stage::Shape(shaper) => shaper.tick(now).map(|value| (i, value)),
+                        _ => None,
+                    });
+            if let Some((i, value)) = first_moving {
+                chain.run(i + 1, value, now);
+                if !outputs.contains(&chain.mapping.output) {
+                    outputs.push(chain.mapping.output.clone());
+                }
+            }
+        }
+        self.values(outputs)
+    }
+
+    fn values(&self, outputs: Vec<String>) -> Vec<(String, f64)> {
+        outputs
+            .into_iter()
+            .filter_map(|output| self.value(&output).map(|value| (output, value)))
+            .collect()
+    }
+
+    /// Records a value written to `output` by something else, such as the keyboard
+    /// override, so that smoothing and slew limiting carry on from there.
+    pub fn hold(&mut self, output: &str, value: f64, now: Duration) {
+        for chain in self.chains.iter_mut() {
+            if chain.mapping.output != output {
+                continue;
+            }
+            for stage in chain.stages.iter_mut() {
+                if let Stage::Shape(shaper) = stage {
+                    shaper.hold(value, now);
+                }
+            }
+        }
+    }
+
+    /// Starts the chains driving `output` over, releasing latched and repeating buttons.
+    pub fn reset_output(&mut self, output: &str) {
+        for chain in self.chains.iter_mut() {
+            if chain.mapping.output == output {
+                chain.reset();
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for chain in self.chains.iter_mut() {
+            chain.reset();
+        }
+    }
+}
@@ -0,0 +1,549 @@
+//! In-memory stand-ins for the Pi's peripherals, so that the drivers and the collector can
+//! run on an ordinary machine.
+//!
+//! Every mock shares a `Timeline`, which records each write along with the time it
+//! happened. Time only moves when the test says so, which keeps recorded timestamps exact.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::blocking::{i2c, spi};
+use embedded_hal::digital::v2::{OutputPin, StatefulOutputPin};
+use embedded_hal::serial;
+
+use crate::clock::Clock;
+use crate::error::*;
+use crate::springboard::uinput::EventSink;
+use crate::springboard::{self, Analog, AnalogOutput, Button, ButtonConfig, Springboard};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Write {
+    /// An output pin was driven high or low
+    Pin {
+        name: String,
+        high: bool,
+    },
+    /// An analog output was set to a value in the range [0, 100]
+    Analog {
+        name: String,
+        value: f64,
+    },
+    I2c {
+        address: u8,
+        bytes: Vec<u8>,
+    },
+    Spi {
+        bytes: Vec<u8>,
+    },
+    Serial {
+        byte: u8,
+    },
+    /// An input event was sent to a virtual device
+    Event {
+        kind: u16,
+        code: u16,
+        value: i32,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub time: Duration,
+    pub write: Write,
+}
+
+#[derive(Default)]
+struct TimelineState {
+    now: Duration,
+    records: Vec<Record>,
+}
+
+/// A shared, manually advanced clock along with everything written while it ran.
+#[derive(Clone, Default)]
+pub struct Timeline {
+    state: Arc<Mutex<TimelineState>>,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn now(&self) -> Duration {
+        self.state.lock().unwrap().now
+    }
+
+    /// Moves the clock to `time`. Time never runs backwards.
+    pub fn set_time(&self, time: Duration) {
+        let mut state = self.state.lock().unwrap();
+        if time > state.now {
+            state.now = time;
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.state.lock().unwrap().now += duration;
+    }
+
+    /// Everything written so far, oldest first.
+    pub fn records(&self) -> Vec<Record> {
+        self.state.lock().unwrap().records.clone()
+    }
+
+    /// The writes to the pin called `name`, as (time, high) pairs.
+    pub fn pin_writes(&self, name: &str) -> Vec<(Duration, bool)> {
+        self.records()
+            .into_iter()
+            .filter_map(|record| match record.write {
+                Write::Pin { name: pin, high } if pin == name => Some((record.time, high)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The values written to the mock analog output called `name`, as (time, value) pairs.
+    pub fn analog_writes(&self, name: &str) -> Vec<(Duration, f64)> {
+        self.records()
+            .into_iter()
+            .filter_map(|record| match record.write {
+                Write::Analog {
+                    name: output,
+                    value,
+                } if output == name => Some((record.time, value)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The events sent to virtual devices with code `code`, as (time, value) pairs. Sync
+    /// events are left out.
+    pub fn event_values(&self, code: u16) -> Vec<(Duration, i32)> {
+        self.records()
+            .into_iter()
+            .filter_map(|record| match record.write {
+                Write::Event {
+                    kind,
+                    code: event_code,
+                    value,
+                } if kind != 0 && event_code == code => Some((record.time, value)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.state.lock().unwrap().records.clear();
+    }
+
+    fn record(&self, write: Write) {
+        let mut state = self.state.lock().unwrap();
+        let time = state.now;
+        state.records.push(Record { time, write });
+    }
+}
+
+impl Clock for Timeline {
+    fn now(&self) -> Duration {
+        Timeline::now(self)
+    }
+}
+
+/// Delays advance the clock, rather than wait.
+impl DelayMs<u8> for Timeline {
+    fn delay_ms(&mut self, ms: u8) {
+        self.advance(Duration::from_millis(u64::from(ms)));
+    }
+}
+
+/// An output pin. Starts low, like a freshly exported GPIO.
+pub struct MockPin {
+    name: String,
+    high: bool,
+    timeline: Timeline,
+}
+
+impl MockPin {
+    pub fn new(name: &str, timeline: &Timeline) -> Self {
+        Self {
+            name: name.to_string(),
+            high: false,
+            timeline: timeline.clone(),
+        }
+    }
+
+    fn write(&mut self, high: bool) {
+        self.high = high;
+        self.timeline.record(Write::Pin {
+            name: self.name.clone(),
+            high,
+        });
+    }
+}
+
+impl OutputPin for MockPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> std::result::Result<(), Infallible> {
+        self.write(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> std::result::Result<(), Infallible> {
+        self.write(true);
+        Ok(())
+    }
+}
+
+impl StatefulOutputPin for MockPin {
+    fn is_set_high(&self) -> std::result::Result<bool, Infallible> {
+        Ok(self.high)
+    }
+
+    fn is_set_low(&self) -> std::result::Result<bool, Infallible> {
+        Ok(!self.high)
+    }
+}
+
+/// A virtual device that records the events sent to it.
+#[derive(Clone)]
+pub struct MockEvents {
+    timeline: Timeline,
+}
+
+impl MockEvents {
+    pub fn new(timeline: &Timeline) -> Self {
+        Self {
+            timeline: timeline.clone(),
+        }
+    }
+}
+
+impl EventSink for MockEvents {
+    fn emit(&mut self, kind: u16, code: u16, value: i32) -> Result<()> {
+        self.timeline.record(Write::Event { kind, code, value });
+        Ok(())
+    }
+}
+
+/// An analog output device that records the values it is set to.
+pub struct MockAnalog {
+    name: String,
+    timeline: Timeline,
+}
+
+impl MockAnalog {
+    pub fn new(name: &str, timeline: &Timeline) -> Self {
+        Self {
+            name: name.to_string(),
+            timeline: timeline.clone(),
+        }
+    }
+}
+
+impl AnalogOutput for MockAnalog {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn set_value(&mut self, value: f64) -> Result<()> {
+        self.timeline.record(Write::Analog {
+            name: self.name.clone(),
+            value,
+        });
+        Ok(())
+    }
+}
+
+/// A springboard with the default buttons and analog outputs, all mocked.
+pub fn springboard(timeline: &Timeline) -> Springboard<MockPin> {
+    let analogs = springboard::default_analog_outputs();
+    let names: Vec<_> = analogs.iter().map(|config| config.name.as_str()).collect();
+    springboard_with(timeline, &springboard::default_buttons(), &names)
+}
+
+/// A springboard with the given buttons and analog outputs, which rest at 0. Each
+/// button's pin and each analog device is named after the output it drives.
+pub fn springboard_with(
+    timeline: &Timeline,
+    buttons: &[ButtonConfig],
+    analogs: &[&str],
+) -> Springboard<MockPin> {
+    let buttons = buttons
+        .iter()
+        .map(|config| Button::new(config.clone(), MockPin::new(&config.name, timeline)))
+        .collect();
+    let analogs = analogs
+        .iter()
+        .map(|name| Analog::new(name, 0f64, Box::new(MockAnalog::new(name, timeline))))
+        .collect();
+    Springboard::new(buttons, analogs).expect("mock pins can't fail")
+}
+
+/// An I2C bus with register-based devices attached. A write of several bytes sets
+/// consecutive registers starting at the first byte; a single-byte write sets register 0,
+/// as on single-register devices like the MCP4018. Plain reads start at register 0.
+/// Talking to an address with no device attached fails, like a NACK.
+#[derive(Clone)]
+pub struct MockI2c {
+    devices: Arc<Mutex<HashMap<u8, [u8; 256]>>>,
+    faults: Arc<Mutex<HashMap<u8, Faults>>>,
+    timeline: Timeline,
+}
+
+/// Misbehaviour queued up for a device, as from loose wiring.
+#[derive(Clone, Copy, Default)]
+struct Faults {
+    /// Transfers still to go unacknowledged
+    nacks: u32,
+    /// Writes still to be acknowledged but lost
+    lost_writes: u32,
+}
+
+impl MockI2c {
+    pub fn new(timeline: &Timeline) -> Self {
+        Self {
+            devices: Arc::new(Mutex::new(HashMap::new())),
+            faults: Arc::new(Mutex::new(HashMap::new())),
+            timeline: timeline.clone(),
+        }
+    }
+
+    /// Makes the next `count` transfers to `address` fail, as if not acknowledged.
+    pub fn nack_next(&self, address: u8, count: u32) {
+        self.faults
+            .lock()
+            .unwrap()
+            .entry(address)
+            .or_default()
+            .nacks = count;
+    }
+
+    /// Makes the next `count` writes to `address` succeed without reaching its registers.
+    pub fn lose_next_writes(&self, address: u8, count: u32) {
+        self.faults
+            .lock()
+            .unwrap()
+            .entry(address)
+            .or_default()
+            .lost_writes = count;
+    }
+
+    /// Whether a transfer to `address` should fail, using up one queued NACK if so.
+    fn nacked(&self, address: u8) -> bool {
+        let mut faults = self.faults.lock().unwrap();
+        match faults.get_mut(&address) {
+            Some(fault) if fault.nacks > 0 => {
+                fault.nacks -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a write to `address` should be lost, using up one queued loss if so.
+    fn lost(&self, address: u8) -> bool {
+        let mut faults = self.faults.lock().unwrap();
+        match faults.get_mut(&address) {
+            Some(fault) if fault.lost_writes > 0 => {
+                fault.lost_writes -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Attaches a device at `address` with all its registers zeroed.
+    pub fn attach(&self, address: u8) {
+        self.devices.lock().unwrap().insert(address, [0u8; 256]);
+    }
+
+    pub fn detach(&self, address: u8) {
+        self.devices.lock().unwrap().remove(&address);
+    }
+
+    pub fn register(&self, address: u8, register: u8) -> Option<u8> {
+        self.devices
+            .lock()
+            .unwrap()
+            .get(&address)
+            .map(|registers| registers[register as usize])
+    }
+
+    fn read_from(&self, address: u8, register: u8, buffer: &mut [u8]) -> Result<()> {
+        if self.nacked(address) {
+            return Err(nack(address));
+        }
+        let devices = self.devices.lock().unwrap();
+        let registers = devices.get(&address).ok_or_else(|| nack(address))?;
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = registers[(register as usize + i) % registers.len()];
+        }
+        Ok(())
+    }
+}
+
+fn nack(address: u8) -> WfpiError {
+    failure::err_msg(format!("no device at {:#04x}", address)).into()
+}
+
+impl i2c::Write for MockI2c {
+    type Error = WfpiError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<()> {
+        if self.nacked(address) {
+            return Err(nack(address));
+        }
+        let mut devices = self.devices.lock().unwrap();
+        let registers = devices.get_mut(&address).ok_or_else(|| nack(address))?;
+        if !self.lost(address) {
+            if let [value] = bytes {
+                registers[0] = *value;
+            } else if let Some((register, values)) = bytes.split_first() {
+                for (i, value) in values.iter().enumerate() {
+                    registers[(*register as usize + i) % registers.len()] = *value;
+                }
+            }
+        }
+        self.timeline.record(Write::I2c {
+            address,
+            bytes: bytes.to_vec(),
+        });
+        Ok(())
+    }
+}
+
+impl i2c::Read for MockI2c {
+    type Error = WfpiError;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<()> {
+        self.read_from(address, 0, buffer)
+    }
+}
+
+impl i2c::WriteRead for MockI2c {
+    type Error = WfpiError;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<()> {
+        let register = bytes.first().cloned().unwrap_or(0);
+        self.read_from(address, register, buffer)
+    }
+}
+
+/// A write-only SPI device, such as the MCP4922 DAC.
+pub struct MockSpi {
+    timeline: Timeline,
+}
+
+impl MockSpi {
+    pub fn new(timeline: &Timeline) -> Self {
+        Self {
+            timeline: timeline.clone(),
+        }
+    }
+}
+
+impl spi::Write<u8> for MockSpi {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> std::result::Result<(), Infallible> {
+        self.timeline.record(Write::Spi {
+            bytes: words.to_vec(),
+        });
+        Ok(())
+    }
+}
+
+/// An MCP3008 ADC on SPI, answering each conversion with the value set for its channel.
+#[derive(Clone)]
+pub struct MockMcp3008 {
+    channels: Arc<Mutex<[u16; 8]>>,
+    timeline: Timeline,
+}
+
+impl MockMcp3008 {
+    pub fn new(timeline: &Timeline) -> Self {
+        Self {
+            channels: Arc::new(Mutex::new([0u16; 8])),
+            timeline: timeline.clone(),
+        }
+    }
+
+    /// Sets the 10-bit reading returned for `channel`.
+    pub fn set_channel(&self, channel: u8, value: u16) {
+        self.channels.lock().unwrap()[(channel & 0x7) as usize] = value & 0x3FF;
+    }
+}
+
+impl spi::Transfer<u8> for MockMcp3008 {
+    type Error = Infallible;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> std::result::Result<&'w [u8], Infallible> {
+        self.timeline.record(Write::Spi {
+            bytes: words.to_vec(),
+        });
+        if words.len() == 3 {
+            // Answer in the framing `MyoReader` expects: bit 9 at the end of the first byte,
+            // bits 8-1 in the second and bit 0 at the top of the third
+            let channel = (words[0] >> 3) & 0x7;
+            let value = self.channels.lock().unwrap()[channel as usize];
+            words[0] = ((value >> 9) & 0x01) as u8;
+            words[1] = ((value >> 1) & 0xFF) as u8;
+            words[2] = ((value & 0x01) << 7) as u8;
+        }
+        Ok(words)
+    }
+}
+
+/// A serial port that reads back bytes queued by the test and records what is written to it.
+/// Blocking reads spin until a byte is queued, so queue whole packets at a time.
+#[derive(Clone)]
+pub struct MockUart {
+    input: Arc<Mutex<VecDeque<u8>>>,
+    timeline: Timeline,
+}
+
+impl MockUart {
+    pub fn new(timeline: &Timeline) -> Self {
+        Self {
+            input: Arc::new(Mutex::new(VecDeque::new())),
+            timeline: timeline.clone(),
+        }
+    }
+
+    /// Queues bytes to be read.
+    pub fn push(&self, bytes: &[u8]) {
+        self.input.lock().unwrap().extend(bytes);
+    }
+
+    pub fn pending(&self) -> usize {
+        self.input.lock().unwrap().len()
+    }
+}
+
+impl serial::Read<u8> for MockUart {
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Infallible> {
+        self.input
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl serial::Write<u8> for MockUart {
+    type Error = Infallible;
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+        self.timeline.record(Write::Serial { byte });
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Infallible> {
+        Ok(())
+    }
+}
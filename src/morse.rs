@@ -0,0 +1,253 @@
+//! Text entry in Morse code from a single switch: short flexes are dots and long flexes are
+//! dashes, with the length of a dot learned from the user's own rhythm as they go.
+
+use std::time::Duration;
+
+use crate::error::*;
+
+/// How many characters of typed text the TUI previews
+const PREVIEW_LENGTH: usize = 32;
+
+/// International Morse code, plus the prosigns used for editing
+const CODE: &[(&str, TextKey)] = &[
+    (".-", TextKey::Char('A')),
+    ("-...", TextKey::Char('B')),
+    ("-.-.", TextKey::Char('C')),
+    ("-..", TextKey::Char('D')),
+    (".", TextKey::Char('E')),
+    ("..-.", TextKey::Char('F')),
+    ("--.", TextKey::Char('G')),
+    ("....", TextKey::Char('H')),
+    ("..", TextKey::Char('I')),
+    (".---", TextKey::Char('J')),
+    ("-.-", TextKey::Char('K')),
+    (".-..", TextKey::Char('L')),
+    ("--", TextKey::Char('M')),
+    ("-.", TextKey::Char('N')),
+    ("---", TextKey::Char('O')),
+    (".--.", TextKey::Char('P')),
+    ("--.-", TextKey::Char('Q')),
+    (".-.", TextKey::Char('R')),
+    ("...", TextKey::Char('S')),
+    ("-", TextKey::Char('T')),
+    ("..-", TextKey::Char('U')),
+    ("...-", TextKey::Char('V')),
+    (".--", TextKey::Char('W')),
+    ("-..-", TextKey::Char('X')),
+    ("-.--", TextKey::Char('Y')),
+    ("--..", TextKey::Char('Z')),
+    ("-----", TextKey::Char('0')),
+    (".----", TextKey::Char('1')),
+    ("..---", TextKey::Char('2')),
+    ("...--", TextKey::Char('3')),
+    ("....-", TextKey::Char('4')),
+    (".....", TextKey::Char('5')),
+    ("-....", TextKey::Char('6')),
+    ("--...", TextKey::Char('7')),
+    ("---..", TextKey::Char('8')),
+    ("----.", TextKey::Char('9')),
+    (".-.-.-", TextKey::Char('.')),
+    ("--..--", TextKey::Char(',')),
+    ("..--..", TextKey::Char('?')),
+    (".----.", TextKey::Char('\'')),
+    ("-.-.--", TextKey::Char('!')),
+    ("-..-.", TextKey::Char('/')),
+    ("-.--.", TextKey::Char('(')),
+    ("-.--.-", TextKey::Char(')')),
+    ("---...", TextKey::Char(':')),
+    ("-.-.-.", TextKey::Char(';')),
+    ("-...-", TextKey::Char('=')),
+    ("-....-", TextKey::Char('-')),
+    (".-..-.", TextKey::Char('"')),
+    (".--.-.", TextKey::Char('@')),
+    // AR, end of message
+    (".-.-.", TextKey::Enter),
+    // The error prosign, eight dots
+    ("........", TextKey::Backspace),
+];
+
+/// What the decoder types.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextKey {
+    /// A letter, in upper case, a digit or punctuation
+    Char(char),
+    Space,
+    Backspace,
+    Enter,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MorseConfig {
+    /// The length of a dot to start from, before the user's rhythm is learned
+    pub unit: Duration,
+    /// The shortest and longest the learned dot length may become
+    pub min_unit: Duration,
+    pub max_unit: Duration,
+    /// How far each flex moves the learned dot length towards its own, from 0 to keep the
+    /// starting length to 1 to follow the last flex entirely
+    pub adaptation: f64,
+    /// Flexes shorter than this are taken for noise and ignored
+    pub min_flex: Duration,
+    /// How long a rest, in dots, ends a letter. Standard timing puts letters three dots
+    /// apart and the dots and dashes within them one apart.
+    pub letter_gap: f64,
+    /// How long a rest, in dots, ends a word, which standard timing puts seven dots apart
+    pub word_gap: f64,
+}
+
+/// Where the decoder's text goes.
+pub trait TextOutput {
+    fn type_key(&mut self, key: TextKey) -> Result<()>;
+}
+
+/// What the TUI shows of the decoder.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorseStatus {
+    /// The learned length of a dot
+    pub unit: Duration,
+    /// The dots and dashes of the letter being entered
+    pub symbols: String,
+    /// The end of the text typed since the last `TextKey::Enter`
+    pub text: String,
+}
+
+/// Turns the flexes of one channel into text.
+pub struct MorseDecoder {
+    config: MorseConfig,
+    /// The learned length of a dot, in seconds
+    unit: f64,
+    /// When the current flex started
+    flexed_since: Option<Duration>,
+    /// When the last flex ended, while a letter or word may still be ended by resting
+    rested_since: Option<Duration>,
+    symbols: String,
+    /// Whether a rest long enough should type a space, after a letter
+    space_pending: bool,
+    text: String,
+}
+
+impl MorseDecoder {
+    pub fn new(config: MorseConfig) -> Self {
+        Self {
+            config,
+            unit: config.unit.as_secs_f64(),
+            flexed_since: None,
+            rested_since: None,
+            symbols: String::new(),
+            space_pending: false,
+            text: String::new(),
+        }
+    }
+
+    pub fn config(&self) -> &MorseConfig {
+        &self.config
+    }
+
+    /// The learned length of a dot
+    pub fn unit(&self) -> Duration {
+        Duration::from_secs_f64(self.unit)
+    }
+
+    /// Drops the letter being entered and the preview, keeping the learned timing.
+    pub fn reset(&mut self) {
+        self.flexed_since = None;
+        self.rested_since = None;
+        self.symbols.clear();
+        self.space_pending = false;
+        self.text.clear();
+    }
+
+    /// Takes whether the channel is flexed at `now`, and returns what's been typed. Call
+    /// regularly, whether or not the channel changes, so that resting ends letters and
+    /// words.
+    pub fn update(&mut self, active: bool, now: Duration) -> Vec<TextKey> {
+        let mut keys = vec![];
+        match (active, self.flexed_since) {
+            (true, None) => {
+                self.rest(now, &mut keys);
+                self.flexed_since = Some(now);
+            }
+            (false, Some(since)) => {
+                self.flexed_since = None;
+                let length = now.checked_sub(since).unwrap_or_default();
+                if length >= self.config.min_flex {
+                    self.mark(length.as_secs_f64());
+                    self.rested_since = Some(now);
+                }
+            }
+            (false, None) => self.rest(now, &mut keys),
+            (true, Some(_)) => (),
+        }
+        for &key in &keys {
+            self.preview(key);
+        }
+        keys
+    }
+
+    /// Adds a dot or dash `length` seconds long, and learns from it.
+    fn mark(&mut self, length: f64) {
+        // Halfway between a dot and a dash, which is three dots long
+        let dash = length >= 2f64 * self.unit;
+        self.symbols.push(if dash { '-' } else { '.' });
+        let sample = if dash { length / 3f64 } else { length };
+        let adaptation = self.config.adaptation.max(0f64).min(1f64);
+        self.unit += (sample - self.unit) * adaptation;
+        self.unit = self
+            .unit
+            .max(self.config.min_unit.as_secs_f64())
+            .min(self.config.max_unit.as_secs_f64());
+    }
+
+    /// Ends the letter, and then the word, if the channel has rested long enough at `now`.
+    fn rest(&mut self, now: Duration, keys: &mut Vec<TextKey>) {
+        let since = match self.rested_since {
+            Some(since) => since,
+            None => return,
+        };
+        let gap = now.checked_sub(since).unwrap_or_default().as_secs_f64();
+        if !self.symbols.is_empty() && gap >= self.config.letter_gap * self.unit {
+            let key = CODE
+                .iter()
+                .find(|(code, _)| *code == self.symbols)
+                .map(|&(_, key)| key);
+            self.symbols.clear();
+            if let Some(key) = key {
+                keys.push(key);
+            }
+            self.space_pending = match key {
+                Some(TextKey::Char(_)) => true,
+                _ => false,
+            };
+        }
+        if self.symbols.is_empty() && gap >= self.config.word_gap * self.unit {
+            if self.space_pending {
+                keys.push(TextKey::Space);
+            }
+            self.space_pending = false;
+            self.rested_since = None;
+        }
+    }
+
+    fn preview(&mut self, key: TextKey) {
+        match key {
+            TextKey::Char(c) => self.text.push(c),
+            TextKey::Space => self.text.push(' '),
+            TextKey::Backspace => {
+                self.text.pop();
+            }
+            TextKey::Enter => self.text.clear(),
+        }
+        let excess = self.text.chars().count().saturating_sub(PREVIEW_LENGTH);
+        if excess > 0 {
+            self.text = self.text.chars().skip(excess).collect();
+        }
+    }
+
+    pub fn status(&self) -> MorseStatus {
+        MorseStatus {
+            unit: self.unit(),
+            symbols: self.symbols.clone(),
+            text: self.text.clone(),
+        }
+    }
+}
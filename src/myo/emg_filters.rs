@@ -2,8 +2,11 @@
 //! 1. an anti-hum notch filter to filter out 50Hz or 60Hz power line noise.
 //! 2. a low-pass filter to filter out noises above 150Hz.
 //! 3. a high-pass filter to filter out noises below 20Hz.
+//! Optionally, the second and third harmonics of the power line frequency can be notched out too.
 //! This is based on https://github.com/oymotion/EMGFilters
 
+use crate::emg_process::Stage;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u32)]
 pub enum NotchFrequency {
@@ -46,17 +49,23 @@ const AHF_DENOMINATOR_COEF_60HZ: [[f32; 6]; 2] = [
     [1.0000, -1.8407, 0.9894, 1.0000, -1.8584, 0.9891],
 ];
 const AHF_OUTPUT_GAIN_COEF_60HZ: [f32; 2] = [1.3430, 1.4206];
+// quality factor of the harmonic notches; wide enough to tolerate some drift in the grid frequency
+const HARMONIC_NOTCH_Q: f32 = 10f32;
+
+// cutoff frequency of the low-pass filter, used to estimate its group delay
+const LPF_CUTOFF: f32 = 150f32;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum FilterType {
+pub enum FilterType {
     LowPass = 0,
     HighPass,
 }
 
-struct Filter2nd {
+pub struct Filter2nd {
     states: [f32; 2],
     num: [f32; 3],
     den: [f32; 3],
+    latency: f32,
 }
 
 impl Filter2nd {
@@ -82,7 +91,36 @@ impl Filter2nd {
             }
         };
 
-        Filter2nd { states, num, den }
+        let latency = match f_type {
+            // group delay of a 2nd order butterworth filter, well inside its passband
+            FilterType::LowPass => {
+                std::f32::consts::SQRT_2 * sample_freq as u32 as f32
+                    / (2f32 * std::f32::consts::PI * LPF_CUTOFF)
+            }
+            FilterType::HighPass => 0f32,
+        };
+
+        Filter2nd {
+            states,
+            num,
+            den,
+            latency,
+        }
+    }
+
+    /// A 2nd order IIR notch filter centred on `freq` Hz
+    pub fn notch(freq: f32, sample_freq: SampleFrequency, q: f32) -> Self {
+        let w0 = 2f32 * std::f32::consts::PI * freq / sample_freq as u32 as f32;
+        let alpha = w0.sin() / (2f32 * q);
+        let cos_w0 = w0.cos();
+        // normalised so that den[0] == 1
+        let a0 = 1f32 + alpha;
+        Filter2nd {
+            states: [0f32; 2],
+            num: [1f32 / a0, -2f32 * cos_w0 / a0, 1f32 / a0],
+            den: [1f32, -2f32 * cos_w0 / a0, (1f32 - alpha) / a0],
+            latency: 0f32,
+        }
     }
 
     pub fn update(&mut self, input: f32) -> f32 {
@@ -95,9 +133,28 @@ impl Filter2nd {
         self.states[0] = tmp;
         output
     }
+
+    pub fn reset(&mut self) {
+        self.states = [0f32; 2];
+    }
 }
 
-struct Filter4th {
+impl Stage for Filter2nd {
+    fn process(&mut self, sample: f64) -> f64 {
+        f64::from(self.update(sample as f32))
+    }
+
+    fn reset(&mut self) {
+        Filter2nd::reset(self);
+    }
+
+    fn latency(&self) -> f64 {
+        f64::from(self.latency)
+    }
+}
+
+pub struct Filter4th {
+    sample_freq: SampleFrequency,
     states: [f32; 4],
     num: [f32; 6],
     den: [f32; 6],
@@ -134,6 +191,7 @@ impl Filter4th {
             },
         };
         Filter4th {
+            sample_freq,
             states,
             num,
             den,
@@ -149,6 +207,24 @@ impl Filter4th {
         self.states[3] = self.num[5] * stage_in - self.den[5] * stage_out;
         self.gain * stage_out
     }
+
+    pub fn reset(&mut self) {
+        self.states = [0f32; 4];
+    }
+}
+
+impl Stage for Filter4th {
+    fn process(&mut self, sample: f64) -> f64 {
+        f64::from(self.update(sample as f32))
+    }
+
+    fn reset(&mut self) {
+        Filter4th::reset(self);
+    }
+
+    fn set_notch_frequency(&mut self, notch_freq: NotchFrequency) {
+        *self = Filter4th::new(self.sample_freq, notch_freq);
+    }
 }
 
 pub struct EMGFilters {
@@ -158,10 +234,12 @@ pub struct EMGFilters {
     notch_filter_enabled: bool,
     lowpass_filter_enabled: bool,
     highpass_filter_enabled: bool,
+    harmonic_filters_enabled: bool,
 
     lpf: Filter2nd,
     hpf: Filter2nd,
     ahf: Filter4th,
+    harmonic_filters: Vec<Filter2nd>,
 }
 
 impl EMGFilters {
@@ -183,23 +261,57 @@ impl EMGFilters {
             notch_filter_enabled: enable_notch_filter,
             lowpass_filter_enabled: enable_lowpass_filter,
             highpass_filter_enabled: enable_highpass_filter,
+            harmonic_filters_enabled: false,
 
             lpf: Filter2nd::new(FilterType::LowPass, sample_freq),
             hpf: Filter2nd::new(FilterType::HighPass, sample_freq),
             ahf: Filter4th::new(sample_freq, notch_freq),
+            harmonic_filters: harmonic_notches(sample_freq, notch_freq),
         }
     }
 
-    pub fn update(&mut self, input_value: i32) -> i32 {
-        let mut output = input_value as f32;
+    pub fn notch_frequency(&self) -> NotchFrequency {
+        self.notch_freq
+    }
+
+    /// Retunes the anti-hum filter (and its harmonics) to a different power line frequency.
+    /// The filter states are reset, so expect a brief transient.
+    pub fn set_notch_frequency(&mut self, notch_freq: NotchFrequency) {
+        self.notch_freq = notch_freq;
+        self.ahf = Filter4th::new(self.sample_freq, notch_freq);
+        self.harmonic_filters = harmonic_notches(self.sample_freq, notch_freq);
+    }
 
+    /// Enables notch filters at the 2nd and 3rd harmonics of the power line frequency.
+    /// These only run when the anti-hum filter itself is enabled.
+    pub fn set_harmonic_filters(&mut self, enabled: bool) {
+        self.harmonic_filters_enabled = enabled;
+    }
+
+    pub fn update(&mut self, input_value: i32) -> i32 {
         if self.bypass_enabled {
             return input_value;
         }
 
+        self.update_f32(input_value as f32) as i32
+    }
+
+    fn update_f32(&mut self, input_value: f32) -> f32 {
+        let mut output = input_value;
+
+        if self.bypass_enabled {
+            return output;
+        }
+
         // first notch filter
         if self.notch_filter_enabled {
             output = self.ahf.update(output);
+
+            if self.harmonic_filters_enabled {
+                for filter in self.harmonic_filters.iter_mut() {
+                    output = filter.update(output);
+                }
+            }
         }
 
         // second low pass filter
@@ -212,6 +324,46 @@ impl EMGFilters {
             output = self.hpf.update(output);
         }
 
-        output as i32
+        output
+    }
+
+    pub fn reset(&mut self) {
+        self.lpf.reset();
+        self.hpf.reset();
+        self.ahf.reset();
+        for filter in self.harmonic_filters.iter_mut() {
+            filter.reset();
+        }
+    }
+}
+
+impl Stage for EMGFilters {
+    fn process(&mut self, sample: f64) -> f64 {
+        f64::from(self.update_f32(sample as f32))
+    }
+
+    fn reset(&mut self) {
+        EMGFilters::reset(self);
+    }
+
+    fn latency(&self) -> f64 {
+        if self.lowpass_filter_enabled && !self.bypass_enabled {
+            Stage::latency(&self.lpf)
+        } else {
+            0f64
+        }
     }
+
+    fn set_notch_frequency(&mut self, notch_freq: NotchFrequency) {
+        EMGFilters::set_notch_frequency(self, notch_freq);
+    }
+}
+
+fn harmonic_notches(sample_freq: SampleFrequency, notch_freq: NotchFrequency) -> Vec<Filter2nd> {
+    let nyquist = sample_freq as u32 / 2;
+    (2..=3)
+        .map(|harmonic| notch_freq as u32 * harmonic)
+        .filter(|freq| *freq < nyquist)
+        .map(|freq| Filter2nd::notch(freq as f32, sample_freq, HARMONIC_NOTCH_Q))
+        .collect()
 }
@@ -0,0 +1,169 @@
+//! Tracks muscle fatigue on a single EMG channel.
+//!
+//! As a muscle tires, the median frequency of its EMG signal during a contraction drifts
+//! downwards while the amplitude tends to rise. We estimate both over short windows that
+//! lie entirely inside a contraction, compare them to a baseline taken at the start of the
+//! session, and condense the result into a fatigue index in the range [0, 1].
+
+use super::spectrum::PowerSpectrum;
+
+/// Number of samples in each analysis window (256ms at 1000Hz)
+const WINDOW_SIZE: usize = 256;
+/// Number of contraction windows averaged to establish the rested baseline
+const BASELINE_WINDOWS: u32 = 10;
+/// Smoothing factor for the running median frequency and amplitude
+const TREND_ALPHA: f32 = 0.1;
+/// A drop in median frequency of this fraction of the baseline counts as fully fatigued
+const MDF_DROP_FATIGUED: f32 = 0.25;
+
+// The band the surface EMG power lives in, matching the high- and low-pass filters
+const EMG_BAND_LOW: f32 = 20f32;
+const EMG_BAND_HIGH: f32 = 150f32;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FatigueLevel {
+    /// Still collecting the baseline
+    Calibrating,
+    Fresh,
+    Tiring,
+    Fatigued,
+}
+
+impl Default for FatigueLevel {
+    fn default() -> Self {
+        FatigueLevel::Calibrating
+    }
+}
+
+impl FatigueLevel {
+    fn from_index(index: f32) -> Self {
+        if index < 0.4 {
+            FatigueLevel::Fresh
+        } else if index < 0.8 {
+            FatigueLevel::Tiring
+        } else {
+            FatigueLevel::Fatigued
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FatigueReport {
+    pub level: FatigueLevel,
+    /// 0 (rested) to 1 (fatigued)
+    pub index: f32,
+    /// Smoothed median frequency during contractions, in Hz
+    pub median_frequency: f32,
+    pub baseline_median_frequency: f32,
+    /// Smoothed RMS amplitude during contractions, relative to the baseline
+    pub amplitude_ratio: f32,
+}
+
+pub struct FatigueMonitor {
+    spectrum: PowerSpectrum,
+    window: Vec<f32>,
+
+    baseline_windows: u32,
+    baseline_mdf: f32,
+    baseline_rms: f32,
+
+    mdf: f32,
+    rms: f32,
+
+    report: FatigueReport,
+}
+
+impl FatigueMonitor {
+    pub fn new(sample_rate: f32) -> Self {
+        FatigueMonitor {
+            spectrum: PowerSpectrum::new(WINDOW_SIZE, sample_rate),
+            window: Vec::with_capacity(WINDOW_SIZE),
+            baseline_windows: 0,
+            baseline_mdf: 0f32,
+            baseline_rms: 0f32,
+            mdf: 0f32,
+            rms: 0f32,
+            report: FatigueReport::default(),
+        }
+    }
+
+    /// Feeds a filtered EMG sample along with whether the muscle is currently contracted.
+    /// Returns a new report whenever a full contraction window has been analysed.
+    pub fn update(&mut self, sample: i32, contracted: bool) -> Option<FatigueReport> {
+        if !contracted {
+            // Only whole windows inside a contraction are meaningful
+            self.window.clear();
+            return None;
+        }
+
+        self.window.push(sample as f32);
+        if self.window.len() < WINDOW_SIZE {
+            return None;
+        }
+
+        self.spectrum.compute(&self.window);
+        let mdf = self.spectrum.median_frequency(EMG_BAND_LOW, EMG_BAND_HIGH);
+        let rms = (self.window.iter().map(|x| x * x).sum::<f32>() / WINDOW_SIZE as f32).sqrt();
+        self.window.clear();
+
+        if self.baseline_windows < BASELINE_WINDOWS {
+            // Cumulative average over the first few contraction windows
+            self.baseline_windows += 1;
+            let n = self.baseline_windows as f32;
+            self.baseline_mdf += (mdf - self.baseline_mdf) / n;
+            self.baseline_rms += (rms - self.baseline_rms) / n;
+            self.mdf = self.baseline_mdf;
+            self.rms = self.baseline_rms;
+
+            if self.baseline_windows == BASELINE_WINDOWS {
+                self.report = self.make_report(FatigueLevel::Fresh, 0f32);
+            } else {
+                self.report = self.make_report(FatigueLevel::Calibrating, 0f32);
+            }
+            return Some(self.report);
+        }
+
+        self.mdf += TREND_ALPHA * (mdf - self.mdf);
+        self.rms += TREND_ALPHA * (rms - self.rms);
+
+        let mdf_drop = if self.baseline_mdf > 0f32 {
+            (self.baseline_mdf - self.mdf) / self.baseline_mdf
+        } else {
+            0f32
+        };
+        let index = (mdf_drop / MDF_DROP_FATIGUED).max(0f32).min(1f32);
+
+        self.report = self.make_report(FatigueLevel::from_index(index), index);
+        Some(self.report)
+    }
+
+    /// The most recent fatigue report.
+    pub fn report(&self) -> FatigueReport {
+        self.report
+    }
+
+    /// Forgets the baseline, e.g. when the electrodes are moved to a different muscle.
+    pub fn reset(&mut self) {
+        self.window.clear();
+        self.baseline_windows = 0;
+        self.baseline_mdf = 0f32;
+        self.baseline_rms = 0f32;
+        self.mdf = 0f32;
+        self.rms = 0f32;
+        self.report = FatigueReport::default();
+    }
+
+    fn make_report(&self, level: FatigueLevel, index: f32) -> FatigueReport {
+        FatigueReport {
+            level,
+            index,
+            median_frequency: self.mdf,
+            baseline_median_frequency: self.baseline_mdf,
+            amplitude_ratio: if self.baseline_rms > 0f32 {
+                self.rms / self.baseline_rms
+            } else {
+                1f32
+            },
+        }
+    }
+}
@@ -1,13 +1,17 @@
 use super::emg_filters;
+use crate::emg_process::Stage;
 
-const SAMPLE_RATE: emg_filters::SampleFrequency = emg_filters::SampleFrequency::Freq1000Hz;
-const NOTCH_FREQ: emg_filters::NotchFrequency = emg_filters::NotchFrequency::Freq60Hz;
+pub const SAMPLE_RATE: emg_filters::SampleFrequency = emg_filters::SampleFrequency::Freq1000Hz;
+
+pub const DEFAULT_THRESHOLD: i32 = 100;
 
 pub struct Flappy {
+    base_threshold: i32,
     threshold: i32,
     start_emitting: bool,
 
     filter: emg_filters::EMGFilters,
+    filtered: i32,
 
     // for get_emg_count
     integral_data: i32,
@@ -19,13 +23,16 @@ pub struct Flappy {
 }
 
 impl Flappy {
-    pub fn new() -> Self {
-        let filter = emg_filters::EMGFilters::new(SAMPLE_RATE, NOTCH_FREQ, true, true, true);
+    pub fn new(notch_freq: emg_filters::NotchFrequency, notch_harmonics: bool) -> Self {
+        let mut filter = emg_filters::EMGFilters::new(SAMPLE_RATE, notch_freq, true, true, true);
+        filter.set_harmonic_filters(notch_harmonics);
         Flappy {
-            threshold: 100, // 0 in the calibration process
+            base_threshold: DEFAULT_THRESHOLD,
+            threshold: DEFAULT_THRESHOLD, // 0 in the calibration process
             start_emitting: false,
 
             filter,
+            filtered: 0,
 
             integral_data: 0,
             integral_data_eve: 0,
@@ -38,6 +45,7 @@ impl Flappy {
 
     pub fn calibration(&mut self, data: u16) -> i32 {
         let data_after_filter = self.filter.update(data as i32); // filter processing
+        self.filtered = data_after_filter;
         data_after_filter.pow(2) // get envelope by squaring the input
     }
 
@@ -59,6 +67,39 @@ impl Flappy {
         (result, envelope)
     }
 
+    /// Retunes the anti-hum filter to a different power line frequency.
+    pub fn set_notch_frequency(&mut self, notch_freq: emg_filters::NotchFrequency) {
+        self.filter.set_notch_frequency(notch_freq);
+    }
+
+    /// The most recent sample after filtering, before it is squared into an envelope.
+    pub fn filtered(&self) -> i32 {
+        self.filtered
+    }
+
+    pub fn threshold(&self) -> i32 {
+        self.threshold
+    }
+
+    /// Sets the envelope value above which the muscle counts as flexed.
+    pub fn set_threshold(&mut self, threshold: i32) {
+        self.base_threshold = threshold;
+        self.threshold = threshold;
+    }
+
+    pub fn reset(&mut self) {
+        self.threshold = self.base_threshold;
+        self.start_emitting = false;
+        self.filter.reset();
+        self.filtered = 0;
+        self.integral_data = 0;
+        self.integral_data_eve = 0;
+        self.remain_flag = false;
+        self.time_millis = 0;
+        self.time_begin_zero = 0;
+        self.fist_num = 0;
+    }
+
     /// If get EMG signal, return true
     fn get_emg_count(&mut self, gforce_envelope: i32) -> bool {
         const TIME_STANDARD: u32 = 75;
@@ -100,3 +141,32 @@ impl Flappy {
         }
     }
 }
+
+impl Stage for Flappy {
+    /// While flexed, outputs the envelope (never less than the threshold); zero otherwise.
+    fn process(&mut self, sample: f64) -> f64 {
+        let data = sample.max(0f64).min(f64::from(std::u16::MAX)) as u16;
+        let (flexed, envelope) = self.update(data);
+        if flexed {
+            f64::from(envelope.max(self.threshold).max(1))
+        } else {
+            0f64
+        }
+    }
+
+    fn reset(&mut self) {
+        Flappy::reset(self);
+    }
+
+    fn latency(&self) -> f64 {
+        Stage::latency(&self.filter)
+    }
+
+    fn set_notch_frequency(&mut self, notch_freq: emg_filters::NotchFrequency) {
+        Flappy::set_notch_frequency(self, notch_freq);
+    }
+
+    fn set_threshold_scale(&mut self, scale: f64) {
+        self.threshold = (f64::from(self.base_threshold) * scale) as i32;
+    }
+}
@@ -0,0 +1,146 @@
+//! Works out whether the power lines around us run at 50Hz or 60Hz, by comparing how much
+//! of each (and their harmonics) the raw ADC readings pick up.
+
+use super::emg_filters::NotchFrequency;
+
+/// Number of samples per decision. At 1000Hz this is a whole number of cycles at both
+/// 50Hz and 60Hz, so a constant offset doesn't leak into either measurement.
+const WINDOW_SIZE: usize = 2000;
+/// One frequency has to carry this many times the power of the other to count as detected
+const DECISION_RATIO: f32 = 2f32;
+/// Consecutive agreeing windows needed before changing an earlier decision
+const CONFIRMATIONS: u32 = 3;
+/// Harmonics examined alongside the fundamental
+const HARMONICS: [u32; 3] = [1, 2, 3];
+
+/// The notch frequency used until the first detection completes
+pub const FALLBACK_FREQUENCY: NotchFrequency = NotchFrequency::Freq60Hz;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MainsFrequency {
+    /// Measure the mains frequency from the raw signal
+    Auto,
+    /// Always use the given frequency
+    Fixed(NotchFrequency),
+}
+
+/// Measures the power of a single frequency component, one sample at a time.
+struct Goertzel {
+    coeff: f32,
+    s1: f32,
+    s2: f32,
+}
+
+impl Goertzel {
+    fn new(freq: f32, sample_rate: f32) -> Self {
+        Goertzel {
+            coeff: 2f32 * (2f32 * std::f32::consts::PI * freq / sample_rate).cos(),
+            s1: 0f32,
+            s2: 0f32,
+        }
+    }
+
+    fn update(&mut self, sample: f32) {
+        let s0 = sample + self.coeff * self.s1 - self.s2;
+        self.s2 = self.s1;
+        self.s1 = s0;
+    }
+
+    fn power(&self) -> f32 {
+        self.s1 * self.s1 + self.s2 * self.s2 - self.coeff * self.s1 * self.s2
+    }
+
+    fn reset(&mut self) {
+        self.s1 = 0f32;
+        self.s2 = 0f32;
+    }
+}
+
+pub struct MainsDetector {
+    fifty: Vec<Goertzel>,
+    sixty: Vec<Goertzel>,
+    count: usize,
+
+    detected: Option<NotchFrequency>,
+    candidate: Option<NotchFrequency>,
+    confirmations: u32,
+}
+
+impl MainsDetector {
+    pub fn new(sample_rate: f32) -> Self {
+        let bank = |fundamental: u32| -> Vec<Goertzel> {
+            HARMONICS
+                .iter()
+                .map(|h| (fundamental * h) as f32)
+                .filter(|freq| *freq < sample_rate / 2f32)
+                .map(|freq| Goertzel::new(freq, sample_rate))
+                .collect()
+        };
+
+        MainsDetector {
+            fifty: bank(50),
+            sixty: bank(60),
+            count: 0,
+            detected: None,
+            candidate: None,
+            confirmations: 0,
+        }
+    }
+
+    /// Feeds a raw sample. Returns the mains frequency whenever the detected value changes.
+    pub fn update(&mut self, sample: f32) -> Option<NotchFrequency> {
+        for goertzel in self.fifty.iter_mut().chain(self.sixty.iter_mut()) {
+            goertzel.update(sample);
+        }
+        self.count += 1;
+        if self.count < WINDOW_SIZE {
+            return None;
+        }
+
+        let fifty: f32 = self.fifty.iter().map(Goertzel::power).sum();
+        let sixty: f32 = self.sixty.iter().map(Goertzel::power).sum();
+        for goertzel in self.fifty.iter_mut().chain(self.sixty.iter_mut()) {
+            goertzel.reset();
+        }
+        self.count = 0;
+
+        let measured = if fifty > DECISION_RATIO * sixty {
+            Some(NotchFrequency::Freq50Hz)
+        } else if sixty > DECISION_RATIO * fifty {
+            Some(NotchFrequency::Freq60Hz)
+        } else {
+            None
+        };
+
+        match measured {
+            Some(freq) if Some(freq) != self.detected => {
+                if self.candidate == Some(freq) {
+                    self.confirmations += 1;
+                } else {
+                    self.candidate = Some(freq);
+                    self.confirmations = 1;
+                }
+
+                // The first decision is taken straight away, so the filters are right
+                // quickly at startup. Later changes need to be confirmed.
+                let needed = if self.detected.is_none() {
+                    1
+                } else {
+                    CONFIRMATIONS
+                };
+                if self.confirmations >= needed {
+                    self.detected = Some(freq);
+                    self.candidate = None;
+                    self.confirmations = 0;
+                    return Some(freq);
+                }
+            }
+            _ => {
+                self.candidate = None;
+                self.confirmations = 0;
+            }
+        }
+
+        None
+    }
+}
@@ -15,41 +15,132 @@
 //! Here we assume that the left MYO sensor is attached to channel 0, and the right sensor is
 //! attached to channel 1.
 
-use crate::Result;
+use crate::error::*;
 
-// use crate::emg_process::*;
+use crate::emg_process::{Chain, Stage, StageConfig};
+use embedded_hal::blocking::spi::Transfer;
 use rppal::spi;
+use serde::Deserialize;
 
 mod emg_filters;
+mod fatigue;
 mod flappy;
+mod mains;
+mod quality;
+mod spectrum;
+
+pub use emg_filters::{
+    EMGFilters, Filter2nd, Filter4th, FilterType, NotchFrequency, SampleFrequency,
+};
+pub use fatigue::{FatigueLevel, FatigueMonitor, FatigueReport};
+pub use flappy::Flappy;
+pub use mains::{MainsDetector, MainsFrequency};
+pub use quality::{QualityMonitor, QualityReport, SignalQuality};
+pub use spectrum::PowerSpectrum;
 
 const SPI_BUS: spi::Bus = spi::Bus::Spi0;
 const SPI_SLAVE_SELECT: spi::SlaveSelect = spi::SlaveSelect::Ss0;
 const SPI_MAX_CLOCK_SPEED: u32 = 1000; // This approximates the Arduino ADC default sample rate
 const SPI_MODE: spi::Mode = spi::Mode::Mode0;
 
-#[derive(Debug, Clone)]
+/// Which power line frequency the anti-hum notch filter removes, unless configured otherwise
+const MAINS_FREQUENCY: MainsFrequency = MainsFrequency::Auto;
+/// Also notch out the 2nd and 3rd harmonics of the power line frequency (100/150Hz or 120/180Hz)
+const NOTCH_HARMONICS: bool = false;
+
+/// Lower the flex threshold as a channel fatigues, so that weaker contractions still register
+const ADAPT_THRESHOLD_TO_FATIGUE: bool = false;
+/// How far the threshold is lowered, as a fraction of the default, once a channel is fully fatigued
+const FATIGUE_THRESHOLD_DROP: f32 = 0.5;
+/// Log a suggestion to take a break when a channel becomes fatigued
+const SUGGEST_BREAKS: bool = true;
+
+/// The processing applied to each channel's raw readings. A channel counts as flexed
+/// whenever the output of its pipeline is above zero.
+const LEFT_PIPELINE: &[StageConfig] = &[StageConfig::Flappy {
+    threshold: flappy::DEFAULT_THRESHOLD,
+    harmonics: NOTCH_HARMONICS,
+}];
+const RIGHT_PIPELINE: &[StageConfig] = &[StageConfig::Flappy {
+    threshold: flappy::DEFAULT_THRESHOLD,
+    harmonics: NOTCH_HARMONICS,
+}];
+
+/// The settings of the myo ADC and of each channel's processing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MyoConfig {
+    /// The SPI clock speed the ADC is read at, in Hz
+    pub spi_clock_speed: u32,
+    pub left_pipeline: Vec<StageConfig>,
+    pub right_pipeline: Vec<StageConfig>,
+    /// The power line frequency the notch filters remove, or whether to detect it
+    pub mains_frequency: MainsFrequency,
+    /// Also notch out the harmonics of the power line frequency ahead of the fatigue
+    /// analysis. Each pipeline's stages say for themselves.
+    pub notch_harmonics: bool,
+    /// Lower the flex threshold as a channel fatigues
+    pub adapt_threshold_to_fatigue: bool,
+    /// How far the threshold is lowered, as a fraction of the default, once a channel is
+    /// fully fatigued
+    pub fatigue_threshold_drop: f32,
+    /// Log a suggestion to take a break when a channel becomes fatigued
+    pub suggest_breaks: bool,
+}
+
+impl Default for MyoConfig {
+    fn default() -> Self {
+        Self {
+            spi_clock_speed: SPI_MAX_CLOCK_SPEED,
+            left_pipeline: LEFT_PIPELINE.to_vec(),
+            right_pipeline: RIGHT_PIPELINE.to_vec(),
+            mains_frequency: MAINS_FREQUENCY,
+            notch_harmonics: NOTCH_HARMONICS,
+            adapt_threshold_to_fatigue: ADAPT_THRESHOLD_TO_FATIGUE,
+            fatigue_threshold_drop: FATIGUE_THRESHOLD_DROP,
+            suggest_breaks: SUGGEST_BREAKS,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Side {
     Left = 0,
     Right = 1,
     Analog = 2,
 }
 
-pub struct MyoReader {
+pub struct MyoReader<SPI = spi::Spi> {
     new_data: bool,
     values: [u16; 3],
 
-    spi: spi::Spi,
+    spi: SPI,
 }
 
 impl MyoReader {
     pub fn init() -> Result<Self> {
-        let spi = spi::Spi::new(SPI_BUS, SPI_SLAVE_SELECT, SPI_MAX_CLOCK_SPEED, SPI_MODE)?;
-        Ok(Self {
+        Self::open(SPI_MAX_CLOCK_SPEED)
+    }
+
+    /// Opens the ADC with the SPI clock at `clock_speed` Hz
+    pub fn open(clock_speed: u32) -> Result<Self> {
+        let spi = spi::Spi::new(SPI_BUS, SPI_SLAVE_SELECT, clock_speed, SPI_MODE)?;
+        Ok(Self::new(spi))
+    }
+}
+
+impl<SPI, E> MyoReader<SPI>
+where
+    SPI: Transfer<u8, Error = E>,
+    WfpiError: From<E>,
+{
+    /// Creates a reader for an MCP3008 on an already configured SPI bus
+    pub fn new(spi: SPI) -> Self {
+        Self {
             new_data: false,
             values: [0u16; 3],
             spi,
-        })
+        }
     }
 
     fn update_channel(&mut self, channel: u8, differential: bool) -> Result<()> {
@@ -67,10 +158,8 @@ impl MyoReader {
 
         let command: u8 = (0x01 << 7) | (sgldiff << 6) | ((channel & 0x7) << 3);
 
-        let tx_buf = [command, 0x0, 0x0];
-        let mut rx_buf = [0_u8; 3];
-
-        self.spi.transfer(&mut rx_buf, &tx_buf)?;
+        let mut buffer = [command, 0x0, 0x0];
+        let rx_buf = self.spi.transfer(&mut buffer)?;
 
         let mut result = (rx_buf[0] as u16 & 0x01) << 9;
         result |= (rx_buf[1] as u16 & 0xFF) << 1;
@@ -90,6 +179,7 @@ impl MyoReader {
 
         self.update_channel(Side::Left as u8, false)?;
         self.update_channel(Side::Right as u8, false)?;
+        self.update_channel(Side::Analog as u8, false)?;
 
         Ok(())
     }
@@ -103,14 +193,33 @@ impl MyoReader {
     }
 }
 
-pub struct MyoParser {
-    reader: MyoReader,
-    left_emg: flappy::Flappy,
-    right_emg: flappy::Flappy,
+pub struct MyoParser<SPI = spi::Spi> {
+    reader: MyoReader<SPI>,
+    left_emg: Chain,
+    right_emg: Chain,
+    // Band-filtered copies of the raw signal for fatigue analysis, independent of the pipelines
+    left_analysis: EMGFilters,
+    right_analysis: EMGFilters,
+    left_fatigue: fatigue::FatigueMonitor,
+    right_fatigue: fatigue::FatigueMonitor,
+    left_fatigue_report: Option<FatigueReport>,
+    right_fatigue_report: Option<FatigueReport>,
+    left_quality: quality::QualityMonitor,
+    right_quality: quality::QualityMonitor,
+    left_quality_report: Option<QualityReport>,
+    right_quality_report: Option<QualityReport>,
+    mains: Option<mains::MainsDetector>,
+    /// The power line frequency the pipelines' notch filters are set to
+    notch_freq: NotchFrequency,
+    /// How far the flex threshold is lowered once a channel is fully fatigued, if at all
+    fatigue_threshold_drop: Option<f32>,
+    suggest_breaks: bool,
 
     left_val: i32,
+    left_flexed: bool,
     left_state: bool,
     right_val: i32,
+    right_flexed: bool,
     right_state: bool,
 
     analog_state: u16,
@@ -119,27 +228,175 @@ pub struct MyoParser {
 impl MyoParser {
     /// Creates a new MYO parser
     pub fn new() -> Result<Self> {
-        Ok(Self {
-            reader: MyoReader::init()?,
-            left_emg: flappy::Flappy::new(),
-            right_emg: flappy::Flappy::new(),
+        Self::with_config(&MyoConfig::default())
+    }
+
+    /// Creates a new MYO parser with the ADC and pipelines set up as `config` says
+    pub fn with_config(config: &MyoConfig) -> Result<Self> {
+        Ok(Self::with_reader(
+            MyoReader::open(config.spi_clock_speed)?,
+            config,
+        ))
+    }
+
+    /// Creates a new MYO parser that processes each channel with the given pipeline
+    pub fn with_pipelines(left: &[StageConfig], right: &[StageConfig]) -> Result<Self> {
+        Self::with_config(&MyoConfig {
+            left_pipeline: left.to_vec(),
+            right_pipeline: right.to_vec(),
+            ..MyoConfig::default()
+        })
+    }
+}
+
+impl<SPI, E> MyoParser<SPI>
+where
+    SPI: Transfer<u8, Error = E>,
+    WfpiError: From<E>,
+{
+    /// Creates a MYO parser that reads from `reader` and processes each channel as `config` says.
+    /// The SPI clock speed is up to `reader`.
+    pub fn with_reader(reader: MyoReader<SPI>, config: &MyoConfig) -> Self {
+        let (notch_freq, mains) = match config.mains_frequency {
+            MainsFrequency::Auto => {
+                log!(
+                    "Mains frequency: detecting, assuming {}Hz until then",
+                    mains::FALLBACK_FREQUENCY as u32
+                );
+                (
+                    mains::FALLBACK_FREQUENCY,
+                    Some(mains::MainsDetector::new(flappy::SAMPLE_RATE as u32 as f32)),
+                )
+            }
+            MainsFrequency::Fixed(freq) => {
+                log!("Mains frequency: {}Hz (manual override)", freq as u32);
+                (freq, None)
+            }
+        };
+
+        Self {
+            reader,
+            left_emg: Chain::builder()
+                .config(&config.left_pipeline, flappy::SAMPLE_RATE, notch_freq)
+                .build(),
+            right_emg: Chain::builder()
+                .config(&config.right_pipeline, flappy::SAMPLE_RATE, notch_freq)
+                .build(),
+            left_analysis: analysis_filters(notch_freq, config.notch_harmonics),
+            right_analysis: analysis_filters(notch_freq, config.notch_harmonics),
+            left_fatigue: fatigue::FatigueMonitor::new(flappy::SAMPLE_RATE as u32 as f32),
+            right_fatigue: fatigue::FatigueMonitor::new(flappy::SAMPLE_RATE as u32 as f32),
+            left_fatigue_report: None,
+            right_fatigue_report: None,
+            left_quality: quality::QualityMonitor::new(flappy::SAMPLE_RATE as u32 as f32),
+            right_quality: quality::QualityMonitor::new(flappy::SAMPLE_RATE as u32 as f32),
+            left_quality_report: None,
+            right_quality_report: None,
+            mains,
+            notch_freq,
+            fatigue_threshold_drop: if config.adapt_threshold_to_fatigue {
+                Some(config.fatigue_threshold_drop)
+            } else {
+                None
+            },
+            suggest_breaks: config.suggest_breaks,
             left_val: 0,
+            left_flexed: false,
             left_state: false,
             right_val: 0,
+            right_flexed: false,
             right_state: false,
 
             analog_state: 0,
-        })
+        }
+    }
+
+    /// Processes each channel with a new pipeline from here on, its filters starting afresh.
+    pub fn set_pipelines(&mut self, left: &[StageConfig], right: &[StageConfig]) {
+        self.left_emg = Chain::builder()
+            .config(left, flappy::SAMPLE_RATE, self.notch_freq)
+            .build();
+        self.right_emg = Chain::builder()
+            .config(right, flappy::SAMPLE_RATE, self.notch_freq)
+            .build();
     }
 
-    /// Updates the MYOs, returns true if there's new data, false otherwise
+    /// Updates the MYOs, returns true if there's new data or a new signal quality report,
+    /// false otherwise. A report can hold a channel released without any new data - a channel
+    /// stuck at a rail never reports any - so its state needs sending all the same.
     pub fn update(&mut self) -> Result<bool> {
         self.reader.update()?;
-        let res = self.reader.has_new_data();
+        let new_data = self.reader.has_new_data();
+
+        // Signal quality is judged on every reading, including unchanging ones - a channel
+        // stuck at a rail never reports new data, but we still need to notice it.
+        let mut res = new_data;
+        if let Some(report) = update_quality(
+            &mut self.left_quality,
+            &mut self.left_fatigue,
+            self.reader.get_value(Side::Left),
+            "Left",
+        ) {
+            self.left_quality_report = Some(report);
+            res = true;
+        }
+        if let Some(report) = update_quality(
+            &mut self.right_quality,
+            &mut self.right_fatigue,
+            self.reader.get_value(Side::Right),
+            "Right",
+        ) {
+            self.right_quality_report = Some(report);
+            res = true;
+        }
 
-        if res {
-            let (ls, lv) = self.left_emg.update(self.reader.get_value(Side::Left));
-            let (rs, rv) = self.right_emg.update(self.reader.get_value(Side::Right));
+        if let Some(detector) = self.mains.as_mut() {
+            // Hum is common to both electrodes, so listen to both at once
+            let raw = f32::from(self.reader.get_value(Side::Left))
+                + f32::from(self.reader.get_value(Side::Right));
+            if let Some(freq) = detector.update(raw) {
+                log!("Mains frequency: {}Hz (detected)", freq as u32);
+                self.notch_freq = freq;
+                self.left_emg.set_notch_frequency(freq);
+                self.right_emg.set_notch_frequency(freq);
+                self.left_analysis.set_notch_frequency(freq);
+                self.right_analysis.set_notch_frequency(freq);
+            }
+        }
+
+        if new_data {
+            let left_raw = self.reader.get_value(Side::Left);
+            let right_raw = self.reader.get_value(Side::Right);
+
+            let lv = self.left_emg.process(f64::from(left_raw));
+            let rv = self.right_emg.process(f64::from(right_raw));
+            let ls = lv > 0f64;
+            let rs = rv > 0f64;
+
+            let left_filtered = self.left_analysis.update(i32::from(left_raw));
+            let right_filtered = self.right_analysis.update(i32::from(right_raw));
+            if let Some(report) = update_fatigue(
+                &mut self.left_fatigue,
+                &mut self.left_emg,
+                left_filtered,
+                ls,
+                self.fatigue_threshold_drop,
+                self.suggest_breaks,
+                "Left",
+            ) {
+                self.left_fatigue_report = Some(report);
+            }
+            if let Some(report) = update_fatigue(
+                &mut self.right_fatigue,
+                &mut self.right_emg,
+                right_filtered,
+                rs,
+                self.fatigue_threshold_drop,
+                self.suggest_breaks,
+                "Right",
+            ) {
+                self.right_fatigue_report = Some(report);
+            }
 
             let analog_out = self.reader.get_value(Side::Analog);
             if analog_out != self.analog_state {
@@ -147,12 +404,17 @@ impl MyoParser {
                 self.analog_state = analog_out;
             }
 
-            self.left_val = lv;
-            self.left_state = ls;
-            self.right_val = rv;
-            self.right_state = rs;
+            self.left_val = lv as i32;
+            self.left_flexed = ls;
+            self.right_val = rv as i32;
+            self.right_flexed = rs;
         }
 
+        // Hold the outputs released while a channel can't be trusted. That includes the
+        // first quality window after startup (256ms), before there's anything to judge by.
+        self.left_state = self.left_flexed && self.left_quality.report().is_usable();
+        self.right_state = self.right_flexed && self.right_quality.report().is_usable();
+
         Ok(res)
     }
 
@@ -164,4 +426,105 @@ impl MyoParser {
             Side::Analog => (false, 0),
         }
     }
+
+    /// Gets the latest reading of the analog channel, from 0 to 1023
+    pub fn get_analog(&self) -> u16 {
+        self.analog_state
+    }
+
+    /// Returns the latest fatigue report for the given side, if there has been one since the last call
+    pub fn take_fatigue_report(&mut self, side: Side) -> Option<FatigueReport> {
+        match side {
+            Side::Left => self.left_fatigue_report.take(),
+            Side::Right => self.right_fatigue_report.take(),
+            Side::Analog => None,
+        }
+    }
+
+    /// Gets the most recent signal quality report for the given side
+    pub fn get_quality(&self, side: Side) -> QualityReport {
+        match side {
+            Side::Left => self.left_quality.report(),
+            Side::Right => self.right_quality.report(),
+            Side::Analog => QualityReport::default(),
+        }
+    }
+
+    /// Returns the latest signal quality report for the given side, if there has been one since the last call
+    pub fn take_quality_report(&mut self, side: Side) -> Option<QualityReport> {
+        match side {
+            Side::Left => self.left_quality_report.take(),
+            Side::Right => self.right_quality_report.take(),
+            Side::Analog => None,
+        }
+    }
+}
+
+fn update_quality(
+    monitor: &mut quality::QualityMonitor,
+    fatigue: &mut fatigue::FatigueMonitor,
+    raw: u16,
+    name: &str,
+) -> Option<QualityReport> {
+    let previous = monitor.report().status;
+    let report = monitor.update(raw)?;
+
+    if report.status != previous {
+        match report.status {
+            SignalQuality::Good => {
+                log!("{} myo signal is good", name);
+                // The electrodes were off, and may not be back on the same muscle
+                if previous == SignalQuality::Saturated || previous == SignalQuality::Flatline {
+                    fatigue.reset();
+                }
+            }
+            SignalQuality::Unknown => (),
+            status => log!(
+                "{} myo signal is {:?} - holding output released, check the electrodes",
+                name,
+                status
+            ),
+        }
+    }
+
+    Some(report)
+}
+
+fn analysis_filters(notch_freq: NotchFrequency, harmonics: bool) -> EMGFilters {
+    let mut filters = EMGFilters::new(flappy::SAMPLE_RATE, notch_freq, true, true, true);
+    filters.set_harmonic_filters(harmonics);
+    filters
+}
+
+fn update_fatigue(
+    monitor: &mut fatigue::FatigueMonitor,
+    pipeline: &mut Chain,
+    filtered: i32,
+    contracted: bool,
+    threshold_drop: Option<f32>,
+    suggest_breaks: bool,
+    name: &str,
+) -> Option<FatigueReport> {
+    let previous = monitor.report().level;
+    let report = monitor.update(filtered, contracted)?;
+
+    if let Some(drop) = threshold_drop {
+        let scale = 1f32 - drop * report.index;
+        pipeline.set_threshold_scale(f64::from(scale));
+    }
+
+    if report.level != previous {
+        log!(
+            "{} myo fatigue: {:?} (median frequency {:.0}Hz, baseline {:.0}Hz)",
+            name,
+            report.level,
+            report.median_frequency,
+            report.baseline_median_frequency
+        );
+        if suggest_breaks && report.level == FatigueLevel::Fatigued {
+            log!("{} muscle is fatigued - consider taking a break", name);
+        }
+    }
+
+    Some(report)
 }
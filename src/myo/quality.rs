@@ -0,0 +1,174 @@
+//! Judges whether a raw EMG channel is carrying muscle signal at all.
+//!
+//! A loose or disconnected electrode shows up on the MCP3008 in a few recognisable ways:
+//! the input floats to a rail (saturation), sits perfectly still (flatline), or picks up
+//! the power lines far more strongly than any muscle activity (mains hum). We look at short
+//! windows of raw 10-bit readings and check for each of these.
+
+use super::spectrum::PowerSpectrum;
+
+/// Number of raw readings in each analysis window
+const WINDOW_SIZE: usize = 256;
+
+const ADC_MAX: u16 = 1023;
+/// Readings this close to either rail count as saturated
+const SATURATION_MARGIN: u16 = 2;
+/// The largest fraction of saturated readings tolerated in a window
+const MAX_SATURATION_RATIO: f32 = 0.05;
+/// A window whose readings span no more than this many counts is considered flat
+const FLATLINE_RANGE: u16 = 2;
+/// Mains hum is tolerated until it carries more power than the rest of the EMG band...
+const MAX_MAINS_RATIO: f32 = 1f32;
+/// ...and has an amplitude of at least this many counts
+const MAINS_AMPLITUDE_FLOOR: f32 = 20f32;
+
+const EMG_BAND_LOW: f32 = 20f32;
+const EMG_BAND_HIGH: f32 = 150f32;
+/// Half-width of the band examined around each mains frequency
+const MAINS_HALF_WIDTH: f32 = 6f32;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SignalQuality {
+    /// Not enough readings yet to judge
+    Unknown,
+    Good,
+    /// The input is pinned at (or very near) 0 or 1023
+    Saturated,
+    /// The input isn't moving at all
+    Flatline,
+    /// Mains interference swamps the EMG band
+    Noisy,
+}
+
+impl Default for SignalQuality {
+    fn default() -> Self {
+        SignalQuality::Unknown
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct QualityReport {
+    pub status: SignalQuality,
+    /// 0 (unusable) to 1 (clean)
+    pub index: f32,
+    /// Fraction of readings at either rail
+    pub saturation_ratio: f32,
+    /// Difference between the largest and smallest reading
+    pub range: u16,
+    /// Mains-band power relative to the rest of the EMG band
+    pub mains_ratio: f32,
+}
+
+impl QualityReport {
+    /// Whether the channel can be trusted to drive outputs. Not until a first window has
+    /// been judged.
+    pub fn is_usable(&self) -> bool {
+        self.status == SignalQuality::Good
+    }
+}
+
+pub struct QualityMonitor {
+    spectrum: PowerSpectrum,
+    window: Vec<f32>,
+    saturated: usize,
+    min: u16,
+    max: u16,
+
+    report: QualityReport,
+}
+
+impl QualityMonitor {
+    pub fn new(sample_rate: f32) -> Self {
+        QualityMonitor {
+            spectrum: PowerSpectrum::new(WINDOW_SIZE, sample_rate),
+            window: Vec::with_capacity(WINDOW_SIZE),
+            saturated: 0,
+            min: ADC_MAX,
+            max: 0,
+            report: QualityReport::default(),
+        }
+    }
+
+    /// Feeds a raw ADC reading. Returns a new report whenever a full window has been analysed.
+    pub fn update(&mut self, raw: u16) -> Option<QualityReport> {
+        self.window.push(f32::from(raw));
+        if raw <= SATURATION_MARGIN || raw >= ADC_MAX - SATURATION_MARGIN {
+            self.saturated += 1;
+        }
+        if raw < self.min {
+            self.min = raw;
+        }
+        if raw > self.max {
+            self.max = raw;
+        }
+
+        if self.window.len() < WINDOW_SIZE {
+            return None;
+        }
+
+        self.spectrum.compute(&self.window);
+        let mains_power = self
+            .mains_band_power(50f32)
+            .max(self.mains_band_power(60f32));
+        let emg_power = self.spectrum.band_power(EMG_BAND_LOW, EMG_BAND_HIGH) - mains_power;
+        let mains_ratio = if emg_power > 0f32 {
+            mains_power / emg_power
+        } else if mains_power > 0f32 {
+            std::f32::INFINITY
+        } else {
+            0f32
+        };
+        let mains_amplitude = self
+            .spectrum
+            .band_amplitude(50f32 - MAINS_HALF_WIDTH, 50f32 + MAINS_HALF_WIDTH)
+            .max(
+                self.spectrum
+                    .band_amplitude(60f32 - MAINS_HALF_WIDTH, 60f32 + MAINS_HALF_WIDTH),
+            );
+
+        let saturation_ratio = self.saturated as f32 / WINDOW_SIZE as f32;
+        let range = self.max - self.min;
+
+        let status = if saturation_ratio > MAX_SATURATION_RATIO {
+            SignalQuality::Saturated
+        } else if range <= FLATLINE_RANGE {
+            SignalQuality::Flatline
+        } else if mains_ratio > MAX_MAINS_RATIO && mains_amplitude > MAINS_AMPLITUDE_FLOOR {
+            SignalQuality::Noisy
+        } else {
+            SignalQuality::Good
+        };
+
+        // Each score is 1 for a clean window and falls towards 0 as the window gets worse
+        let saturation_score =
+            1f32 - (saturation_ratio / (2f32 * MAX_SATURATION_RATIO)).min(1f32);
+        let flatline_score = (f32::from(range) / f32::from(4 * FLATLINE_RANGE)).min(1f32);
+        let mains_score = 1f32 / (1f32 + mains_ratio / MAX_MAINS_RATIO);
+        let index = saturation_score.min(flatline_score).min(2f32 * mains_score);
+
+        self.report = QualityReport {
+            status,
+            index: index.min(1f32),
+            saturation_ratio,
+            range,
+            mains_ratio,
+        };
+
+        self.window.clear();
+        self.saturated = 0;
+        self.min = ADC_MAX;
+        self.max = 0;
+
+        Some(self.report)
+    }
+
+    /// The most recent quality report.
+    pub fn report(&self) -> QualityReport {
+        self.report
+    }
+
+    fn mains_band_power(&self, freq: f32) -> f32 {
+        self.spectrum
+            .band_power(freq - MAINS_HALF_WIDTH, freq + MAINS_HALF_WIDTH)
+    }
+}
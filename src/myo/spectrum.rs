@@ -0,0 +1,113 @@
+//! A small power-spectrum estimator for windows of EMG samples.
+//!
+//! The windows we look at are short (a few hundred samples), so a plain DFT over
+//! precomputed twiddle factors is fast enough and keeps us free of an FFT dependency.
+
+use std::f32::consts::PI;
+
+pub struct PowerSpectrum {
+    size: usize,
+    sample_rate: f32,
+    window: Vec<f32>,
+    cos_table: Vec<f32>,
+    sin_table: Vec<f32>,
+    power: Vec<f32>,
+}
+
+impl PowerSpectrum {
+    /// Creates an estimator for windows of `size` samples taken at `sample_rate` Hz.
+    pub fn new(size: usize, sample_rate: f32) -> Self {
+        // Hann window, to keep leakage from the edges of the window out of the EMG band
+        let window = (0..size)
+            .map(|n| 0.5 - 0.5 * (2f32 * PI * n as f32 / size as f32).cos())
+            .collect();
+        let cos_table = (0..size)
+            .map(|n| (2f32 * PI * n as f32 / size as f32).cos())
+            .collect();
+        let sin_table = (0..size)
+            .map(|n| (2f32 * PI * n as f32 / size as f32).sin())
+            .collect();
+
+        PowerSpectrum {
+            size,
+            sample_rate,
+            window,
+            cos_table,
+            sin_table,
+            power: vec![0f32; size / 2 + 1],
+        }
+    }
+
+    /// Computes the one-sided power spectrum of `samples`, which must be exactly one window long.
+    pub fn compute(&mut self, samples: &[f32]) -> &[f32] {
+        debug_assert_eq!(samples.len(), self.size);
+
+        // Remove the DC offset so that it doesn't leak into the lowest bins
+        let mean = samples.iter().sum::<f32>() / self.size as f32;
+
+        for k in 0..self.power.len() {
+            let mut re = 0f32;
+            let mut im = 0f32;
+            for (n, sample) in samples.iter().enumerate() {
+                let x = (sample - mean) * self.window[n];
+                let idx = (k * n) % self.size;
+                re += x * self.cos_table[idx];
+                im -= x * self.sin_table[idx];
+            }
+            self.power[k] = re * re + im * im;
+        }
+
+        &self.power
+    }
+
+    /// The width of a single frequency bin, in Hz.
+    pub fn bin_width(&self) -> f32 {
+        self.sample_rate / self.size as f32
+    }
+
+    /// The total power of the most recently computed spectrum between `low` and `high` Hz (inclusive).
+    pub fn band_power(&self, low: f32, high: f32) -> f32 {
+        let bin_width = self.bin_width();
+        self.power
+            .iter()
+            .enumerate()
+            .filter(|(k, _)| {
+                let freq = *k as f32 * bin_width;
+                freq >= low && freq <= high
+            })
+            .map(|(_, p)| p)
+            .sum()
+    }
+
+    /// Estimates the peak amplitude of a sinusoid whose power lies between `low` and `high` Hz,
+    /// in the units of the input samples. The band should cover the main lobe of the Hann window
+    /// (at least one bin either side of the frequency of interest).
+    pub fn band_amplitude(&self, low: f32, high: f32) -> f32 {
+        // A sinusoid of amplitude A shows up in the Hann-windowed bins as A*N/4 in the centre
+        // and A*N/8 either side, so the total power in its main lobe is 1.5 * (A*N/4)^2.
+        4f32 / self.size as f32 * (self.band_power(low, high) / 1.5).sqrt()
+    }
+
+    /// The frequency that splits the power of the most recently computed spectrum between
+    /// `low` and `high` Hz into two equal halves.
+    pub fn median_frequency(&self, low: f32, high: f32) -> f32 {
+        let bin_width = self.bin_width();
+        let total = self.band_power(low, high);
+        if total <= 0f32 {
+            return 0f32;
+        }
+
+        let mut cumulative = 0f32;
+        for (k, p) in self.power.iter().enumerate() {
+            let freq = k as f32 * bin_width;
+            if freq < low || freq > high {
+                continue;
+            }
+            cumulative += p;
+            if cumulative >= total / 2f32 {
+                return freq;
+            }
+        }
+        high
+    }
+}
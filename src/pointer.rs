@@ -0,0 +1,287 @@
+//! Pointer control for computer access: flexes move the pointer or click, attention, flex
+//! strength or the analog channel sets how fast it goes, and resting the pointer on a spot
+//! can click it.
+
+use std::f64::consts::PI;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::error::*;
+use crate::myo::Side;
+use crate::shaping::Curve;
+
+/// The longest gap between updates the pointer is moved across, so that a stall doesn't
+/// make it jump
+const MAX_STEP: Duration = Duration::from_millis(100);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+/// What flexing a myo channel does to the pointer.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PointerAction {
+    /// Moves the pointer while held, `x` to the right and `y` down. Both at 1 moves
+    /// diagonally at the full speed.
+    Move {
+        x: f64,
+        y: f64,
+    },
+    /// Moves the pointer while held, in a direction that turns while no channel is moving
+    /// it, so that a single switch can reach the whole screen
+    Sweep,
+    /// Clicks a mouse button on each flex
+    Click(MouseButton),
+    Nothing,
+}
+
+/// What sets the pointer's speed, between `PointerConfig::min_speed` and `max_speed`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeedSource {
+    /// Always the full speed
+    Constant,
+    /// EEG attention, from 0 to 100
+    Attention,
+    /// How hard the moving channel is flexed, up to `PointerConfig::emg_full_speed`
+    Emg,
+    /// The ADC's analog channel, from 0 to 1023
+    Analog,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PointerConfig {
+    pub left: PointerAction,
+    pub right: PointerAction,
+    /// Pixels per second when a movement starts, and once it has been held for
+    /// `acceleration` with the speed source at its highest
+    pub min_speed: f64,
+    pub max_speed: f64,
+    pub acceleration: Duration,
+    /// How the speed builds up over `acceleration`
+    pub acceleration_curve: Curve,
+    pub speed_source: SpeedSource,
+    /// The myo reading that counts as flexing at full strength, for `SpeedSource::Emg`
+    pub emg_full_speed: i32,
+    /// How long the pointer has to rest after moving before it left-clicks, if it should
+    pub dwell: Option<Duration>,
+    /// How long `PointerAction::Sweep` takes to turn all the way round
+    pub sweep_period: Duration,
+}
+
+/// Where the pointer's movements and clicks go.
+pub trait PointerOutput {
+    fn move_by(&mut self, dx: i32, dy: i32) -> Result<()>;
+
+    fn click(&mut self, button: MouseButton) -> Result<()>;
+}
+
+/// What the TUI shows of the pointer.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PointerStatus {
+    /// Pixels per second, while moving
+    pub speed: f64,
+    /// The direction `PointerAction::Sweep` moves in, in degrees clockwise from right
+    pub heading: f64,
+    /// How far through the dwell time the pointer is, from 0 to 1, while it's counting
+    pub dwell: Option<f64>,
+}
+
+#[derive(Copy, Clone, Default)]
+struct Channel {
+    active: bool,
+    strength: i32,
+}
+
+/// Turns flexes and attention into pointer movements and clicks.
+pub struct Pointer {
+    config: PointerConfig,
+    channels: [Channel; 2],
+    attention: f64,
+    analog: f64,
+    /// When the current movement started
+    moving_since: Option<Duration>,
+    /// When the pointer last stopped, while it's waiting to dwell-click
+    resting_since: Option<Duration>,
+    /// Radians clockwise from right
+    heading: f64,
+    speed: f64,
+    /// Movement not yet made because it's less than a pixel
+    remainder: (f64, f64),
+    clicks: Vec<MouseButton>,
+    last_update: Option<Duration>,
+}
+
+impl Pointer {
+    pub fn new(config: PointerConfig) -> Self {
+        Self {
+            config,
+            channels: [Channel::default(); 2],
+            attention: 0f64,
+            analog: 0f64,
+            moving_since: None,
+            resting_since: None,
+            heading: 0f64,
+            speed: 0f64,
+            remainder: (0f64, 0f64),
+            clicks: vec![],
+            last_update: None,
+        }
+    }
+
+    pub fn config(&self) -> &PointerConfig {
+        &self.config
+    }
+
+    fn action(&self, side: Side) -> PointerAction {
+        match side {
+            Side::Left => self.config.left,
+            Side::Right => self.config.right,
+            Side::Analog => PointerAction::Nothing,
+        }
+    }
+
+    /// Takes whether the myo channel on `side` is flexed, and how hard.
+    pub fn set_channel(&mut self, side: Side, active: bool, strength: i32) {
+        if side == Side::Analog {
+            return;
+        }
+        if let PointerAction::Click(button) = self.action(side) {
+            if active && !self.channels[side as usize].active {
+                self.clicks.push(button);
+            }
+        }
+        let channel = &mut self.channels[side as usize];
+        channel.active = active;
+        channel.strength = strength;
+    }
+
+    pub fn set_attention(&mut self, attention: f64) {
+        self.attention = attention;
+    }
+
+    /// Takes the reading of the ADC's analog channel, from 0 to 1023.
+    pub fn set_analog(&mut self, analog: f64) {
+        self.analog = analog;
+    }
+
+    /// Forgets every input, stopping the pointer, as when its sources go quiet.
+    pub fn reset(&mut self) {
+        self.channels = [Channel::default(); 2];
+        self.moving_since = None;
+        self.resting_since = None;
+        self.speed = 0f64;
+        self.remainder = (0f64, 0f64);
+        self.clicks.clear();
+    }
+
+    /// Moves time on to `now` and sends the movement and clicks since the last update to
+    /// `output`.
+    pub fn update(&mut self, now: Duration, output: &mut dyn PointerOutput) -> Result<()> {
+        let step = self
+            .last_update
+            .map_or(Duration::from_secs(0), |last| {
+                now.checked_sub(last).unwrap_or_default()
+            })
+            .min(MAX_STEP)
+            .as_secs_f64();
+        self.last_update = Some(now);
+
+        let (mut x, mut y, mut strength) = (0f64, 0f64, 0);
+        for &side in &[Side::Left, Side::Right] {
+            let channel = self.channels[side as usize];
+            if !channel.active {
+                continue;
+            }
+            match self.action(side) {
+                PointerAction::Move { x: dx, y: dy } => {
+                    x += dx;
+                    y += dy;
+                }
+                PointerAction::Sweep => {
+                    x += self.heading.cos();
+                    y += self.heading.sin();
+                }
+                PointerAction::Click(_) | PointerAction::Nothing => continue,
+            }
+            strength = strength.max(channel.strength);
+        }
+        let moving = x.abs() > std::f64::EPSILON || y.abs() > std::f64::EPSILON;
+
+        if !moving && self.config.sweep_period > Duration::from_secs(0) {
+            self.heading += 2f64 * PI * step / self.config.sweep_period.as_secs_f64();
+            self.heading %= 2f64 * PI;
+        }
+
+        if moving {
+            let since = *self.moving_since.get_or_insert(now);
+            self.resting_since = None;
+            self.speed = self.speed_at(now - since, strength);
+            self.remainder.0 += x * self.speed * step;
+            self.remainder.1 += y * self.speed * step;
+        } else {
+            if self.moving_since.take().is_some() {
+                self.resting_since = Some(now);
+            }
+            self.speed = 0f64;
+        }
+
+        if let (Some(dwell), Some(since)) = (self.config.dwell, self.resting_since) {
+            if now - since >= dwell {
+                self.clicks.push(MouseButton::Left);
+                self.resting_since = None;
+            }
+        }
+
+        let (dx, dy) = (self.remainder.0.trunc(), self.remainder.1.trunc());
+        self.remainder.0 -= dx;
+        self.remainder.1 -= dy;
+        if dx.abs() >= 1f64 || dy.abs() >= 1f64 {
+            output.move_by(dx as i32, dy as i32)?;
+        }
+        for button in self.clicks.drain(..) {
+            output.click(button)?;
+        }
+        Ok(())
+    }
+
+    /// Pixels per second, `held` into a movement flexed at `strength`
+    fn speed_at(&self, held: Duration, strength: i32) -> f64 {
+        let config = &self.config;
+        let ramp = if config.acceleration > Duration::from_secs(0) {
+            (held.as_secs_f64() / config.acceleration.as_secs_f64()).min(1f64)
+        } else {
+            1f64
+        };
+        let source = match config.speed_source {
+            SpeedSource::Constant => 1f64,
+            SpeedSource::Attention => self.attention / 100f64,
+            SpeedSource::Emg => f64::from(strength) / f64::from(config.emg_full_speed.max(1)),
+            SpeedSource::Analog => self.analog / 1023f64,
+        }
+        .max(0f64)
+        .min(1f64);
+        let top_speed = config.min_speed + (config.max_speed - config.min_speed) * source;
+        config.min_speed + (top_speed - config.min_speed) * config.acceleration_curve.apply(ramp)
+    }
+
+    pub fn status(&self, now: Duration) -> PointerStatus {
+        PointerStatus {
+            speed: self.speed,
+            heading: self.heading.to_degrees(),
+            dwell: match (self.config.dwell, self.resting_since) {
+                (Some(dwell), Some(since)) => Some(
+                    (now.checked_sub(since).unwrap_or_default().as_secs_f64()
+                        / dwell.as_secs_f64())
+                    .min(1f64),
+                ),
+                _ => None,
+            },
+        }
+    }
+}
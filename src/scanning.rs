@@ -0,0 +1,180 @@
+//! Switch scanning, the assistive-technology pattern for reaching many outputs with one or
+//! two switches: a highlight moves through the outputs, and a switch selects the
+//! highlighted one.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanMethod {
+    /// One switch: the highlight moves on by itself every `ScanConfig::interval`, and the
+    /// switch selects
+    Auto,
+    /// Two switches: the first moves the highlight on, the second selects
+    Step,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanConfig {
+    /// The outputs scanned through, in order
+    pub outputs: Vec<String>,
+    pub method: ScanMethod,
+    /// How long the highlight stays on each output with `ScanMethod::Auto`
+    pub interval: Duration,
+    /// While a switch is held, how often stepping repeats, or the selected output is
+    /// released and pressed again. Without it, holding a switch does nothing more.
+    pub auto_repeat: Option<Duration>,
+}
+
+/// What a selection does to an output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScanEvent {
+    Press(String),
+    Release(String),
+}
+
+/// What the TUI shows of the scan.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanStatus {
+    pub outputs: Vec<String>,
+    pub highlighted: usize,
+    /// Whether the highlighted output is selected
+    pub selected: bool,
+}
+
+/// Moves a highlight through outputs and turns selections into presses and releases.
+pub struct Scanner {
+    config: ScanConfig,
+    highlighted: usize,
+    /// When the highlight last moved
+    moved: Duration,
+    step: bool,
+    select: bool,
+    /// When a held switch next repeats
+    repeat_at: Option<Duration>,
+    selected: Option<String>,
+}
+
+impl Scanner {
+    pub fn new(config: ScanConfig) -> Self {
+        Self {
+            config,
+            highlighted: 0,
+            moved: Duration::from_secs(0),
+            step: false,
+            select: false,
+            repeat_at: None,
+            selected: None,
+        }
+    }
+
+    pub fn config(&self) -> &ScanConfig {
+        &self.config
+    }
+
+    pub fn highlighted(&self) -> Option<&str> {
+        self.config
+            .outputs
+            .get(self.highlighted)
+            .map(String::as_str)
+    }
+
+    /// The output being held by a selection
+    pub fn selected(&self) -> Option<&str> {
+        self.selected.as_ref().map(String::as_str)
+    }
+
+    pub fn status(&self) -> ScanStatus {
+        ScanStatus {
+            outputs: self.config.outputs.clone(),
+            highlighted: self.highlighted,
+            selected: self.selected.is_some(),
+        }
+    }
+
+    /// Starts the scan over from the first output at `now`, forgetting the switches. The
+    /// caller releases any selected output.
+    pub fn reset(&mut self, now: Duration) {
+        self.highlighted = 0;
+        self.moved = now;
+        self.step = false;
+        self.select = false;
+        self.repeat_at = None;
+        self.selected = None;
+    }
+
+    fn advance(&mut self, now: Duration) {
+        if !self.config.outputs.is_empty() {
+            self.highlighted = (self.highlighted + 1) % self.config.outputs.len();
+        }
+        self.moved = now;
+    }
+
+    /// Takes whether the step and select switches are held at `now`, and returns what
+    /// happens to the outputs. With `ScanMethod::Auto` the step switch is ignored; call
+    /// regularly, whether or not the switches change, so that the highlight moves on.
+    pub fn update(&mut self, step: bool, select: bool, now: Duration) -> Vec<ScanEvent> {
+        let mut events = vec![];
+        let stepped = step && !self.step && self.config.method == ScanMethod::Step;
+        let selected = select && !self.select;
+        let released = !select && self.select;
+        self.step = step;
+        self.select = select;
+
+        if released {
+            if let Some(name) = self.selected.take() {
+                events.push(ScanEvent::Release(name));
+            }
+            // Give the user a full interval on the output they just used
+            self.moved = now;
+            self.repeat_at = None;
+        }
+
+        if selected {
+            if let Some(name) = self.highlighted().map(str::to_string) {
+                self.selected = Some(name.clone());
+                events.push(ScanEvent::Press(name));
+                self.repeat_at = self.config.auto_repeat.map(|repeat| now + repeat);
+            }
+        } else if let Some(name) = &self.selected {
+            if let (Some(repeat), Some(at)) = (self.config.auto_repeat, self.repeat_at) {
+                if now >= at {
+                    events.push(ScanEvent::Release(name.clone()));
+                    events.push(ScanEvent::Press(name.clone()));
+                    self.repeat_at = Some(now + repeat);
+                }
+            }
+        }
+
+        // The highlight stays put while an output is selected
+        if self.selected.is_some() {
+            return events;
+        }
+        match self.config.method {
+            ScanMethod::Auto => {
+                if self.config.interval > Duration::from_secs(0) {
+                    while now >= self.moved + self.config.interval {
+                        let moved = self.moved + self.config.interval;
+                        self.advance(moved);
+                    }
+                }
+            }
+            ScanMethod::Step => {
+                if stepped {
+                    self.advance(now);
+                    self.repeat_at = self.config.auto_repeat.map(|repeat| now + repeat);
+                } else if !step {
+                    self.repeat_at = None;
+                } else if let (Some(repeat), Some(at)) = (self.config.auto_repeat, self.repeat_at) {
+                    if now >= at {
+                        self.advance(now);
+                        self.repeat_at = Some(now + repeat);
+                    }
+                }
+            }
+        }
+        events
+    }
+}
@@ -0,0 +1,141 @@
+//! Sits between the collector and the springboard, holding back button presses and
+//! releases until each button's `PressTiming` allows them, so that every press the
+//! collector asks for reaches the XAC long enough to register, and none are lost.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::error::*;
+use crate::springboard::{ButtonConfig, PressTiming};
+
+/// How many presses and releases may wait for each button. When presses come faster than
+/// the button can make them, the latest are merged into one.
+const MAX_QUEUED_CHANGES: usize = 4;
+
+struct Schedule {
+    name: String,
+    timing: PressTiming,
+    pressed: bool,
+    /// When the button was last pressed or released
+    changed: Option<Duration>,
+    /// When the button was last pressed
+    last_press: Option<Duration>,
+    /// Presses and releases waiting to be made, oldest first
+    queue: VecDeque<bool>,
+}
+
+impl Schedule {
+    /// The earliest time the next queued change may be made
+    fn due(&self) -> Option<Duration> {
+        let &next = self.queue.front()?;
+        let held = if self.pressed {
+            self.timing.min_press
+        } else {
+            self.timing.min_release
+        };
+        let mut due = self
+            .changed
+            .map_or(Duration::from_secs(0), |changed| changed + held);
+        if next {
+            if let Some(last_press) = self.last_press {
+                due = due.max(last_press + self.timing.min_period);
+            }
+        }
+        Some(due)
+    }
+
+    fn apply(&mut self, pressed: bool, now: Duration) {
+        self.pressed = pressed;
+        self.changed = Some(now);
+        if pressed {
+            self.last_press = Some(now);
+        }
+    }
+}
+
+/// Schedules the presses and releases of a set of buttons.
+pub struct Scheduler {
+    buttons: Vec<Schedule>,
+}
+
+impl Scheduler {
+    /// Schedules the given buttons, which start released.
+    pub fn new(buttons: &[ButtonConfig]) -> Self {
+        Self {
+            buttons: buttons
+                .iter()
+                .map(|config| Schedule {
+                    name: config.name.clone(),
+                    timing: config.timing,
+                    pressed: false,
+                    changed: None,
+                    last_press: None,
+                    queue: VecDeque::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn button(&mut self, name: &str) -> Result<&mut Schedule> {
+        self.buttons
+            .iter_mut()
+            .find(|button| button.name == name)
+            .ok_or_else(|| WfpiError::UnknownOutput {
+                name: name.to_string(),
+            })
+    }
+
+    /// Whether the button called `name` has been pressed, as far as the changes made so far
+    /// go.
+    pub fn is_pressed(&self, name: &str) -> bool {
+        self.buttons
+            .iter()
+            .any(|button| button.name == name && button.pressed)
+    }
+
+    /// Asks for the button called `name` to be pressed or released once its timing allows.
+    pub fn request(&mut self, name: &str, pressed: bool) -> Result<()> {
+        let button = self.button(name)?;
+        let last = button.queue.back().cloned().unwrap_or(button.pressed);
+        if pressed == last {
+            return Ok(());
+        }
+        if button.queue.len() >= MAX_QUEUED_CHANGES {
+            // Leaves the queue ending in `pressed`, merging the last two presses into one
+            button.queue.pop_back();
+        } else {
+            button.queue.push_back(pressed);
+        }
+        Ok(())
+    }
+
+    /// Notes that the button called `name` was pressed or released at `now` outside the
+    /// schedule, as when it's released for safety, and drops the changes waiting for it.
+    pub fn set(&mut self, name: &str, pressed: bool, now: Duration) -> Result<()> {
+        let button = self.button(name)?;
+        button.queue.clear();
+        if pressed != button.pressed {
+            button.apply(pressed, now);
+        }
+        Ok(())
+    }
+
+    /// Takes the presses and releases that are due at `now`, in order, as they should be
+    /// written to the buttons.
+    pub fn take_due(&mut self, now: Duration) -> Vec<(String, bool)> {
+        let mut changes = vec![];
+        for button in self.buttons.iter_mut() {
+            while button.due().map_or(false, |due| due <= now) {
+                let pressed = button.queue.pop_front().unwrap();
+                button.apply(pressed, now);
+                changes.push((button.name.clone(), pressed));
+            }
+        }
+        changes
+    }
+
+    /// The earliest time a waiting press or release is due, if any are waiting.
+    pub fn next_due(&self) -> Option<Duration> {
+        self.buttons.iter().filter_map(Schedule::due).min()
+    }
+}
@@ -0,0 +1,194 @@
+//! Turns a raw input level into the value sent to an analog output: rescaling, deadzone,
+//! response curve, output range, smoothing and slew-rate limiting, applied in that order.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// The response curve applied to the input once it is normalised to [0, 1]. Every curve
+/// maps 0 to 0 and 1 to 1.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Curve {
+    Linear,
+    /// `(e^(kx) - 1) / (e^k - 1)`. A positive `k` gives finer control at the low end, a
+    /// negative one at the high end.
+    Exponential(f64),
+    /// A logistic curve centred on 0.5 with steepness `k`, for a soft on/off feel.
+    Sigmoid(f64),
+}
+
+impl Curve {
+    pub fn apply(self, x: f64) -> f64 {
+        match self {
+            Curve::Linear => x,
+            Curve::Exponential(k) => {
+                if k.abs() < std::f64::EPSILON {
+                    x
+                } else {
+                    (k * x).exp_m1() / k.exp_m1()
+                }
+            }
+            Curve::Sigmoid(k) => {
+                let logistic = |x: f64| 1f64 / (1f64 + (-k * (x - 0.5)).exp());
+                let (low, high) = (logistic(0f64), logistic(1f64));
+                if (high - low).abs() < std::f64::EPSILON {
+                    x
+                } else {
+                    (logistic(x) - low) / (high - low)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Shaping {
+    /// Inputs at or below `input_min` give `output_min`, at or above `input_max` give
+    /// `output_max`
+    pub input_min: f64,
+    pub input_max: f64,
+    /// Fraction of the input range, from the bottom, that gives `output_min`
+    pub deadzone: f64,
+    pub curve: Curve,
+    /// The output range, within [0, 100]
+    pub output_min: f64,
+    pub output_max: f64,
+    /// Time constant of the exponential smoothing, if any
+    pub smoothing: Option<Duration>,
+    /// The most the output may change per second, if limited
+    pub slew_rate: Option<f64>,
+}
+
+impl Shaping {
+    /// Passes values in [0, 100] through untouched.
+    pub const IDENTITY: Shaping = Shaping {
+        input_min: 0f64,
+        input_max: 100f64,
+        deadzone: 0f64,
+        curve: Curve::Linear,
+        output_min: 0f64,
+        output_max: 100f64,
+        smoothing: None,
+        slew_rate: None,
+    };
+
+    /// The stateless part of the shaping: everything but smoothing and slew limiting.
+    pub fn map(&self, input: f64) -> f64 {
+        let range = self.input_max - self.input_min;
+        let x = if range.abs() < std::f64::EPSILON {
+            if input >= self.input_max {
+                1f64
+            } else {
+                0f64
+            }
+        } else {
+            ((input - self.input_min) / range).max(0f64).min(1f64)
+        };
+
+        let x = if x <= self.deadzone {
+            0f64
+        } else {
+            (x - self.deadzone) / (1f64 - self.deadzone)
+        };
+        let x = self.curve.apply(x).max(0f64).min(1f64);
+
+        self.output_min + x * (self.output_max - self.output_min)
+    }
+}
+
+impl Default for Shaping {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// How close smoothing has to bring the output to its target, out of 100, for it to be
+/// taken as there.
+const SETTLED: f64 = 0.01;
+
+/// Applies a `Shaping` to a stream of inputs, keeping the state smoothing and slew
+/// limiting need.
+pub struct Shaper {
+    shaping: Shaping,
+    /// The time and value of the last output
+    last: Option<(Duration, f64)>,
+    /// The value the output is moving towards, until it gets there
+    target: Option<f64>,
+}
+
+impl Shaper {
+    pub fn new(shaping: Shaping) -> Self {
+        Self {
+            shaping,
+            last: None,
+            target: None,
+        }
+    }
+
+    pub fn shaping(&self) -> &Shaping {
+        &self.shaping
+    }
+
+    /// Shapes an input arriving at time `now`, and returns the value to output. The first
+    /// input after creating or resetting the shaper is only mapped, not smoothed or limited.
+    pub fn shape(&mut self, input: f64, now: Duration) -> f64 {
+        let target = self.shaping.map(input);
+        self.target = Some(target);
+        self.advance(target, now)
+    }
+
+    /// Moves the output on towards the last input at time `now`, for when smoothing or
+    /// slew limiting held it back, and returns the value to output. Returns `None` once
+    /// the output has got there.
+    pub fn tick(&mut self, now: Duration) -> Option<f64> {
+        let target = self.target?;
+        Some(self.advance(target, now))
+    }
+
+    fn advance(&mut self, target: f64, now: Duration) -> f64 {
+        let value = match self.last {
+            None => target,
+            Some((then, last)) => {
+                let elapsed = now.checked_sub(then).unwrap_or_default().as_secs_f64();
+
+                let smoothed = match self.shaping.smoothing {
+                    Some(tau) if tau > Duration::from_secs(0) => {
+                        let alpha = 1f64 - (-elapsed / tau.as_secs_f64()).exp();
+                        last + alpha * (target - last)
+                    }
+                    _ => target,
+                };
+
+                match self.shaping.slew_rate {
+                    Some(rate) => {
+                        let step = rate * elapsed;
+                        smoothed.max(last - step).min(last + step)
+                    }
+                    None => smoothed,
+                }
+            }
+        };
+
+        let value = if (value - target).abs() < SETTLED {
+            self.target = None;
+            target
+        } else {
+            value
+        };
+        self.last = Some((now, value));
+        value
+    }
+
+    /// Records a value written to the output by something else, such as the keyboard
+    /// override, so that shaping carries on from there.
+    pub fn hold(&mut self, value: f64, now: Duration) {
+        self.last = Some((now, value));
+        self.target = None;
+    }
+
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.target = None;
+    }
+}
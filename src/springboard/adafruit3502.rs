@@ -1,54 +1,108 @@
-use rppal::i2c;
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::blocking::i2c;
 
 use crate::error::*;
 
+use super::digipot::{self, Digipot};
+
 /// The default starting address
 pub const DS3502_I2CADDR_DEFAULT: u8 = 0x28;
 
 const DS3502_WIPER: u8 = 0x00;
 const DS3502_MODE: u8 = 0x02;
+/// How long the DS3502 takes to write its EEPROM, in milliseconds
+const DS3502_EEPROM_WRITE_TIME: u8 = 100;
 
-pub struct AdafruitDS3502 {
-    pub bus: i2c::I2c,
+pub struct AdafruitDS3502<I2C = rppal::i2c::I2c> {
+    pub bus: I2C,
+    address: u8,
 }
 
-impl AdafruitDS3502 {
-    pub fn new(bus: i2c::I2c) -> Self {
-        Self { bus }
+impl<I2C, E> AdafruitDS3502<I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::WriteRead<Error = E>,
+    WfpiError: From<E>,
+{
+    pub fn new(bus: I2C) -> Self {
+        Self {
+            bus,
+            address: DS3502_I2CADDR_DEFAULT,
+        }
     }
 
     pub fn begin(&mut self, i2c_addr: u8) -> Result<()> {
-        self.bus.set_slave_address(i2c_addr as u16)?;
+        self.address = i2c_addr;
 
         // Select mode
-        self.bus.smbus_write_byte(DS3502_MODE, 0x80)?;
+        self.write_register(DS3502_MODE, 0x80)?;
 
         Ok(())
     }
 
-    pub fn wiper(&self) -> Result<u8> {
-        let res = self.bus.smbus_read_byte(DS3502_WIPER)?;
-        Ok(res)
+    pub fn wiper(&mut self) -> Result<u8> {
+        let mut buffer = [0u8; 1];
+        self.bus
+            .write_read(self.address, &[DS3502_WIPER], &mut buffer)?;
+        Ok(buffer[0])
     }
 
-    pub fn set_wiper(&self, value: u8) -> Result<()> {
-        if value > 127 {
-            return Ok(()); // TODO: Should be error
-        }
-        self.bus.smbus_write_byte(DS3502_WIPER, value)?;
+    pub fn set_wiper(&mut self, value: u8) -> Result<()> {
+        digipot::check_wiper(value)?;
+        self.write_register(DS3502_WIPER, value)?;
         Ok(())
     }
 
-    pub fn set_wiper_default(&self, default: u8) -> Result<()> {
+    /// Stores the wiper position the DS3502 powers up at, and moves the wiper there.
+    pub fn set_wiper_default<D>(&mut self, default: u8, delay: &mut D) -> Result<()>
+    where
+        D: DelayMs<u8> + ?Sized,
+    {
+        digipot::check_wiper(default)?;
         // Set mode to write default on wiper write
-        self.bus.smbus_write_byte(DS3502_MODE, 0x00)?;
-        // Write the new default
-        self.bus.smbus_write_byte(DS3502_WIPER, default)?;
-        // TODO: delay to allow EEPROM write to IVR to finish? delay(100)
+        self.write_register(DS3502_MODE, 0x00)?;
+        // Write the new default, and give the EEPROM time to store it
+        self.write_register(DS3502_WIPER, default)?;
+        delay.delay_ms(DS3502_EEPROM_WRITE_TIME);
+        let stored = self.wiper();
+
+        // Set mode back to regular writes, even if the read failed
+        self.write_register(DS3502_MODE, 0x80)?;
 
-        // Set mode back to regular writes
-        self.bus.smbus_write_byte(DS3502_MODE, 0x80)?;
+        let stored = stored?;
+        if stored != default {
+            return Err(WfpiError::WiperMismatch {
+                expected: default,
+                actual: stored,
+            });
+        }
+        Ok(())
+    }
 
+    fn write_register(&mut self, register: u8, value: u8) -> Result<()> {
+        self.bus.write(self.address, &[register, value])?;
         Ok(())
     }
 }
+
+impl<I2C, E> Digipot for AdafruitDS3502<I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::WriteRead<Error = E>,
+    WfpiError: From<E>,
+{
+    fn name(&self) -> &'static str {
+        "DS3502"
+    }
+
+    fn wiper(&mut self) -> Result<u8> {
+        AdafruitDS3502::wiper(self)
+    }
+
+    fn set_wiper(&mut self, value: u8) -> Result<()> {
+        AdafruitDS3502::set_wiper(self, value)
+    }
+
+    fn set_default(&mut self, value: u8, delay: &mut dyn DelayMs<u8>) -> Result<bool> {
+        self.set_wiper_default(value, delay)?;
+        Ok(true)
+    }
+}
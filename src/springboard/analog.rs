@@ -0,0 +1,269 @@
+//! A common interface for the devices that can drive the XAC's analog inputs (triggers and
+//! thumbstick axes), and the logic for connecting to them at startup.
+
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::blocking::i2c::Read;
+use rppal::{i2c, spi};
+use serde::Deserialize;
+
+use crate::error::*;
+
+use super::calibration::Calibration;
+use super::digipot::CheckedDigipot;
+use super::mcp4922::Channel;
+use super::shared::SharedBus;
+use super::{adafruit3502, mcp4018, mcp4922, DIGIPOT_WRITES};
+
+pub const I2C_ANALOG_BUS: u8 = 1; // For the early model B Rev 1, bus 0 is selected. For every other model, bus 1 is used.
+                                  // This is tied to physical pin 3 and 5 (SDA and SCL)
+
+pub const SPI_MAX_CLOCK_SPEED: u32 = 1000;
+
+/// A device that sets one analog input on the XAC.
+pub trait AnalogOutput {
+    /// A short name for the device, for logging
+    fn name(&self) -> &'static str;
+
+    /// Update the output to a value in the range [0, 100]
+    fn set_value(&mut self, value: f64) -> Result<()>;
+
+    /// Leaves the device idle once it has been set to rest, for when the program stops
+    /// driving it. Most devices hold their last value and have nothing to do.
+    fn shutdown(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// How many writes have read back a different value than was written
+    fn mismatches(&self) -> u32 {
+        0
+    }
+
+    /// Stores the value the device powers up at, if it has non-volatile memory for it.
+    /// Returns whether it does.
+    fn set_default(&mut self, _value: f64, _delay: &mut dyn DelayMs<u8>) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+/// Which device drives an analog output.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnalogDevice {
+    /// Look for a digital potentiometer at the default addresses on the I2C bus, falling back
+    /// to channel A of the MCP4922 DAC. Only suitable for kits with a single analog output.
+    Probe,
+    /// Adafruit DS3502 digital potentiometer at the given I2C address
+    Ds3502 { address: u8 },
+    /// MCP4018 digital potentiometer at the given I2C address
+    Mcp4018 { address: u8 },
+    /// One channel of the MCP4922 DAC on SPI0, slave select 1
+    Mcp4922 { channel: Channel },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnalogConfig {
+    /// The name the mapping logic refers to the output by
+    pub name: String,
+    pub device: AnalogDevice,
+    /// The value the output returns to when nothing drives it: 0 for a trigger, 50 for a
+    /// centred thumbstick axis
+    pub rest: f64,
+}
+
+/// A named analog output, its calibration and the value it was last set to.
+pub struct Analog {
+    name: String,
+    rest: f64,
+    output: Box<dyn AnalogOutput>,
+    calibration: Option<Calibration>,
+    value: f64,
+}
+
+impl Analog {
+    pub fn new(name: &str, rest: f64, output: Box<dyn AnalogOutput>) -> Self {
+        Self {
+            name: name.to_string(),
+            rest,
+            output,
+            calibration: None,
+            value: rest,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The name of the device behind the output
+    pub fn device_name(&self) -> &'static str {
+        self.output.name()
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn rest(&self) -> f64 {
+        self.rest
+    }
+
+    pub fn mismatches(&self) -> u32 {
+        self.output.mismatches()
+    }
+
+    pub fn calibration(&self) -> Option<&Calibration> {
+        self.calibration.as_ref()
+    }
+
+    pub fn set_calibration(&mut self, calibration: Option<Calibration>) {
+        self.calibration = calibration;
+    }
+
+    /// Update the output to a value in the range [0, 100], as the XAC should see it
+    pub fn set(&mut self, value: f64) -> Result<()> {
+        self.output.set_value(self.device_value(value))?;
+        self.value = value;
+        Ok(())
+    }
+
+    /// Sets the device itself to a value in the range [0, 100], ignoring the calibration
+    pub fn set_raw(&mut self, value: f64) -> Result<()> {
+        self.output.set_value(value)?;
+        self.value = value;
+        Ok(())
+    }
+
+    /// Returns the output to rest and shuts the device down. A device that shuts down to
+    /// ground is only shut down when that is where it rests.
+    pub fn return_to_rest(&mut self) -> Result<()> {
+        self.set(self.rest)?;
+        if self.rest <= 0f64 {
+            self.output.shutdown()?;
+        }
+        Ok(())
+    }
+
+    /// Makes rest the value the device powers up at, so that the XAC sees the output at
+    /// rest even if the Pi goes down. Returns whether the device can store it.
+    pub fn persist_rest(&mut self, delay: &mut dyn DelayMs<u8>) -> Result<bool> {
+        let device_value = self.device_value(self.rest);
+        let stored = self.output.set_default(device_value, delay)?;
+        self.value = self.rest;
+        Ok(stored)
+    }
+
+    /// The device value that makes the XAC see `value`
+    fn device_value(&self, value: f64) -> f64 {
+        match &self.calibration {
+            Some(calibration) => calibration.device_value(value),
+            None => value,
+        }
+    }
+}
+
+/// The buses the analog devices hang off, opened the first time a device needs them so that
+/// several devices can share each one.
+pub struct Buses {
+    i2c_bus: u8,
+    spi_clock_speed: u32,
+    i2c: Option<SharedBus<i2c::I2c>>,
+    spi: Option<SharedBus<spi::Spi>>,
+}
+
+impl Default for Buses {
+    fn default() -> Self {
+        Self::with_settings(I2C_ANALOG_BUS, SPI_MAX_CLOCK_SPEED)
+    }
+}
+
+impl Buses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Buses that open I2C bus `i2c_bus`, and SPI with the clock at `spi_clock_speed` Hz
+    pub fn with_settings(i2c_bus: u8, spi_clock_speed: u32) -> Self {
+        Self {
+            i2c_bus,
+            spi_clock_speed,
+            i2c: None,
+            spi: None,
+        }
+    }
+
+    fn i2c(&mut self) -> Result<SharedBus<i2c::I2c>> {
+        if self.i2c.is_none() {
+            self.i2c = Some(SharedBus::new(i2c::I2c::with_bus(self.i2c_bus)?));
+        }
+        Ok(self.i2c.clone().unwrap())
+    }
+
+    fn spi(&mut self) -> Result<SharedBus<spi::Spi>> {
+        if self.spi.is_none() {
+            self.spi = Some(SharedBus::new(spi::Spi::new(
+                spi::Bus::Spi0,
+                spi::SlaveSelect::Ss1,
+                self.spi_clock_speed,
+                spi::Mode::Mode0,
+            )?));
+        }
+        Ok(self.spi.clone().unwrap())
+    }
+}
+
+impl AnalogDevice {
+    /// Connects to the device this describes.
+    pub fn open(self, buses: &mut Buses) -> Result<Box<dyn AnalogOutput>> {
+        match self {
+            AnalogDevice::Probe => probe(buses),
+            AnalogDevice::Ds3502 { address } => open_ds3502(buses.i2c()?, address),
+            AnalogDevice::Mcp4018 { address } => open_mcp4018(buses.i2c()?, address),
+            AnalogDevice::Mcp4922 { channel } => Ok(Box::new(mcp4922::Mcp4922Channel::new(
+                mcp4922::Mcp4922::new(buses.spi()?),
+                channel,
+            ))),
+        }
+    }
+}
+
+/// Picks the first device that answers on the I2C bus. The MCP4922 is write-only, so it
+/// can't be detected and is assumed when neither potentiometer responds.
+fn probe(buses: &mut Buses) -> Result<Box<dyn AnalogOutput>> {
+    if let Ok(mut bus) = buses.i2c() {
+        if responds(&mut bus, adafruit3502::DS3502_I2CADDR_DEFAULT) {
+            log!(
+                "Analog: found DS3502 at {:#04x}",
+                adafruit3502::DS3502_I2CADDR_DEFAULT
+            );
+            return open_ds3502(bus, adafruit3502::DS3502_I2CADDR_DEFAULT);
+        }
+        if responds(&mut bus, mcp4018::I2CADDR_DEFAULT) {
+            log!("Analog: found MCP4018 at {:#04x}", mcp4018::I2CADDR_DEFAULT);
+            return open_mcp4018(bus, mcp4018::I2CADDR_DEFAULT);
+        }
+    }
+
+    log!("Analog: no potentiometer found on I2C, assuming MCP4922");
+    AnalogDevice::Mcp4922 {
+        channel: Channel::CHA,
+    }
+    .open(buses)
+}
+
+/// Whether a device acknowledges a read at `address`.
+fn responds<I2C: Read>(bus: &mut I2C, address: u8) -> bool {
+    let mut buffer = [0u8; 1];
+    bus.read(address, &mut buffer).is_ok()
+}
+
+fn open_ds3502(bus: SharedBus<i2c::I2c>, address: u8) -> Result<Box<dyn AnalogOutput>> {
+    let mut output = adafruit3502::AdafruitDS3502::new(bus);
+    output.begin(address)?;
+    Ok(Box::new(CheckedDigipot::new(output, DIGIPOT_WRITES)))
+}
+
+fn open_mcp4018(bus: SharedBus<i2c::I2c>, address: u8) -> Result<Box<dyn AnalogOutput>> {
+    let mut output = mcp4018::Mcp4018::new(bus);
+    output.begin(address)?;
+    Ok(Box::new(CheckedDigipot::new(output, DIGIPOT_WRITES)))
+}
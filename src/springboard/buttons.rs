@@ -0,0 +1,150 @@
+//! Digital outputs wired to the XAC's 3.5mm switch jacks.
+
+use std::time::Duration;
+
+use embedded_hal::digital::v2::OutputPin;
+use serde::Deserialize;
+
+use crate::error::*;
+
+/// The level a button's pin is driven to while the button is pressed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActiveLevel {
+    Low,
+    High,
+}
+
+/// The switch jacks along the back of the Xbox Adaptive Controller.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum XacJack {
+    X1,
+    X2,
+    LeftStick,
+    LeftBumper,
+    LeftTrigger,
+    RightStick,
+    RightBumper,
+    RightTrigger,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+    A,
+    B,
+    X,
+    Y,
+    View,
+    Menu,
+}
+
+impl XacJack {
+    /// The label printed above the jack.
+    pub fn label(self) -> &'static str {
+        match self {
+            XacJack::X1 => "X1",
+            XacJack::X2 => "X2",
+            XacJack::LeftStick => "LS",
+            XacJack::LeftBumper => "LB",
+            XacJack::LeftTrigger => "LT",
+            XacJack::RightStick => "RS",
+            XacJack::RightBumper => "RB",
+            XacJack::RightTrigger => "RT",
+            XacJack::DpadUp => "D-pad Up",
+            XacJack::DpadDown => "D-pad Down",
+            XacJack::DpadLeft => "D-pad Left",
+            XacJack::DpadRight => "D-pad Right",
+            XacJack::A => "A",
+            XacJack::B => "B",
+            XacJack::X => "X",
+            XacJack::Y => "Y",
+            XacJack::View => "View",
+            XacJack::Menu => "Menu",
+        }
+    }
+}
+
+/// The shortest presses and releases a button makes. The XAC and the console polling it
+/// can miss a press that starts and ends between two of their frames.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PressTiming {
+    /// How long each press is held, at least
+    pub min_press: Duration,
+    /// How long the button stays released between presses, at least
+    pub min_release: Duration,
+    /// The shortest time from the start of one press to the start of the next, which caps
+    /// how fast the button can be toggled
+    pub min_period: Duration,
+}
+
+impl PressTiming {
+    /// Presses and releases are made as soon as they're asked for
+    pub const NONE: PressTiming = PressTiming {
+        min_press: Duration::from_millis(0),
+        min_release: Duration::from_millis(0),
+        min_period: Duration::from_millis(0),
+    };
+
+    /// Holds each press and release for at least three frames at 60 fps, and makes no
+    /// more than ten presses a second
+    pub const XAC: PressTiming = PressTiming {
+        min_press: Duration::from_millis(50),
+        min_release: Duration::from_millis(50),
+        min_period: Duration::from_millis(100),
+    };
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ButtonConfig {
+    /// The name the mapping logic refers to the button by
+    pub name: String,
+    /// BCM GPIO number
+    pub pin: u8,
+    pub active_level: ActiveLevel,
+    /// Which XAC jack the pin is wired to
+    pub jack: XacJack,
+    pub timing: PressTiming,
+}
+
+pub struct Button<P> {
+    config: ButtonConfig,
+    pin: P,
+    pressed: bool,
+}
+
+impl<P, E> Button<P>
+where
+    P: OutputPin<Error = E>,
+    WfpiError: From<E>,
+{
+    pub fn new(config: ButtonConfig, pin: P) -> Self {
+        Self {
+            config,
+            pin,
+            pressed: false,
+        }
+    }
+
+    pub fn config(&self) -> &ButtonConfig {
+        &self.config
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    pub fn set(&mut self, pressed: bool) -> Result<()> {
+        let high = match self.config.active_level {
+            ActiveLevel::High => pressed,
+            ActiveLevel::Low => !pressed,
+        };
+        if high {
+            self.pin.set_high()?;
+        } else {
+            self.pin.set_low()?;
+        }
+        self.pressed = pressed;
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+//! Corrects for the XAC not responding linearly to the devices driving its analog inputs.
+//!
+//! A calibration is measured by stepping an output through its range and noting what the
+//! XAC reports at each step, either by asking the operator or from a test rig. The
+//! measurements are stored per board, and used to pick the device value that makes the
+//! XAC report what was asked for.
+
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::*;
+
+use super::Analog;
+
+/// Where calibrations are stored, one file per output and device
+pub const CALIBRATION_DIR: &str = "calibration";
+/// How many steps the output's range is split into when calibrating
+pub const CALIBRATION_STEPS: u32 = 20;
+
+/// Pairs of device value and the value the XAC reported for it, both in [0, 100], sorted
+/// by device value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Calibration {
+    points: Vec<(f64, f64)>,
+}
+
+impl Calibration {
+    /// Builds a calibration from (device value, XAC value) measurements, in any order.
+    /// The XAC's response is taken to never fall as the device value rises, so readings
+    /// that dip below an earlier one are raised to it.
+    pub fn from_measurements(measurements: &[(f64, f64)]) -> Result<Self> {
+        let mut points = measurements.to_vec();
+        if points
+            .iter()
+            .any(|&(device, xac)| !device.is_finite() || !xac.is_finite())
+        {
+            return Err(invalid("measurements must be numbers"));
+        }
+        points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        points.dedup_by(|b, a| (a.0 - b.0).abs() < std::f64::EPSILON);
+
+        let mut highest = std::f64::NEG_INFINITY;
+        for point in points.iter_mut() {
+            highest = highest.max(point.1);
+            point.1 = highest;
+        }
+
+        match (points.first(), points.last()) {
+            (Some(first), Some(last)) if last.1 > first.1 => Ok(Self { points }),
+            _ => Err(invalid("the XAC's reading never changed")),
+        }
+    }
+
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /// The device value at which the XAC reports `target`, interpolating between the
+    /// measurements. Targets past either end of the measured range get the device value
+    /// at that end.
+    pub fn device_value(&self, target: f64) -> f64 {
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        if target <= first.1 {
+            return first.0;
+        }
+        if target >= last.1 {
+            // The first device value to reach the top, not whatever saturates beyond it
+            return self
+                .points
+                .iter()
+                .find(|point| point.1 >= last.1)
+                .map_or(last.0, |point| point.0);
+        }
+
+        for pair in self.points.windows(2) {
+            let ((low_device, low_xac), (high_device, high_xac)) = (pair[0], pair[1]);
+            if low_xac < target && target <= high_xac {
+                let fraction = (target - low_xac) / (high_xac - low_xac);
+                return low_device + fraction * (high_device - low_device);
+            }
+        }
+        last.0
+    }
+
+    /// Reads a calibration written by `save`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut measurements = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let values: Vec<f64> = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|e| invalid(format!("line {}: {}", i + 1, e)))?;
+            match values.as_slice() {
+                [device, xac] => measurements.push((*device, *xac)),
+                _ => {
+                    return Err(invalid(format!(
+                        "line {}: expected a device value and an XAC value",
+                        i + 1
+                    )))
+                }
+            }
+        }
+        Self::from_measurements(&measurements)
+    }
+
+    /// Loads the calibration at `path`, if there is one.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::parse(&fs::read_to_string(path)?).map(Some)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Calibration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# device value, XAC value")?;
+        for (device, xac) in &self.points {
+            writeln!(f, "{} {}", device, xac)?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid<S: Into<String>>(reason: S) -> WfpiError {
+    WfpiError::InvalidCalibration {
+        reason: reason.into(),
+    }
+}
+
+/// Where the calibration for an analog output is stored. Each output has one per device,
+/// so swapping boards doesn't apply the wrong table.
+pub fn path(analog: &Analog) -> PathBuf {
+    let board = format!("{}-{}", analog.name(), analog.device_name())
+        .to_lowercase()
+        .replace(' ', "-");
+    Path::new(CALIBRATION_DIR).join(format!("{}.cal", board))
+}
+
+/// Something that can tell what the XAC reports for an analog input.
+pub trait Gauge {
+    /// Reads the XAC's value, in [0, 100], while the device is at `device_value`. `None`
+    /// skips the step.
+    fn read(&mut self, device_value: f64) -> Result<Option<f64>>;
+}
+
+/// Asks the operator what the XAC reports, for example in the Xbox Accessories app.
+pub struct OperatorGauge<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> OperatorGauge<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+}
+
+impl<R: BufRead, W: Write> Gauge for OperatorGauge<R, W> {
+    fn read(&mut self, device_value: f64) -> Result<Option<f64>> {
+        loop {
+            write!(
+                self.output,
+                "Device at {:.0}%. What does the XAC report (0-100, blank to skip)? ",
+                device_value
+            )?;
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                return Ok(None);
+            }
+            match line.parse::<f64>() {
+                Ok(value) if value >= 0f64 && value <= 100f64 => return Ok(Some(value)),
+                _ => writeln!(self.output, "Please enter a number from 0 to 100")?,
+            }
+        }
+    }
+}
+
+/// Steps `analog` through its range without any calibration applied, reads the XAC at
+/// each step, and returns the output to rest.
+pub fn calibrate(analog: &mut Analog, gauge: &mut dyn Gauge, steps: u32) -> Result<Calibration> {
+    let mut measurements = vec![];
+    for step in 0..=steps {
+        let device_value = 100f64 * f64::from(step) / f64::from(steps);
+        analog.set_raw(device_value)?;
+        if let Some(xac) = gauge.read(device_value)? {
+            measurements.push((device_value, xac));
+        }
+    }
+    analog.return_to_rest()?;
+
+    Calibration::from_measurements(&measurements)
+}
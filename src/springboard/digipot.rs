@@ -0,0 +1,117 @@
+//! What the digital potentiometers have in common, and checked writes to their wipers.
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use crate::error::*;
+
+use super::AnalogOutput;
+
+/// The highest wiper position on the 7-bit potentiometers
+pub const WIPER_MAX: u8 = 127;
+
+/// A digital potentiometer with a readable wiper.
+pub trait Digipot {
+    /// A short name for the device, for logging
+    fn name(&self) -> &'static str;
+
+    fn wiper(&mut self) -> Result<u8>;
+
+    /// Moves the wiper to a position in [0, WIPER_MAX]
+    fn set_wiper(&mut self, value: u8) -> Result<()>;
+
+    /// Stores the position the wiper powers up at, if the device has non-volatile memory
+    /// for it. Returns whether it does.
+    fn set_default(&mut self, _value: u8, _delay: &mut dyn DelayMs<u8>) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+/// The wiper position for an output value in the range [0, 100]
+fn wiper_for(value: f64) -> u8 {
+    (value.max(0f64).min(100f64) * f64::from(WIPER_MAX) / 100f64) as u8
+}
+
+/// Fails with a typed error if `value` isn't a wiper position.
+pub fn check_wiper(value: u8) -> Result<()> {
+    if value > WIPER_MAX {
+        return Err(WfpiError::WiperOutOfRange {
+            value,
+            max: WIPER_MAX,
+        });
+    }
+    Ok(())
+}
+
+/// How writes to a potentiometer's wiper are checked.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WriteCheck {
+    /// Read the wiper back after each write and treat a different value as a failure
+    pub verify: bool,
+    /// How many more times a failed write is tried. A bus error most often means the
+    /// device didn't acknowledge, which loose wiring makes intermittent.
+    pub retries: u32,
+}
+
+/// A potentiometer as an analog output, with its writes checked.
+pub struct CheckedDigipot<D> {
+    pot: D,
+    check: WriteCheck,
+    mismatches: u32,
+}
+
+impl<D: Digipot> CheckedDigipot<D> {
+    pub fn new(pot: D, check: WriteCheck) -> Self {
+        Self {
+            pot,
+            check,
+            mismatches: 0,
+        }
+    }
+
+    pub fn pot(&mut self) -> &mut D {
+        &mut self.pot
+    }
+
+    fn write(&mut self, value: u8) -> Result<()> {
+        self.pot.set_wiper(value)?;
+        if self.check.verify {
+            let actual = self.pot.wiper()?;
+            if actual != value {
+                self.mismatches += 1;
+                return Err(WfpiError::WiperMismatch {
+                    expected: value,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<D: Digipot> AnalogOutput for CheckedDigipot<D> {
+    fn name(&self) -> &'static str {
+        self.pot.name()
+    }
+
+    fn set_value(&mut self, value: f64) -> Result<()> {
+        let wiper = wiper_for(value);
+
+        let mut result = self.write(wiper);
+        for _ in 0..self.check.retries {
+            match &result {
+                Ok(()) => break,
+                Err(e) => log!("{} write failed, retrying: {}", self.pot.name(), e),
+            }
+            result = self.write(wiper);
+        }
+        result
+    }
+
+    fn mismatches(&self) -> u32 {
+        self.mismatches
+    }
+
+    fn set_default(&mut self, value: f64, delay: &mut dyn DelayMs<u8>) -> Result<bool> {
+        self.pot.set_default(wiper_for(value), delay)
+    }
+}
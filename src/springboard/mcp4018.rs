@@ -1,37 +1,63 @@
-use rppal::i2c;
+use embedded_hal::blocking::i2c;
 
 use crate::error::*;
 
+use super::digipot::{self, Digipot};
+
 /// The default starting address
 pub const I2CADDR_DEFAULT: u8 = 0b0111110;
 
-const COMMAND_WRITE: u8 = 0x0;
-
-pub struct Mcp4018 {
-    pub bus: i2c::I2c,
+pub struct Mcp4018<I2C = rppal::i2c::I2c> {
+    pub bus: I2C,
+    address: u8,
 }
 
-impl Mcp4018 {
-    pub fn new(bus: i2c::I2c) -> Self {
-        Self { bus }
+impl<I2C, E> Mcp4018<I2C>
+where
+    I2C: i2c::Read<Error = E> + i2c::Write<Error = E>,
+    WfpiError: From<E>,
+{
+    pub fn new(bus: I2C) -> Self {
+        Self {
+            bus,
+            address: I2CADDR_DEFAULT,
+        }
     }
 
     pub fn begin(&mut self, i2c_addr: u8) -> Result<()> {
-        self.bus.set_slave_address(i2c_addr as u16)?;
+        self.address = i2c_addr;
 
         Ok(())
     }
 
-    pub fn wiper(&self) -> Result<u8> {
-        let res = self.bus.smbus_read_byte(COMMAND_WRITE)?;
-        Ok(res)
+    pub fn wiper(&mut self) -> Result<u8> {
+        // The MCP4018 has a single register, read and written without a command byte
+        let mut buffer = [0u8; 1];
+        self.bus.read(self.address, &mut buffer)?;
+        Ok(buffer[0])
     }
 
-    pub fn set_wiper(&self, value: u8) -> Result<()> {
-        if value > 127 {
-            return Ok(()); // TODO: Should be error
-        }
-        self.bus.smbus_write_byte(COMMAND_WRITE, value)?;
+    pub fn set_wiper(&mut self, value: u8) -> Result<()> {
+        digipot::check_wiper(value)?;
+        self.bus.write(self.address, &[value])?;
         Ok(())
     }
 }
+
+impl<I2C, E> Digipot for Mcp4018<I2C>
+where
+    I2C: i2c::Read<Error = E> + i2c::Write<Error = E>,
+    WfpiError: From<E>,
+{
+    fn name(&self) -> &'static str {
+        "MCP4018"
+    }
+
+    fn wiper(&mut self) -> Result<u8> {
+        Mcp4018::wiper(self)
+    }
+
+    fn set_wiper(&mut self, value: u8) -> Result<()> {
+        Mcp4018::set_wiper(self, value)
+    }
+}
@@ -1,13 +1,20 @@
-use rppal::spi;
+use embedded_hal::blocking::spi;
+use serde::Deserialize;
 
 use crate::error::*;
 
-pub struct Mcp4922 {
-    spi: spi::Spi,
+use super::AnalogOutput;
+
+pub struct Mcp4922<SPI = rppal::spi::Spi> {
+    spi: SPI,
 }
 
-impl Mcp4922 {
-    pub fn new(spi: spi::Spi) -> Self {
+impl<SPI, E> Mcp4922<SPI>
+where
+    SPI: spi::Write<u8, Error = E>,
+    WfpiError: From<E>,
+{
+    pub fn new(spi: SPI) -> Self {
         Mcp4922 { spi }
     }
 
@@ -18,10 +25,9 @@ impl Mcp4922 {
         };
         let buffer_bit = 0; // Unbuffered
         let output_gain_bit = 1; // 1x
-        let power_down_bit = 0; // Not power down
+        let active_bit = 1; // Output on (the SHDN bit is active low)
 
-        let config_bits =
-            channel_bit << 3 | buffer_bit << 2 | output_gain_bit << 1 | power_down_bit;
+        let config_bits = channel_bit << 3 | buffer_bit << 2 | output_gain_bit << 1 | active_bit;
         // Compose the first byte to send the DAC:
         // the 4 control bits, and the 4 most significant bits of the value
         let first_byte = config_bits << 4 | (value & 0xF00) >> 8;
@@ -34,6 +40,7 @@ impl Mcp4922 {
         Ok(())
     }
 
+    /// Powers down a channel until it is next set.
     pub fn shutdown(&mut self, channel: Channel) -> Result<()> {
         let channel_bit = match channel {
             Channel::CHA => 0,
@@ -41,10 +48,9 @@ impl Mcp4922 {
         };
         let buffer_bit = 0; // Unbuffered
         let output_gain_bit = 1; // 1x
-        let power_down_bit = 1; // Not power down
+        let active_bit = 0; // Shut down: the output is pulled to ground through 500k
 
-        let config_bits =
-            channel_bit << 3 | buffer_bit << 2 | output_gain_bit << 1 | power_down_bit;
+        let config_bits = channel_bit << 3 | buffer_bit << 2 | output_gain_bit << 1 | active_bit;
         // Compose the first byte to send the DAC:
         // the 4 control bits, and the 4 most significant bits of the value
         let first_byte = config_bits << 4;
@@ -58,7 +64,52 @@ impl Mcp4922 {
     }
 }
 
+/// One channel of an MCP4922, as an analog output. To drive both channels, give each
+/// its own `Mcp4922` over a `SharedBus`.
+pub struct Mcp4922Channel<SPI = rppal::spi::Spi> {
+    dac: Mcp4922<SPI>,
+    channel: Channel,
+}
+
+impl<SPI, E> Mcp4922Channel<SPI>
+where
+    SPI: spi::Write<u8, Error = E>,
+    WfpiError: From<E>,
+{
+    pub fn new(dac: Mcp4922<SPI>, channel: Channel) -> Self {
+        Self { dac, channel }
+    }
+}
+
+impl<SPI, E> AnalogOutput for Mcp4922Channel<SPI>
+where
+    SPI: spi::Write<u8, Error = E>,
+    WfpiError: From<E>,
+{
+    fn name(&self) -> &'static str {
+        match self.channel {
+            Channel::CHA => "MCP4922 channel A",
+            Channel::CHB => "MCP4922 channel B",
+        }
+    }
+
+    fn set_value(&mut self, value: f64) -> Result<()> {
+        // The DAC expects a value in the range [0, 4095]
+        self.dac.set_wiper(
+            self.channel,
+            (value.max(0f64).min(100f64) * 4095f64 / 100f64) as u16,
+        )
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.dac.shutdown(self.channel)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
 pub enum Channel {
+    #[serde(rename = "a")]
     CHA,
+    #[serde(rename = "b")]
     CHB,
 }
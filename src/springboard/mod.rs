@@ -4,122 +4,382 @@
 //! Since the Pi's analog audio output uses both PWM channels, using them and playing
 //! audio simultaneously may cause issues.
 
-cfg_if::cfg_if! {
-    if #[cfg(feature = "adafruit")] {
-        mod adafruit3502;
-        use rppal::i2c;
-    } else if #[cfg(feature = "mcp4018")] {
-        mod mcp4018;
-        use rppal::i2c;
-    } else {
-        mod mcp4922;
-        use rppal::spi;
-    }
-}
+pub mod adafruit3502;
+mod analog;
+mod buttons;
+pub mod calibration;
+pub mod digipot;
+pub mod mcp4018;
+pub mod mcp4922;
+mod shared;
+pub mod uinput;
+
+pub use analog::{Analog, AnalogConfig, AnalogDevice, AnalogOutput, Buses};
+pub use buttons::{ActiveLevel, Button, ButtonConfig, PressTiming, XacJack};
+pub use shared::SharedBus;
 
-use crate::Result;
+use std::fmt;
+use std::path::PathBuf;
 
+use crate::error::*;
+
+use calibration::{Calibration, Gauge};
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::digital::v2::OutputPin;
 use rppal::gpio;
+use uinput::{EventSink, UinputDevice, VirtualDevice, VirtualKey};
 
-// BCM GPIO 22 is tied to physical pin 15
-const GPIO_LEFT_BTN: u8 = 22;
-// BCM GPIO 23 is tied to phyiscal pin 16
-const GPIO_RIGHT_BTN: u8 = 23;
+/// The buttons wired to the XAC. Each needs a unique name and BCM GPIO pin; GPIO 2 and 3
+/// (I2C) and 7 to 11 (SPI) are taken by the analog outputs and the myo ADC.
+pub fn default_buttons() -> Vec<ButtonConfig> {
+    vec![
+        // BCM GPIO 22 is tied to physical pin 15
+        ButtonConfig {
+            name: "left".to_string(),
+            pin: 22,
+            active_level: ActiveLevel::Low,
+            jack: XacJack::LeftBumper,
+            timing: PressTiming::XAC,
+        },
+        // BCM GPIO 23 is tied to phyiscal pin 16
+        ButtonConfig {
+            name: "right".to_string(),
+            pin: 23,
+            active_level: ActiveLevel::Low,
+            jack: XacJack::RightBumper,
+            timing: PressTiming::XAC,
+        },
+    ]
+}
 
-const I2C_TRIGGER_BUS: u8 = 1; // For the early model B Rev 1, bus 0 is selected. For every other model, bus 1 is used.
-                               // This is tied to physical pin 3 and 5 (SDA and SCL)
+/// The analog outputs wired to the XAC, each with a unique name. Probing lets one build
+/// serve kits with different boards; kits with several outputs have to name each device,
+/// for example both channels of the MCP4922 for a thumbstick's X and Y axes:
+///
+/// ```text
+/// AnalogConfig { name: "stick_x".to_string(), device: AnalogDevice::Mcp4922 { channel: Channel::CHA }, rest: 50f64 },
+/// AnalogConfig { name: "stick_y".to_string(), device: AnalogDevice::Mcp4922 { channel: Channel::CHB }, rest: 50f64 },
+/// ```
+///
+/// or two digipots at different addresses for both triggers.
+pub fn default_analog_outputs() -> Vec<AnalogConfig> {
+    vec![AnalogConfig {
+        name: "trigger".to_string(),
+        device: AnalogDevice::Probe,
+        rest: 0f64,
+    }]
+}
 
-const SPI_MAX_CLOCK_SPEED: u32 = 1000;
+/// How writes to the digital potentiometers are checked. Reading each write back costs a
+/// second I2C transfer, but catches flaky wiring that would otherwise go unnoticed.
+pub const DIGIPOT_WRITES: digipot::WriteCheck = digipot::WriteCheck {
+    verify: true,
+    retries: 2,
+};
 
-cfg_if::cfg_if! {
-    if #[cfg(feature = "adafruit")] {
-        pub struct Springboard {
-            left_btn: gpio::OutputPin,
-            right_btn: gpio::OutputPin,
-            trigger: adafruit3502::AdafruitDS3502,
-        }
-    } else if #[cfg(feature = "mcp4018")] {
-        pub struct Springboard {
-            left_btn: gpio::OutputPin,
-            right_btn: gpio::OutputPin,
-            trigger: mcp4018::Mcp4018,
+/// What's wired to the XAC and the buses it hangs off. By default, `default_buttons()` and
+/// `default_analog_outputs()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wiring {
+    pub buttons: Vec<ButtonConfig>,
+    pub analog_outputs: Vec<AnalogConfig>,
+    /// The I2C bus the digital potentiometers are on
+    pub i2c_bus: u8,
+    /// The SPI clock speed the MCP4922 is written at, in Hz
+    pub spi_clock_speed: u32,
+}
+
+impl Default for Wiring {
+    fn default() -> Self {
+        Self {
+            buttons: default_buttons(),
+            analog_outputs: default_analog_outputs(),
+            i2c_bus: analog::I2C_ANALOG_BUS,
+            spi_clock_speed: analog::SPI_MAX_CLOCK_SPEED,
         }
-    } else {
-        pub struct Springboard {
-            left_btn: gpio::OutputPin,
-            right_btn: gpio::OutputPin,
-            trigger: mcp4922::Mcp4922,
+    }
+}
+
+/// What an output is set to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OutputValue {
+    /// Whether the button is pressed
+    Button(bool),
+    /// The analog output's value, from 0 to 100
+    Analog(f64),
+}
+
+impl fmt::Display for OutputValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputValue::Button(true) => write!(f, "pressed"),
+            OutputValue::Button(false) => write!(f, "released"),
+            OutputValue::Analog(value) => write!(f, "{:.0}", value),
         }
     }
 }
 
+/// The buttons and analog outputs wired to the XAC. Dropping it, including while
+/// unwinding from a panic, puts every output into its safe state.
+pub struct Springboard<P = gpio::OutputPin>
+where
+    P: OutputPin,
+    WfpiError: From<P::Error>,
+{
+    buttons: Vec<Button<P>>,
+    analogs: Vec<Analog>,
+}
+
 impl Springboard {
     pub fn init() -> Result<Self> {
+        Self::init_with(&Wiring::default())
+    }
+
+    /// Opens the outputs in `wiring`.
+    pub fn init_with(wiring: &Wiring) -> Result<Self> {
         let gpio_ = gpio::Gpio::new()?;
-        let left_btn = gpio_.get(GPIO_LEFT_BTN)?.into_output();
-        let right_btn = gpio_.get(GPIO_RIGHT_BTN)?.into_output();
-
-        cfg_if::cfg_if! {
-            if #[cfg(feature = "adafruit")] {
-                let trigger_bus = i2c::I2c::with_bus(I2C_TRIGGER_BUS)?;
-                let mut trigger = adafruit3502::AdafruitDS3502::new(trigger_bus);
-                trigger.begin(adafruit3502::DS3502_I2CADDR_DEFAULT)?;
-            } else if #[cfg(feature = "mcp4018")] {
-                let trigger_bus = i2c::I2c::with_bus(I2C_TRIGGER_BUS)?;
-                let mut trigger = mcp4018::Mcp4018::new(trigger_bus);
-                trigger.begin(mcp4018::I2CADDR_DEFAULT)?;
-            } else {
-                let trigger_bus = spi::Spi::new(
-                    spi::Bus::Spi0,
-                    spi::SlaveSelect::Ss1,
-                    SPI_MAX_CLOCK_SPEED,
-                    spi::Mode::Mode0,
-                )?;
-                let trigger = mcp4922::Mcp4922::new(trigger_bus);
+        let mut buttons = Vec::with_capacity(wiring.buttons.len());
+        for config in &wiring.buttons {
+            buttons.push(Button::new(
+                config.clone(),
+                gpio_.get(config.pin)?.into_output(),
+            ));
+        }
+
+        let mut buses = Buses::with_settings(wiring.i2c_bus, wiring.spi_clock_speed);
+        let mut analogs = Vec::with_capacity(wiring.analog_outputs.len());
+        for config in &wiring.analog_outputs {
+            let mut analog =
+                Analog::new(&config.name, config.rest, config.device.open(&mut buses)?);
+            log!(
+                "Analog output {}: using {}",
+                analog.name(),
+                analog.device_name()
+            );
+            let path = calibration::path(&analog);
+            if let Some(calibration) = Calibration::load(&path)? {
+                log!(
+                    "Analog output {}: calibrated from {}",
+                    analog.name(),
+                    path.display()
+                );
+                analog.set_calibration(Some(calibration));
             }
+            analogs.push(analog);
         }
 
-        Ok(Self {
-            left_btn,
-            right_btn,
-            trigger,
-        })
+        Self::new(buttons, analogs)
     }
+}
 
-    pub fn update_left_btn(&mut self, pressed: bool) {
-        self.left_btn.write(if pressed {
-            gpio::Level::Low
-        } else {
-            gpio::Level::High
-        });
+impl Springboard<VirtualKey> {
+    /// Creates a virtual gamepad or keyboard in place of the XAC, with the buttons in
+    /// `default_buttons()` and the analog outputs in `default_analog_outputs()`.
+    pub fn init_virtual(device: VirtualDevice) -> Result<Self> {
+        Self::init_virtual_with(device, &Wiring::default())
     }
 
-    pub fn update_right_btn(&mut self, pressed: bool) {
-        self.right_btn.write(if pressed {
-            gpio::Level::Low
-        } else {
-            gpio::Level::High
-        });
+    /// Creates a virtual gamepad or keyboard in place of the XAC, with the buttons and
+    /// analog outputs in `wiring`.
+    pub fn init_virtual_with(device: VirtualDevice, wiring: &Wiring) -> Result<Self> {
+        let (keys, axes) = uinput::codes(device, &wiring.buttons, &wiring.analog_outputs)?;
+        let sink = SharedBus::new(UinputDevice::create(device.name(), &keys, &[], &axes)?);
+        log!("Created {}", device.name());
+        Self::virtual_device_with(device, sink, wiring)
     }
+}
 
-    /// Update the trigger pull to a value in the range [0, 100]
-    pub fn update_trigger(&mut self, value: f64) -> Result<()> {
-        cfg_if::cfg_if! {
-            if #[cfg(feature = "adafruit")] {
-                // The wiper expects a value in the range [0, 127]
-                self.trigger.set_wiper((value * 127f64 / 100f64) as u8)?;
-                log!("Set wiper to: {}", self.trigger.wiper()?);
-            } else if #[cfg(feature = "mcp4018")] {
-                // The wiper expects a value in the range [0, 127]
-                self.trigger.set_wiper((value * 127f64 / 100f64) as u8)?;
-                log!("Set wiper to: {}", self.trigger.wiper()?);
-            } else {
-                // The wiper expects a value in the range [0, 4095]
-                self.trigger
-                    .set_wiper(mcp4922::Channel::CHA, (value * 4095f64 / 100f64) as u16)?;
-            }
+impl<S> Springboard<VirtualKey<S>>
+where
+    S: EventSink + Clone + 'static,
+{
+    /// Creates a springboard that sends the buttons in `default_buttons()` and the analog
+    /// outputs in `default_analog_outputs()` to a virtual `device` through `sink`.
+    pub fn virtual_device(device: VirtualDevice, sink: S) -> Result<Self> {
+        Self::virtual_device_with(device, sink, &Wiring::default())
+    }
+
+    /// Creates a springboard that sends the buttons and analog outputs in `wiring` to a
+    /// virtual `device` through `sink`.
+    pub fn virtual_device_with(device: VirtualDevice, sink: S, wiring: &Wiring) -> Result<Self> {
+        let buttons = wiring
+            .buttons
+            .iter()
+            .map(|config| {
+                // Virtual keys are pressed by driving them high, however the XAC is wired
+                let config = ButtonConfig {
+                    active_level: ActiveLevel::High,
+                    ..config.clone()
+                };
+                let key = VirtualKey::new(sink.clone(), device.key(config.jack));
+                Button::new(config, key)
+            })
+            .collect();
+        let mut analogs = Vec::with_capacity(wiring.analog_outputs.len());
+        for config in &wiring.analog_outputs {
+            let output = uinput::analog_output(device, &config.name, sink.clone())?;
+            analogs.push(Analog::new(&config.name, config.rest, output));
+        }
+        Self::new(buttons, analogs)
+    }
+}
+
+impl<P, E> Springboard<P>
+where
+    P: OutputPin<Error = E>,
+    WfpiError: From<E>,
+{
+    /// Creates a springboard with every button released and every analog output at rest.
+    /// Devices with no stored power-on value, like the MCP4018, start wherever their
+    /// hardware default puts them until this runs.
+    pub fn new(buttons: Vec<Button<P>>, analogs: Vec<Analog>) -> Result<Self> {
+        let mut springboard = Self { buttons, analogs };
+        springboard.release_all()?;
+        for analog in springboard.analogs.iter_mut() {
+            analog.set(analog.rest())?;
+        }
+        Ok(springboard)
+    }
+
+    pub fn buttons(&self) -> &[Button<P>] {
+        &self.buttons
+    }
+
+    pub fn button(&self, name: &str) -> Option<&Button<P>> {
+        self.buttons
+            .iter()
+            .find(|button| button.config().name == name)
+    }
+
+    /// Presses or releases the button called `name`.
+    pub fn update_button(&mut self, name: &str, pressed: bool) -> Result<()> {
+        match self
+            .buttons
+            .iter_mut()
+            .find(|button| button.config().name == name)
+        {
+            Some(button) => button.set(pressed),
+            None => Err(WfpiError::UnknownOutput {
+                name: name.to_string(),
+            }),
         }
+    }
 
+    pub fn release_all(&mut self) -> Result<()> {
+        for button in self.buttons.iter_mut() {
+            button.set(false)?;
+        }
         Ok(())
     }
+
+    pub fn analogs(&self) -> &[Analog] {
+        &self.analogs
+    }
+
+    pub fn analog(&self, name: &str) -> Option<&Analog> {
+        self.analogs.iter().find(|analog| analog.name() == name)
+    }
+
+    /// Update the analog output called `name` to a value in the range [0, 100]
+    pub fn update_analog(&mut self, name: &str, value: f64) -> Result<()> {
+        match self.analogs.iter_mut().find(|analog| analog.name() == name) {
+            Some(analog) => analog.set(value),
+            None => Err(WfpiError::UnknownOutput {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// What every output is set to, by name: the buttons, then the analog outputs.
+    pub fn values(&self) -> Vec<(String, OutputValue)> {
+        let buttons = self.buttons.iter().map(|button| {
+            let value = OutputValue::Button(button.is_pressed());
+            (button.config().name.clone(), value)
+        });
+        let analogs = self.analogs.iter().map(|analog| {
+            (
+                analog.name().to_string(),
+                OutputValue::Analog(analog.value()),
+            )
+        });
+        buttons.chain(analogs).collect()
+    }
+
+    /// How many writes to the analog outputs have read back a different value
+    pub fn mismatches(&self) -> u32 {
+        self.analogs.iter().map(Analog::mismatches).sum()
+    }
+
+    /// Calibrates the analog output called `name` against `gauge`, stores the calibration
+    /// for this board and starts using it. Returns where it was stored.
+    pub fn calibrate(&mut self, name: &str, gauge: &mut dyn Gauge) -> Result<PathBuf> {
+        let analog = self
+            .analogs
+            .iter_mut()
+            .find(|analog| analog.name() == name)
+            .ok_or_else(|| WfpiError::UnknownOutput {
+                name: name.to_string(),
+            })?;
+        let calibration = calibration::calibrate(analog, gauge, calibration::CALIBRATION_STEPS)?;
+        let path = calibration::path(analog);
+        calibration.save(&path)?;
+        analog.set_calibration(Some(calibration));
+        Ok(path)
+    }
+
+    /// Stores the rest value of the analog output called `name` as its device's power-on
+    /// value. Returns whether the device can store one.
+    pub fn persist_rest(&mut self, name: &str, delay: &mut dyn DelayMs<u8>) -> Result<bool> {
+        match self.analogs.iter_mut().find(|analog| analog.name() == name) {
+            Some(analog) => analog.persist_rest(delay),
+            None => Err(WfpiError::UnknownOutput {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Releases the button called `name`, or returns the analog output called `name` to
+    /// rest.
+    pub fn release(&mut self, name: &str) -> Result<()> {
+        if let Some(button) = self
+            .buttons
+            .iter_mut()
+            .find(|button| button.config().name == name)
+        {
+            return button.set(false);
+        }
+        match self.analogs.iter_mut().find(|analog| analog.name() == name) {
+            Some(analog) => analog.set(analog.rest()),
+            None => Err(WfpiError::UnknownOutput {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Releases every button and returns every analog output to rest, so that the XAC
+    /// isn't left holding an input. Carries on past errors to leave as much as possible
+    /// safe, and returns the first.
+    pub fn safe_state(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        for button in self.buttons.iter_mut() {
+            result = result.and(button.set(false));
+        }
+        for analog in self.analogs.iter_mut() {
+            result = result.and(analog.return_to_rest());
+        }
+        result
+    }
+}
+
+impl<P> Drop for Springboard<P>
+where
+    P: OutputPin,
+    WfpiError: From<P::Error>,
+{
+    fn drop(&mut self) {
+        match self.safe_state() {
+            Ok(()) => log!("Outputs released"),
+            Err(e) => log!("Failed to release outputs: {}", e),
+        }
+    }
 }
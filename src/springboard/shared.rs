@@ -0,0 +1,77 @@
+//! Lets several drivers talk over the same bus, e.g. two digipots on one I2C bus or both
+//! channels of the MCP4922 on one SPI device, or the buttons and axes of one virtual
+//! gamepad.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use embedded_hal::blocking::{i2c, spi};
+
+use crate::error;
+
+use super::uinput::EventSink;
+
+/// A bus that can be cloned and handed to several drivers. Drivers take turns, since the
+/// springboard only ever drives one output at a time.
+pub struct SharedBus<B> {
+    bus: Rc<RefCell<B>>,
+}
+
+impl<B> SharedBus<B> {
+    pub fn new(bus: B) -> Self {
+        Self {
+            bus: Rc::new(RefCell::new(bus)),
+        }
+    }
+}
+
+impl<B> Clone for SharedBus<B> {
+    fn clone(&self) -> Self {
+        Self {
+            bus: self.bus.clone(),
+        }
+    }
+}
+
+impl<B: i2c::Write> i2c::Write for SharedBus<B> {
+    type Error = B::Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        i2c::Write::write(&mut *self.bus.borrow_mut(), address, bytes)
+    }
+}
+
+impl<B: i2c::Read> i2c::Read for SharedBus<B> {
+    type Error = B::Error;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        i2c::Read::read(&mut *self.bus.borrow_mut(), address, buffer)
+    }
+}
+
+impl<B: i2c::WriteRead> i2c::WriteRead for SharedBus<B> {
+    type Error = B::Error;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        i2c::WriteRead::write_read(&mut *self.bus.borrow_mut(), address, bytes, buffer)
+    }
+}
+
+impl<B: spi::Write<u8>> spi::Write<u8> for SharedBus<B> {
+    type Error = B::Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        spi::Write::write(&mut *self.bus.borrow_mut(), words)
+    }
+}
+
+impl<B: EventSink> EventSink for SharedBus<B> {
+    fn emit(&mut self, kind: u16, code: u16, value: i32) -> error::Result<()> {
+        self.bus.borrow_mut().emit(kind, code, value)
+    }
+}
@@ -0,0 +1,583 @@
+//! A virtual gamepad or keyboard made through Linux's uinput module, so that the same
+//! buttons and analog outputs that drive the XAC can drive PC games and accessibility
+//! software directly, or be tried out on any Linux machine without extra hardware.
+//!
+//! Buttons become gamepad buttons or keys according to the XAC jack they're wired to, and
+//! analog outputs become absolute axes on the gamepad, or keys held while past half way on
+//! the keyboard. Opening `/dev/uinput` usually needs root, or a udev rule granting access.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+
+use embedded_hal::digital::v2::OutputPin;
+
+use crate::error::*;
+use crate::morse::{TextKey, TextOutput};
+use crate::pointer::{MouseButton, PointerOutput};
+
+use super::analog::{AnalogConfig, AnalogOutput};
+use super::buttons::{ButtonConfig, XacJack};
+use super::shared::SharedBus;
+
+const UINPUT_PATH: &str = "/dev/uinput";
+
+/// The value an axis reports at 100
+pub const AXIS_MAX: i32 = 1023;
+
+// Event types and codes, from linux/input-event-codes.h
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0;
+const BUS_VIRTUAL: u16 = 0x06;
+
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+
+pub const ABS_X: u16 = 0x00;
+pub const ABS_Y: u16 = 0x01;
+pub const ABS_Z: u16 = 0x02;
+pub const ABS_RX: u16 = 0x03;
+pub const ABS_RY: u16 = 0x04;
+pub const ABS_RZ: u16 = 0x05;
+
+/// The axis each analog output moves on the virtual gamepad, by output name. Triggers use
+/// the axes the xpad driver gives an Xbox controller's triggers.
+pub const GAMEPAD_AXES: &[(&str, u16)] = &[
+    ("trigger", ABS_RZ),
+    ("left_trigger", ABS_Z),
+    ("stick_x", ABS_X),
+    ("stick_y", ABS_Y),
+    ("right_stick_x", ABS_RX),
+    ("right_stick_y", ABS_RY),
+];
+
+/// The key each analog output holds on the virtual keyboard, by output name
+pub const KEYBOARD_ANALOG_KEYS: &[(&str, u16)] = &[("trigger", 45 /* KEY_X */)];
+
+// ioctls on /dev/uinput, from linux/uinput.h
+const UI_DEV_CREATE: u64 = 0x5501;
+const UI_DEV_DESTROY: u64 = 0x5502;
+const UI_DEV_SETUP: u64 = 0x405c_5503;
+const UI_ABS_SETUP: u64 = 0x401c_5504;
+const UI_SET_EVBIT: u64 = 0x4004_5564;
+const UI_SET_KEYBIT: u64 = 0x4004_5565;
+const UI_SET_RELBIT: u64 = 0x4004_5566;
+const UI_SET_ABSBIT: u64 = 0x4004_5567;
+
+const UINPUT_MAX_NAME_SIZE: usize = 80;
+
+#[repr(C)]
+struct InputId {
+    bustype: u16,
+    vendor: u16,
+    product: u16,
+    version: u16,
+}
+
+#[repr(C)]
+struct UinputSetup {
+    id: InputId,
+    name: [u8; UINPUT_MAX_NAME_SIZE],
+    ff_effects_max: u32,
+}
+
+#[repr(C)]
+struct UinputAbsSetup {
+    code: u16,
+    absinfo: libc::input_absinfo,
+}
+
+/// Which kind of device to present to the system.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VirtualDevice {
+    Gamepad,
+    Keyboard,
+}
+
+impl VirtualDevice {
+    pub fn name(self) -> &'static str {
+        match self {
+            VirtualDevice::Gamepad => "wfpi virtual gamepad",
+            VirtualDevice::Keyboard => "wfpi virtual keyboard",
+        }
+    }
+
+    /// The button or key pressed in place of the XAC jack `jack`
+    pub fn key(self, jack: XacJack) -> u16 {
+        match self {
+            VirtualDevice::Gamepad => gamepad_button(jack),
+            VirtualDevice::Keyboard => keyboard_key(jack),
+        }
+    }
+}
+
+/// The gamepad button for each jack, as the xpad driver reports an Xbox controller's
+fn gamepad_button(jack: XacJack) -> u16 {
+    match jack {
+        XacJack::A => 0x130,            // BTN_SOUTH
+        XacJack::B => 0x131,            // BTN_EAST
+        XacJack::X => 0x133,            // BTN_NORTH
+        XacJack::Y => 0x134,            // BTN_WEST
+        XacJack::LeftBumper => 0x136,   // BTN_TL
+        XacJack::RightBumper => 0x137,  // BTN_TR
+        XacJack::LeftTrigger => 0x138,  // BTN_TL2
+        XacJack::RightTrigger => 0x139, // BTN_TR2
+        XacJack::View => 0x13a,         // BTN_SELECT
+        XacJack::Menu => 0x13b,         // BTN_START
+        XacJack::LeftStick => 0x13d,    // BTN_THUMBL
+        XacJack::RightStick => 0x13e,   // BTN_THUMBR
+        XacJack::DpadUp => 0x220,       // BTN_DPAD_UP
+        XacJack::DpadDown => 0x221,     // BTN_DPAD_DOWN
+        XacJack::DpadLeft => 0x222,     // BTN_DPAD_LEFT
+        XacJack::DpadRight => 0x223,    // BTN_DPAD_RIGHT
+        XacJack::X1 => 0x2c0,           // BTN_TRIGGER_HAPPY1
+        XacJack::X2 => 0x2c1,           // BTN_TRIGGER_HAPPY2
+    }
+}
+
+const KEY_BACKSPACE: u16 = 14;
+const KEY_ENTER: u16 = 28;
+const KEY_LEFTSHIFT: u16 = 42;
+
+/// The key for each character typed as text, and whether it's shifted, on a US layout
+const TEXT_KEYS: &[(char, u16, bool)] = &[
+    ('A', 30, false),
+    ('B', 48, false),
+    ('C', 46, false),
+    ('D', 32, false),
+    ('E', 18, false),
+    ('F', 33, false),
+    ('G', 34, false),
+    ('H', 35, false),
+    ('I', 23, false),
+    ('J', 36, false),
+    ('K', 37, false),
+    ('L', 38, false),
+    ('M', 50, false),
+    ('N', 49, false),
+    ('O', 24, false),
+    ('P', 25, false),
+    ('Q', 16, false),
+    ('R', 19, false),
+    ('S', 31, false),
+    ('T', 20, false),
+    ('U', 22, false),
+    ('V', 47, false),
+    ('W', 17, false),
+    ('X', 45, false),
+    ('Y', 21, false),
+    ('Z', 44, false),
+    ('1', 2, false),
+    ('2', 3, false),
+    ('3', 4, false),
+    ('4', 5, false),
+    ('5', 6, false),
+    ('6', 7, false),
+    ('7', 8, false),
+    ('8', 9, false),
+    ('9', 10, false),
+    ('0', 11, false),
+    ('-', 12, false),
+    ('=', 13, false),
+    (';', 39, false),
+    ('\'', 40, false),
+    (',', 51, false),
+    ('.', 52, false),
+    ('/', 53, false),
+    ('!', 2, true),
+    ('@', 3, true),
+    ('(', 10, true),
+    (')', 11, true),
+    (':', 39, true),
+    ('"', 40, true),
+    ('?', 53, true),
+    (' ', 57, false),
+];
+
+/// The keyboard key for each jack, following common PC game bindings
+fn keyboard_key(jack: XacJack) -> u16 {
+    match jack {
+        XacJack::A => 57,            // KEY_SPACE
+        XacJack::B => 1,             // KEY_ESC
+        XacJack::X => 19,            // KEY_R
+        XacJack::Y => 15,            // KEY_TAB
+        XacJack::LeftBumper => 16,   // KEY_Q
+        XacJack::RightBumper => 18,  // KEY_E
+        XacJack::LeftTrigger => 44,  // KEY_Z
+        XacJack::RightTrigger => 45, // KEY_X
+        XacJack::View => 14,         // KEY_BACKSPACE
+        XacJack::Menu => 28,         // KEY_ENTER
+        XacJack::LeftStick => 42,    // KEY_LEFTSHIFT
+        XacJack::RightStick => 33,   // KEY_F
+        XacJack::DpadUp => 103,      // KEY_UP
+        XacJack::DpadDown => 108,    // KEY_DOWN
+        XacJack::DpadLeft => 105,    // KEY_LEFT
+        XacJack::DpadRight => 106,   // KEY_RIGHT
+        XacJack::X1 => 2,            // KEY_1
+        XacJack::X2 => 3,            // KEY_2
+    }
+}
+
+/// Somewhere to send input events.
+pub trait EventSink {
+    /// Sends one event. Events take effect once followed by `sync`.
+    fn emit(&mut self, kind: u16, code: u16, value: i32) -> Result<()>;
+
+    fn sync(&mut self) -> Result<()> {
+        self.emit(EV_SYN, SYN_REPORT, 0)
+    }
+}
+
+/// A device created through `/dev/uinput`, which goes away when dropped.
+pub struct UinputDevice {
+    file: File,
+    created: bool,
+}
+
+impl UinputDevice {
+    /// Creates a device called `name` with the given keys and relative and absolute axes.
+    pub fn create(name: &str, keys: &[u16], relative: &[u16], axes: &[u16]) -> Result<Self> {
+        let file = OpenOptions::new().write(true).open(UINPUT_PATH)?;
+        let mut device = Self {
+            file,
+            created: false,
+        };
+
+        if !keys.is_empty() {
+            device.ioctl_value(UI_SET_EVBIT, EV_KEY)?;
+        }
+        for &key in keys {
+            device.ioctl_value(UI_SET_KEYBIT, key)?;
+        }
+        if !relative.is_empty() {
+            device.ioctl_value(UI_SET_EVBIT, EV_REL)?;
+        }
+        for &axis in relative {
+            device.ioctl_value(UI_SET_RELBIT, axis)?;
+        }
+        if !axes.is_empty() {
+            device.ioctl_value(UI_SET_EVBIT, EV_ABS)?;
+        }
+        for &axis in axes {
+            device.ioctl_value(UI_SET_ABSBIT, axis)?;
+            let setup = UinputAbsSetup {
+                code: axis,
+                absinfo: libc::input_absinfo {
+                    value: 0,
+                    minimum: 0,
+                    maximum: AXIS_MAX,
+                    fuzz: 0,
+                    flat: 0,
+                    resolution: 0,
+                },
+            };
+            device.ioctl_ptr(UI_ABS_SETUP, &setup)?;
+        }
+
+        let mut setup = UinputSetup {
+            id: InputId {
+                bustype: BUS_VIRTUAL,
+                vendor: 0,
+                product: 0,
+                version: 1,
+            },
+            name: [0; UINPUT_MAX_NAME_SIZE],
+            ff_effects_max: 0,
+        };
+        // Leaves at least one NUL at the end
+        let length = name.len().min(UINPUT_MAX_NAME_SIZE - 1);
+        setup.name[..length].copy_from_slice(&name.as_bytes()[..length]);
+        device.ioctl_ptr(UI_DEV_SETUP, &setup)?;
+        device.ioctl_value(UI_DEV_CREATE, 0)?;
+        device.created = true;
+        Ok(device)
+    }
+
+    fn ioctl_value(&self, request: u64, value: u16) -> Result<()> {
+        let result = unsafe {
+            libc::ioctl(
+                self.file.as_raw_fd(),
+                request as _,
+                libc::c_int::from(value),
+            )
+        };
+        if result < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    fn ioctl_ptr<T>(&self, request: u64, value: &T) -> Result<()> {
+        let result = unsafe { libc::ioctl(self.file.as_raw_fd(), request as _, value as *const T) };
+        if result < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+}
+
+impl EventSink for UinputDevice {
+    fn emit(&mut self, kind: u16, code: u16, value: i32) -> Result<()> {
+        let event = libc::input_event {
+            time: libc::timeval {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            type_: kind,
+            code,
+            value,
+        };
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                &event as *const libc::input_event as *const u8,
+                std::mem::size_of::<libc::input_event>(),
+            )
+        };
+        self.file.write_all(bytes)?;
+        Ok(())
+    }
+}
+
+impl Drop for UinputDevice {
+    fn drop(&mut self) {
+        if !self.created {
+            return;
+        }
+        if let Err(e) = self.ioctl_value(UI_DEV_DESTROY, 0) {
+            log!("Failed to remove virtual device: {}", e);
+        }
+    }
+}
+
+/// A button or key on a virtual device, driven like a pin: high is pressed.
+pub struct VirtualKey<S = SharedBus<UinputDevice>> {
+    sink: S,
+    code: u16,
+}
+
+impl<S: EventSink> VirtualKey<S> {
+    pub fn new(sink: S, code: u16) -> Self {
+        Self { sink, code }
+    }
+
+    fn set(&mut self, value: i32) -> Result<()> {
+        self.sink.emit(EV_KEY, self.code, value)?;
+        self.sink.sync()
+    }
+}
+
+impl<S: EventSink> OutputPin for VirtualKey<S> {
+    type Error = WfpiError;
+
+    fn set_high(&mut self) -> Result<()> {
+        self.set(1)
+    }
+
+    fn set_low(&mut self) -> Result<()> {
+        self.set(0)
+    }
+}
+
+/// An absolute axis on a virtual gamepad.
+pub struct VirtualAxis<S = SharedBus<UinputDevice>> {
+    sink: S,
+    code: u16,
+}
+
+impl<S: EventSink> VirtualAxis<S> {
+    pub fn new(sink: S, code: u16) -> Self {
+        Self { sink, code }
+    }
+}
+
+impl<S: EventSink> AnalogOutput for VirtualAxis<S> {
+    fn name(&self) -> &'static str {
+        "virtual axis"
+    }
+
+    fn set_value(&mut self, value: f64) -> Result<()> {
+        let value = (value.max(0f64).min(100f64) / 100f64 * f64::from(AXIS_MAX)).round();
+        self.sink.emit(EV_ABS, self.code, value as i32)?;
+        self.sink.sync()
+    }
+}
+
+/// A key on a virtual keyboard standing in for an analog output, held while the output is
+/// past half way.
+pub struct VirtualAnalogKey<S = SharedBus<UinputDevice>> {
+    key: VirtualKey<S>,
+    pressed: Option<bool>,
+}
+
+impl<S: EventSink> VirtualAnalogKey<S> {
+    pub fn new(sink: S, code: u16) -> Self {
+        Self {
+            key: VirtualKey::new(sink, code),
+            pressed: None,
+        }
+    }
+}
+
+impl<S: EventSink> AnalogOutput for VirtualAnalogKey<S> {
+    fn name(&self) -> &'static str {
+        "virtual key"
+    }
+
+    fn set_value(&mut self, value: f64) -> Result<()> {
+        let pressed = value > 50f64;
+        if self.pressed != Some(pressed) {
+            self.key.set(if pressed { 1 } else { 0 })?;
+            self.pressed = Some(pressed);
+        }
+        Ok(())
+    }
+}
+
+/// The buttons of a virtual mouse
+fn mouse_button(button: MouseButton) -> u16 {
+    match button {
+        MouseButton::Left => 0x110,   // BTN_LEFT
+        MouseButton::Right => 0x111,  // BTN_RIGHT
+        MouseButton::Middle => 0x112, // BTN_MIDDLE
+    }
+}
+
+/// A virtual mouse for pointer control.
+pub struct VirtualMouse<S = UinputDevice> {
+    sink: S,
+}
+
+impl VirtualMouse {
+    pub fn create() -> Result<Self> {
+        let buttons: Vec<_> = [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+            .iter()
+            .map(|&button| mouse_button(button))
+            .collect();
+        let device = UinputDevice::create("wfpi virtual mouse", &buttons, &[REL_X, REL_Y], &[])?;
+        Ok(Self::new(device))
+    }
+}
+
+impl<S: EventSink> VirtualMouse<S> {
+    pub fn new(sink: S) -> Self {
+        Self { sink }
+    }
+}
+
+impl<S: EventSink> PointerOutput for VirtualMouse<S> {
+    fn move_by(&mut self, dx: i32, dy: i32) -> Result<()> {
+        self.sink.emit(EV_REL, REL_X, dx)?;
+        self.sink.emit(EV_REL, REL_Y, dy)?;
+        self.sink.sync()
+    }
+
+    fn click(&mut self, button: MouseButton) -> Result<()> {
+        let code = mouse_button(button);
+        self.sink.emit(EV_KEY, code, 1)?;
+        self.sink.sync()?;
+        self.sink.emit(EV_KEY, code, 0)?;
+        self.sink.sync()
+    }
+}
+
+/// A virtual keyboard for typing text.
+pub struct VirtualKeyboard<S = UinputDevice> {
+    sink: S,
+}
+
+impl VirtualKeyboard {
+    pub fn create() -> Result<Self> {
+        let mut keys: Vec<_> = TEXT_KEYS.iter().map(|&(_, code, _)| code).collect();
+        keys.extend_from_slice(&[KEY_LEFTSHIFT, KEY_BACKSPACE, KEY_ENTER]);
+        keys.sort();
+        keys.dedup();
+        let device = UinputDevice::create("wfpi virtual text keyboard", &keys, &[], &[])?;
+        Ok(Self::new(device))
+    }
+}
+
+impl<S: EventSink> VirtualKeyboard<S> {
+    pub fn new(sink: S) -> Self {
+        Self { sink }
+    }
+
+    fn tap(&mut self, code: u16, shift: bool) -> Result<()> {
+        if shift {
+            self.sink.emit(EV_KEY, KEY_LEFTSHIFT, 1)?;
+        }
+        self.sink.emit(EV_KEY, code, 1)?;
+        self.sink.sync()?;
+        self.sink.emit(EV_KEY, code, 0)?;
+        if shift {
+            self.sink.emit(EV_KEY, KEY_LEFTSHIFT, 0)?;
+        }
+        self.sink.sync()
+    }
+}
+
+impl<S: EventSink> TextOutput for VirtualKeyboard<S> {
+    fn type_key(&mut self, key: TextKey) -> Result<()> {
+        let c = match key {
+            TextKey::Char(c) => c.to_ascii_uppercase(),
+            TextKey::Space => ' ',
+            TextKey::Backspace => return self.tap(KEY_BACKSPACE, false),
+            TextKey::Enter => return self.tap(KEY_ENTER, false),
+        };
+        let &(_, code, shift) =
+            TEXT_KEYS
+                .iter()
+                .find(|&&(key, _, _)| key == c)
+                .ok_or_else(|| WfpiError::UnknownOutput {
+                    name: format!("key for {:?}", c),
+                })?;
+        self.tap(code, shift)
+    }
+}
+
+/// The keys and axes a virtual `device` needs for the given buttons and analog outputs.
+pub fn codes(
+    device: VirtualDevice,
+    buttons: &[ButtonConfig],
+    analogs: &[AnalogConfig],
+) -> Result<(Vec<u16>, Vec<u16>)> {
+    let mut keys: Vec<u16> = buttons
+        .iter()
+        .map(|config| device.key(config.jack))
+        .collect();
+    let mut axes = vec![];
+    for config in analogs {
+        match device {
+            VirtualDevice::Gamepad => axes.push(lookup(GAMEPAD_AXES, &config.name)?),
+            VirtualDevice::Keyboard => keys.push(lookup(KEYBOARD_ANALOG_KEYS, &config.name)?),
+        }
+    }
+    Ok((keys, axes))
+}
+
+/// The output standing in for the analog output called `name` on a virtual `device`.
+pub fn analog_output<S: EventSink + 'static>(
+    device: VirtualDevice,
+    name: &str,
+    sink: S,
+) -> Result<Box<dyn AnalogOutput>> {
+    Ok(match device {
+        VirtualDevice::Gamepad => Box::new(VirtualAxis::new(sink, lookup(GAMEPAD_AXES, name)?)),
+        VirtualDevice::Keyboard => Box::new(VirtualAnalogKey::new(
+            sink,
+            lookup(KEYBOARD_ANALOG_KEYS, name)?,
+        )),
+    })
+}
+
+fn lookup(table: &[(&str, u16)], name: &str) -> Result<u16> {
+    table
+        .iter()
+        .find(|(output, _)| *output == name)
+        .map(|&(_, code)| code)
+        .ok_or_else(|| WfpiError::UnknownOutput {
+            name: name.to_string(),
+        })
+}
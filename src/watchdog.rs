@@ -0,0 +1,99 @@
+//! Notices when something that should be updated regularly has gone quiet.
+
+use std::time::Duration;
+
+struct Entry<K> {
+    key: K,
+    timeout: Duration,
+    /// When the key was last fed, if ever
+    last: Option<Duration>,
+    stale: bool,
+}
+
+/// Tracks when each of a set of keys was last fed, and reports each one once when it goes
+/// longer than its timeout without being fed. Keys that were never fed are never stale.
+pub struct Watchdog<K> {
+    entries: Vec<Entry<K>>,
+}
+
+impl<K: Clone + PartialEq> Watchdog<K> {
+    pub fn new() -> Self {
+        Self { entries: vec![] }
+    }
+
+    /// Starts watching `key`, which goes stale after `timeout` without being fed. A key
+    /// that's already watched takes the new timeout and keeps when it was last fed.
+    pub fn watch(&mut self, key: K, timeout: Duration) {
+        match self.entries.iter_mut().find(|entry| entry.key == key) {
+            Some(entry) => entry.timeout = timeout,
+            None => self.entries.push(Entry {
+                key,
+                timeout,
+                last: None,
+                stale: false,
+            }),
+        }
+    }
+
+    /// Stops watching the keys that `keep` rejects.
+    pub fn retain(&mut self, keep: impl Fn(&K) -> bool) {
+        self.entries.retain(|entry| keep(&entry.key));
+    }
+
+    /// Records that `key` was updated at `now`, and returns whether it had gone stale.
+    pub fn feed<Q>(&mut self, key: Q, now: Duration) -> bool
+    where
+        K: PartialEq<Q>,
+    {
+        match self.entries.iter_mut().find(|entry| entry.key == key) {
+            Some(entry) => {
+                entry.last = Some(now);
+                let was_stale = entry.stale;
+                entry.stale = false;
+                was_stale
+            }
+            None => false,
+        }
+    }
+
+    /// The keys that have gone stale since the last call.
+    pub fn expire(&mut self, now: Duration) -> Vec<K> {
+        let mut expired = vec![];
+        for entry in self.entries.iter_mut() {
+            if let Some(last) = entry.last {
+                if !entry.stale && now.checked_sub(last).unwrap_or_default() > entry.timeout {
+                    entry.stale = true;
+                    expired.push(entry.key.clone());
+                }
+            }
+        }
+        expired
+    }
+
+    pub fn is_stale<Q>(&self, key: Q) -> bool
+    where
+        K: PartialEq<Q>,
+    {
+        self.entries
+            .iter()
+            .any(|entry| entry.key == key && entry.stale)
+    }
+
+    /// How long ago `key` was last fed.
+    pub fn age<Q>(&self, key: Q, now: Duration) -> Option<Duration>
+    where
+        K: PartialEq<Q>,
+    {
+        self.entries
+            .iter()
+            .find(|entry| entry.key == key)
+            .and_then(|entry| entry.last)
+            .map(|last| now.checked_sub(last).unwrap_or_default())
+    }
+}
+
+impl<K: Clone + PartialEq> Default for Watchdog<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
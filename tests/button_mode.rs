@@ -0,0 +1,125 @@
+//! Checks how each button mode turns a source's activity into button presses.
+
+use std::time::Duration;
+
+use wfpi::button_mode::{ButtonBehaviour, ButtonMode};
+
+fn ms(time: u64) -> Duration {
+    Duration::from_millis(time)
+}
+
+/// Feeds each input to a behaviour at its time, in milliseconds, and returns whether the
+/// button was pressed after each. `None` moves time on without a new input.
+fn run(mode: ButtonMode, script: &[(u64, Option<bool>)]) -> Vec<bool> {
+    let mut behaviour = ButtonBehaviour::new(mode);
+    script
+        .iter()
+        .map(|&(time, input)| match input {
+            Some(active) => behaviour.update(active, ms(time)),
+            None => behaviour.tick(ms(time)),
+        })
+        .collect()
+}
+
+#[test]
+fn momentary_follows_the_input() {
+    let pressed = run(
+        ButtonMode::Momentary,
+        &[
+            (0, Some(true)),
+            (10, Some(true)),
+            (20, Some(false)),
+            (30, None),
+        ],
+    );
+    assert_eq!(pressed, vec![true, true, false, false]);
+}
+
+#[test]
+fn toggle_flips_on_each_activation() {
+    let pressed = run(
+        ButtonMode::Toggle,
+        &[
+            (0, Some(true)),
+            (10, Some(true)),
+            (20, Some(false)),
+            (30, Some(true)),
+            (40, Some(false)),
+            (5000, None),
+        ],
+    );
+    assert_eq!(pressed, vec![true, true, true, false, false, false]);
+}
+
+#[test]
+fn turbo_repeats_while_held() {
+    let pressed = run(
+        ButtonMode::Turbo { interval: ms(100) },
+        &[
+            (0, Some(true)),
+            (40, None),
+            (60, None),
+            (110, None),
+            (160, Some(true)),
+            (210, None),
+            (220, Some(false)),
+            (310, None),
+        ],
+    );
+    assert_eq!(
+        pressed,
+        vec![true, true, false, true, false, true, false, false]
+    );
+}
+
+#[test]
+fn pulse_has_a_fixed_length() {
+    let pressed = run(
+        ButtonMode::Pulse { length: ms(50) },
+        &[
+            // A flex shorter than the pulse
+            (0, Some(true)),
+            (10, Some(false)),
+            (40, None),
+            (50, None),
+            // A flex longer than the pulse
+            (100, Some(true)),
+            (160, None),
+            (500, Some(true)),
+        ],
+    );
+    assert_eq!(pressed, vec![true, true, true, false, true, false, false]);
+}
+
+#[test]
+fn sticky_latches_until_released_or_timed_out() {
+    let pressed = run(
+        ButtonMode::Sticky { timeout: ms(1000) },
+        &[
+            // Released by a second flex
+            (0, Some(true)),
+            (100, Some(false)),
+            (200, Some(true)),
+            (300, Some(false)),
+            // Released by the timeout
+            (400, Some(true)),
+            (500, Some(false)),
+            (1399, None),
+            (1400, None),
+        ],
+    );
+    assert_eq!(
+        pressed,
+        vec![true, true, false, false, true, true, true, false]
+    );
+}
+
+#[test]
+fn reset_releases_a_latched_button() {
+    let mut behaviour = ButtonBehaviour::new(ButtonMode::Toggle);
+    assert!(behaviour.update(true, ms(0)));
+    behaviour.reset();
+    assert!(!behaviour.is_pressed());
+    // An input still held after the reset counts as a new activation
+    assert!(behaviour.update(true, ms(10)));
+}
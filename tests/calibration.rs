@@ -0,0 +1,131 @@
+//! Checks building, storing and applying analog output calibrations.
+
+use std::io::Cursor;
+
+use wfpi::mock::{MockAnalog, Timeline};
+use wfpi::springboard::calibration::{self, Calibration, Gauge, OperatorGauge};
+use wfpi::springboard::Analog;
+use wfpi::Result;
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+/// An XAC that ignores the bottom 30% of the device's range and responds linearly above it
+fn dead_below_30(device_value: f64) -> f64 {
+    ((device_value - 30f64) / 0.7).max(0f64)
+}
+
+/// A test rig that reads the XAC's response directly.
+struct Rig(fn(f64) -> f64);
+
+impl Gauge for Rig {
+    fn read(&mut self, device_value: f64) -> Result<Option<f64>> {
+        Ok(Some((self.0)(device_value)))
+    }
+}
+
+#[test]
+fn dead_ranges_are_skipped() {
+    let measurements: Vec<_> = (0..=10)
+        .map(|i| f64::from(i) * 10f64)
+        .map(|device| (device, dead_below_30(device)))
+        .collect();
+    let calibration = Calibration::from_measurements(&measurements).unwrap();
+
+    assert!(close(calibration.device_value(0f64), 0f64));
+    assert!(close(calibration.device_value(0.1), 30.07));
+    assert!(close(calibration.device_value(50f64), 65f64));
+    assert!(close(calibration.device_value(100f64), 100f64));
+    assert!(close(calibration.device_value(150f64), 100f64));
+}
+
+#[test]
+fn saturation_uses_the_first_device_value_at_the_top() {
+    let calibration =
+        Calibration::from_measurements(&[(0f64, 0f64), (50f64, 100f64), (100f64, 100f64)]).unwrap();
+    assert!(close(calibration.device_value(100f64), 50f64));
+    assert!(close(calibration.device_value(50f64), 25f64));
+}
+
+#[test]
+fn readings_never_fall() {
+    let calibration = Calibration::from_measurements(&[
+        (100f64, 100f64),
+        (0f64, 0f64),
+        (50f64, 60f64),
+        (60f64, 55f64),
+    ])
+    .unwrap();
+    assert_eq!(
+        calibration.points(),
+        &[
+            (0f64, 0f64),
+            (50f64, 60f64),
+            (60f64, 60f64),
+            (100f64, 100f64)
+        ]
+    );
+}
+
+#[test]
+fn flat_measurements_are_rejected() {
+    assert!(Calibration::from_measurements(&[]).is_err());
+    assert!(Calibration::from_measurements(&[(0f64, 40f64), (100f64, 40f64)]).is_err());
+}
+
+#[test]
+fn calibrations_round_trip_through_text() {
+    let calibration =
+        Calibration::from_measurements(&[(0f64, 0f64), (30f64, 0f64), (100f64, 100f64)]).unwrap();
+    assert_eq!(
+        Calibration::parse(&calibration.to_string()).unwrap(),
+        calibration
+    );
+
+    let error = Calibration::parse("0 0\n100\n").unwrap_err();
+    assert!(error.to_string().contains("line 2"), "{}", error);
+}
+
+#[test]
+fn calibrated_outputs_write_corrected_device_values() {
+    let timeline = Timeline::new();
+    let mut analog = Analog::new(
+        "trigger",
+        0f64,
+        Box::new(MockAnalog::new("trigger", &timeline)),
+    );
+
+    let calibration = calibration::calibrate(&mut analog, &mut Rig(dead_below_30), 10).unwrap();
+    let steps: Vec<f64> = timeline
+        .analog_writes("trigger")
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect();
+    assert_eq!(steps.len(), 12);
+    assert!(close(steps[10], 100f64));
+    // Back to rest once done
+    assert!(close(steps[11], 0f64));
+
+    timeline.clear();
+    analog.set_calibration(Some(calibration));
+    analog.set(50f64).unwrap();
+    assert!(close(timeline.analog_writes("trigger")[0].1, 65f64));
+    // The value the XAC should see is what's reported
+    assert!(close(analog.value(), 50f64));
+}
+
+#[test]
+fn operators_are_asked_until_they_answer() {
+    let mut prompts = vec![];
+    let mut gauge = OperatorGauge::new(Cursor::new("lots\n120\n42\n\n"), &mut prompts);
+
+    assert_eq!(gauge.read(25f64).unwrap(), Some(42f64));
+    assert_eq!(gauge.read(30f64).unwrap(), None);
+    // Running out of input skips the rest
+    assert_eq!(gauge.read(35f64).unwrap(), None);
+
+    let prompts = String::from_utf8(prompts).unwrap();
+    assert_eq!(prompts.matches("Device at 25%").count(), 3);
+    assert_eq!(prompts.matches("Please enter a number").count(), 2);
+}
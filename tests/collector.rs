@@ -0,0 +1,1173 @@
+//! Drives the collector and device drivers against the in-memory peripherals in
+//! `wfpi::mock`, and checks the resulting output timelines.
+
+use std::time::Duration;
+
+use termion::event::Key;
+
+use wfpi::collector::{Collector, CollectorConfig, DeviceSignal, Source, UiState};
+use wfpi::eeg::{EegBand, Mindwave};
+use wfpi::mapping::{self, Mapping, Transform};
+use wfpi::mock::{self, MockEvents, MockI2c, MockMcp3008, MockPin, MockSpi, MockUart, Timeline};
+use wfpi::myo::{MyoConfig, MyoParser, MyoReader, Side};
+use wfpi::pointer::{PointerConfig, SpeedSource};
+use wfpi::springboard::digipot::{CheckedDigipot, WriteCheck};
+use wfpi::springboard::mcp4922::{Channel, Mcp4922, Mcp4922Channel};
+use wfpi::springboard::uinput::{self, VirtualDevice, VirtualKeyboard, VirtualMouse};
+use wfpi::springboard::{
+    self, adafruit3502, mcp4018, ActiveLevel, Analog, AnalogOutput, Button, ButtonConfig,
+    OutputValue, PressTiming, SharedBus, Springboard, XacJack,
+};
+use wfpi::WfpiError;
+
+enum Input {
+    Signal(DeviceSignal),
+    Key(Key),
+    /// A pass of the main loop with no data
+    Tick,
+}
+
+/// Feeds each input to a collector at its time, in milliseconds, and returns everything
+/// written to the outputs.
+fn run(script: Vec<(u64, Input)>) -> (Timeline, Collector<MockPin, Timeline>) {
+    let timeline = Timeline::new();
+    let mut collector = Collector::with_clock(mock::springboard(&timeline), timeline.clone());
+    // Only keep what the script causes, not the buttons being released at startup
+    timeline.clear();
+    for (time, input) in script {
+        timeline.set_time(Duration::from_millis(time));
+        match input {
+            Input::Signal(signal) => collector
+                .handle_signal(signal)
+                .expect("failed to write to XAC"),
+            Input::Key(key) => collector.handle_key(key),
+            Input::Tick => collector.tick(),
+        }
+    }
+    (timeline, collector)
+}
+
+fn ms(time: u64) -> Duration {
+    Duration::from_millis(time)
+}
+
+/// What the TUI shows being sent to the output called `name`
+fn sent(sending: &[(String, OutputValue)], name: &str) -> Option<OutputValue> {
+    sending
+        .iter()
+        .find(|(output, _)| output == name)
+        .map(|&(_, value)| value)
+}
+
+#[test]
+fn flexes_press_and_release_buttons() {
+    let (timeline, _collector) = run(vec![
+        (0, Input::Signal(DeviceSignal::Myo1(false, 0))),
+        (10, Input::Signal(DeviceSignal::Myo1(true, 450))),
+        (15, Input::Signal(DeviceSignal::Myo2(true, 300))),
+        (90, Input::Signal(DeviceSignal::Myo1(false, 0))),
+        (120, Input::Signal(DeviceSignal::Myo2(false, 0))),
+    ]);
+
+    // The XAC's buttons are active low, and a button that's already released isn't
+    // written again
+    assert_eq!(
+        timeline.pin_writes("left"),
+        vec![(ms(10), false), (ms(90), true)]
+    );
+    assert_eq!(
+        timeline.pin_writes("right"),
+        vec![(ms(15), false), (ms(120), true)]
+    );
+    assert!(timeline.analog_writes("trigger").is_empty());
+}
+
+#[test]
+fn attention_is_rescaled_onto_the_trigger() {
+    let (timeline, collector) = run(vec![
+        (0, Input::Signal(DeviceSignal::Eeg(10, 50, 200))),
+        (1000, Input::Signal(DeviceSignal::Eeg(50, 50, 200))),
+        (2000, Input::Signal(DeviceSignal::Eeg(95, 50, 200))),
+    ]);
+
+    let writes = timeline.analog_writes("trigger");
+    assert_eq!(writes.len(), 3);
+    assert_eq!(writes[0], (ms(0), 0f64));
+    assert_eq!(writes[1].0, ms(1000));
+    assert!((writes[1].1 - 50f64).abs() < 1e-9);
+    assert_eq!(writes[2], (ms(2000), 100f64));
+
+    let UiState {
+        eeg_data,
+        sending,
+        current_time,
+        ..
+    } = collector.ui_state();
+    assert_eq!(eeg_data.len(), 3);
+    assert_eq!(eeg_data[2].1, [95, 50, 200]);
+    // The screen shows the time of the latest reading
+    assert!((eeg_data[2].0 - 1f64).abs() < std::f64::EPSILON);
+    assert!((current_time - 1f64).abs() < std::f64::EPSILON);
+    assert_eq!(sent(&sending, "trigger"), Some(OutputValue::Analog(100f64)));
+}
+
+#[test]
+fn attention_jumps_are_slew_limited() {
+    let (timeline, _collector) = run(vec![
+        (0, Input::Signal(DeviceSignal::Eeg(20, 50, 200))),
+        (500, Input::Signal(DeviceSignal::Eeg(80, 50, 200))),
+        (1000, Input::Signal(DeviceSignal::Eeg(80, 50, 200))),
+        (3000, Input::Signal(DeviceSignal::Eeg(80, 50, 200))),
+    ]);
+
+    let values: Vec<f64> = timeline
+        .analog_writes("trigger")
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect();
+    assert_eq!(values, vec![0f64, 25f64, 50f64, 100f64]);
+}
+
+#[test]
+fn attention_ramps_between_readings() {
+    let (timeline, _collector) = run(vec![
+        (0, Input::Signal(DeviceSignal::Eeg(20, 50, 200))),
+        (1000, Input::Signal(DeviceSignal::Eeg(80, 50, 200))),
+        (1250, Input::Tick),
+        (1500, Input::Tick),
+        (2000, Input::Tick),
+        (2500, Input::Tick),
+    ]);
+
+    // Attention only arrives once a second, so the trigger is moved on in between
+    assert_eq!(
+        timeline.analog_writes("trigger"),
+        vec![
+            (ms(0), 0f64),
+            (ms(1000), 50f64),
+            (ms(1250), 62.5),
+            (ms(1500), 75f64),
+            (ms(2000), 100f64),
+        ]
+    );
+}
+
+#[test]
+fn override_takes_the_outputs_from_the_devices() {
+    let (timeline, collector) = run(vec![
+        (0, Input::Key(Key::Char('z'))),
+        (10, Input::Key(Key::Char('m'))),
+        (20, Input::Signal(DeviceSignal::Myo1(true, 450))),
+        (30, Input::Signal(DeviceSignal::Eeg(80, 50, 200))),
+        (40, Input::Key(Key::Char('z'))),
+        (50, Input::Key(Key::Char('b'))),
+        (60, Input::Key(Key::Char('x'))),
+        (70, Input::Key(Key::Char('m'))),
+        (80, Input::Signal(DeviceSignal::Myo1(false, 0))),
+    ]);
+
+    // Keys do nothing until the override is on, and the devices do nothing while it is
+    assert_eq!(
+        timeline.pin_writes("left"),
+        vec![(ms(40), false), (ms(60), true)]
+    );
+    assert_eq!(timeline.analog_writes("trigger"), vec![(ms(50), 100f64)]);
+
+    let UiState {
+        myo_left_data,
+        override_output,
+        ..
+    } = collector.ui_state();
+    assert!(!override_output);
+    // Readings are still charted while overridden
+    assert_eq!(myo_left_data.len(), 2);
+}
+
+#[test]
+fn stalled_sources_release_their_outputs() {
+    let (timeline, collector) = run(vec![
+        (0, Input::Signal(DeviceSignal::Eeg(80, 50, 200))),
+        (0, Input::Signal(DeviceSignal::Myo1(true, 450))),
+        (100, Input::Signal(DeviceSignal::Myo2(true, 450))),
+        (400, Input::Signal(DeviceSignal::Myo2(true, 450))),
+        (550, Input::Tick),
+        (700, Input::Signal(DeviceSignal::Myo2(true, 450))),
+        (2900, Input::Tick),
+        (3100, Input::Tick),
+        (3200, Input::Tick),
+    ]);
+
+    // The left myo went quiet after its first reading
+    assert_eq!(
+        timeline.pin_writes("left"),
+        vec![(ms(0), false), (ms(550), true)]
+    );
+    // The right myo carried on until 700ms
+    assert_eq!(
+        timeline.pin_writes("right"),
+        vec![(ms(100), false), (ms(2900), true)]
+    );
+    // The EEG is allowed longer, and is only released once
+    assert_eq!(
+        timeline.analog_writes("trigger"),
+        vec![(ms(0), 100f64), (ms(3100), 0f64)]
+    );
+    assert!(collector.is_stale(Source::Eeg));
+
+    let sending = collector.ui_state().sending;
+    assert_eq!(
+        sending,
+        vec![
+            ("left".to_string(), OutputValue::Button(false)),
+            ("right".to_string(), OutputValue::Button(false)),
+            ("trigger".to_string(), OutputValue::Analog(0f64)),
+        ]
+    );
+}
+
+#[test]
+fn resumed_sources_drive_their_outputs_again() {
+    let (timeline, collector) = run(vec![
+        (0, Input::Signal(DeviceSignal::Myo1(true, 450))),
+        (600, Input::Tick),
+        (1000, Input::Signal(DeviceSignal::Myo1(true, 450))),
+    ]);
+
+    assert_eq!(
+        timeline.pin_writes("left"),
+        vec![(ms(0), false), (ms(600), true), (ms(1000), false)]
+    );
+    assert!(!collector.is_stale(Source::MyoLeft));
+}
+
+#[test]
+fn the_watchdog_leaves_overridden_outputs_alone() {
+    let (timeline, _collector) = run(vec![
+        (0, Input::Signal(DeviceSignal::Myo1(false, 0))),
+        (10, Input::Key(Key::Char('m'))),
+        (20, Input::Key(Key::Char('z'))),
+        (10_000, Input::Tick),
+    ]);
+
+    assert_eq!(timeline.pin_writes("left"), vec![(ms(20), false)]);
+}
+
+#[test]
+fn blips_are_held_long_enough_to_register() {
+    let (timeline, collector) = run(vec![
+        (0, Input::Signal(DeviceSignal::Myo1(true, 450))),
+        (10, Input::Signal(DeviceSignal::Myo1(false, 0))),
+        (40, Input::Tick),
+        (50, Input::Tick),
+        // Too soon after the last press to press again
+        (60, Input::Signal(DeviceSignal::Myo1(true, 450))),
+        (70, Input::Signal(DeviceSignal::Myo1(false, 0))),
+        (100, Input::Tick),
+        (150, Input::Tick),
+    ]);
+
+    // Each blip registers once, held and spaced out by `PressTiming::XAC`
+    assert_eq!(
+        timeline.pin_writes("left"),
+        vec![
+            (ms(0), false),
+            (ms(50), true),
+            (ms(100), false),
+            (ms(150), true)
+        ]
+    );
+    assert_eq!(collector.next_due(), None);
+}
+
+#[test]
+fn held_back_changes_say_when_they_are_due() {
+    let (timeline, collector) = run(vec![
+        (0, Input::Signal(DeviceSignal::Myo2(true, 450))),
+        (20, Input::Signal(DeviceSignal::Myo2(false, 0))),
+    ]);
+
+    assert_eq!(timeline.pin_writes("right"), vec![(ms(0), false)]);
+    assert_eq!(collector.next_due(), Some(ms(30)));
+    let sending = collector.ui_state().sending;
+    assert_eq!(sent(&sending, "right"), Some(OutputValue::Button(true)));
+}
+
+#[test]
+fn reports_are_kept_for_the_ui_without_touching_outputs() {
+    let mut report = wfpi::myo::QualityReport::default();
+    report.status = wfpi::myo::SignalQuality::Flatline;
+    let (timeline, collector) = run(vec![(
+        0,
+        Input::Signal(DeviceSignal::Quality(Side::Right, report)),
+    )]);
+
+    assert!(timeline.records().is_empty());
+    let quality = collector.ui_state().quality;
+    assert_eq!(quality[Side::Right as usize].status, report.status);
+}
+
+#[test]
+fn button_bank_respects_active_levels() {
+    let timeline = Timeline::new();
+    let buttons = [
+        ButtonConfig {
+            name: "jump".to_string(),
+            pin: 5,
+            active_level: ActiveLevel::Low,
+            jack: XacJack::A,
+            timing: PressTiming::NONE,
+        },
+        ButtonConfig {
+            name: "up".to_string(),
+            pin: 6,
+            active_level: ActiveLevel::High,
+            jack: XacJack::DpadUp,
+            timing: PressTiming::NONE,
+        },
+    ];
+    let mut springboard = mock::springboard_with(&timeline, &buttons, &[]);
+
+    // Everything starts released
+    assert_eq!(timeline.pin_writes("jump"), vec![(ms(0), true)]);
+    assert_eq!(timeline.pin_writes("up"), vec![(ms(0), false)]);
+
+    timeline.set_time(ms(10));
+    springboard.update_button("up", true).unwrap();
+    springboard.update_button("jump", true).unwrap();
+    assert_eq!(timeline.pin_writes("up")[1], (ms(10), true));
+    assert_eq!(timeline.pin_writes("jump")[1], (ms(10), false));
+    assert!(springboard.button("up").unwrap().is_pressed());
+    assert_eq!(
+        springboard.button("jump").unwrap().config().jack.label(),
+        "A"
+    );
+
+    match springboard.update_button("left", true) {
+        Err(WfpiError::UnknownOutput { name }) => assert_eq!(name, "left"),
+        _ => panic!("expected an unknown output error"),
+    }
+
+    springboard.release_all().unwrap();
+    assert!(springboard.buttons().iter().all(|b| !b.is_pressed()));
+}
+
+#[test]
+fn mcp3008_readings_reach_the_reader() {
+    let timeline = Timeline::new();
+    let adc = MockMcp3008::new(&timeline);
+    let mut reader = MyoReader::new(adc.clone());
+
+    adc.set_channel(0, 0x2A5);
+    adc.set_channel(1, 1023);
+    reader.update().unwrap();
+    assert!(reader.has_new_data());
+    assert_eq!(reader.get_value(Side::Left), 0x2A5);
+    assert_eq!(reader.get_value(Side::Right), 1023);
+
+    reader.update().unwrap();
+    assert!(!reader.has_new_data());
+}
+
+#[test]
+fn flatlined_myo_never_presses() {
+    let timeline = Timeline::new();
+    let adc = MockMcp3008::new(&timeline);
+    adc.set_channel(0, 512);
+    adc.set_channel(1, 512);
+    let mut parser = MyoParser::with_reader(
+        MyoReader::new(adc.clone()),
+        &MyoConfig {
+            left_pipeline: vec![],
+            right_pipeline: vec![],
+            ..MyoConfig::default()
+        },
+    );
+    let mut collector = Collector::new(mock::springboard(&timeline));
+    timeline.clear();
+
+    for i in 0..2000u64 {
+        timeline.set_time(Duration::from_millis(i));
+        // With no processing every reading counts as a flex, so only the quality check
+        // can hold the button released
+        let value = if i % 2 == 0 { 100 } else { 900 };
+        adc.set_channel(0, if i < 1000 { 512 } else { value });
+        if parser.update().unwrap() {
+            let (state, val) = parser.get_value(Side::Left);
+            collector
+                .handle_signal(DeviceSignal::Myo1(state, val))
+                .unwrap();
+        }
+    }
+
+    // The first second is flat, so nothing may be pressed until the channel recovers
+    assert!(timeline
+        .pin_writes("left")
+        .iter()
+        .all(|&(time, high)| high || time >= ms(1000)));
+}
+
+#[test]
+fn analog_channel_drives_its_mapping() {
+    let timeline = Timeline::new();
+    let adc = MockMcp3008::new(&timeline);
+    let mut parser = MyoParser::with_reader(MyoReader::new(adc.clone()), &MyoConfig::default());
+    let mut collector = Collector::with_clock(mock::springboard(&timeline), timeline.clone())
+        .with_mappings(&[Mapping {
+            input: mapping::Input::Analog,
+            transforms: vec![Transform::Scale {
+                input_min: 0f64,
+                input_max: 1000f64,
+                output_min: 0f64,
+                output_max: 100f64,
+            }],
+            output: "trigger".to_string(),
+        }]);
+    timeline.clear();
+
+    adc.set_channel(2, 250);
+    parser.update().unwrap();
+    collector
+        .handle_signal(DeviceSignal::Analog(parser.get_analog()))
+        .unwrap();
+
+    assert_eq!(parser.get_analog(), 250);
+    assert_eq!(timeline.analog_writes("trigger"), vec![(ms(0), 25f64)]);
+}
+
+#[test]
+fn mindwave_packets_are_parsed() {
+    let timeline = Timeline::new();
+    let uart = MockUart::new(&timeline);
+    let mut mindwave = Mindwave::new(uart.clone());
+
+    let payload = [0x02, 0, 0x04, 60, 0x05, 40];
+    let checksum = 0xFF - payload.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    uart.push(&[0xAA, 0xAA, payload.len() as u8]);
+    uart.push(&payload);
+    uart.push(&[checksum]);
+
+    mindwave.update().unwrap();
+    assert!(mindwave.has_new_data());
+    assert_eq!(mindwave.get_attention(), 60);
+    assert_eq!(mindwave.get_meditation(), 40);
+    assert_eq!(mindwave.get_quality(), 200);
+    assert_eq!(uart.pending(), 0);
+
+    // Nothing to read isn't an error
+    mindwave.update().unwrap();
+    assert!(!mindwave.has_new_data());
+}
+
+#[test]
+fn digipots_write_their_wipers() {
+    let timeline = Timeline::new();
+    let bus = MockI2c::new(&timeline);
+    bus.attach(adafruit3502::DS3502_I2CADDR_DEFAULT);
+    bus.attach(mcp4018::I2CADDR_DEFAULT);
+
+    let mut ds3502 = adafruit3502::AdafruitDS3502::new(bus.clone());
+    ds3502.begin(adafruit3502::DS3502_I2CADDR_DEFAULT).unwrap();
+    let mut ds3502 = CheckedDigipot::new(ds3502, springboard::DIGIPOT_WRITES);
+    ds3502.set_value(50f64).unwrap();
+    assert_eq!(
+        bus.register(adafruit3502::DS3502_I2CADDR_DEFAULT, 0x00),
+        Some(63)
+    );
+
+    let mut mcp = mcp4018::Mcp4018::new(bus.clone());
+    mcp.begin(mcp4018::I2CADDR_DEFAULT).unwrap();
+    let mut mcp = CheckedDigipot::new(mcp, springboard::DIGIPOT_WRITES);
+    mcp.set_value(100f64).unwrap();
+    assert_eq!(mcp.pot().wiper().unwrap(), 127);
+
+    // A missing device is an error, not a silent no-op
+    bus.detach(mcp4018::I2CADDR_DEFAULT);
+    assert!(mcp.set_value(0f64).is_err());
+}
+
+#[test]
+fn wiper_writes_are_range_checked() {
+    let timeline = Timeline::new();
+    let bus = MockI2c::new(&timeline);
+    bus.attach(mcp4018::I2CADDR_DEFAULT);
+    let mut mcp = mcp4018::Mcp4018::new(bus.clone());
+
+    match mcp.set_wiper(128) {
+        Err(WfpiError::WiperOutOfRange { value, max }) => assert_eq!((value, max), (128, 127)),
+        _ => panic!("expected a range error"),
+    }
+    assert!(timeline.records().is_empty());
+}
+
+#[test]
+fn wiper_writes_are_retried_and_verified() {
+    let timeline = Timeline::new();
+    let bus = MockI2c::new(&timeline);
+    let address = adafruit3502::DS3502_I2CADDR_DEFAULT;
+    bus.attach(address);
+    let mut ds3502 = adafruit3502::AdafruitDS3502::new(bus.clone());
+    ds3502.begin(address).unwrap();
+    let mut ds3502 = CheckedDigipot::new(
+        ds3502,
+        WriteCheck {
+            verify: true,
+            retries: 2,
+        },
+    );
+
+    // A NACK is tried again
+    bus.nack_next(address, 1);
+    ds3502.set_value(100f64).unwrap();
+    assert_eq!(bus.register(address, 0x00), Some(127));
+    assert_eq!(ds3502.mismatches(), 0);
+
+    // So is a write that doesn't stick, and the mismatch is counted
+    bus.lose_next_writes(address, 1);
+    ds3502.set_value(0f64).unwrap();
+    assert_eq!(bus.register(address, 0x00), Some(0));
+    assert_eq!(ds3502.mismatches(), 1);
+
+    // Until the retries run out
+    bus.lose_next_writes(address, 3);
+    match ds3502.set_value(50f64) {
+        Err(WfpiError::WiperMismatch { expected, actual }) => {
+            assert_eq!((expected, actual), (63, 0))
+        }
+        _ => panic!("expected a mismatch"),
+    }
+    assert_eq!(ds3502.mismatches(), 4);
+}
+
+#[test]
+fn dac_writes_channel_a() {
+    let timeline = Timeline::new();
+    let mut dac = Mcp4922Channel::new(Mcp4922::new(MockSpi::new(&timeline)), Channel::CHA);
+    timeline.set_time(ms(5));
+    dac.set_value(100f64).unwrap();
+
+    let records = timeline.records();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].time, ms(5));
+    assert_eq!(
+        records[0].write,
+        mock::Write::Spi {
+            bytes: vec![0x3F, 0xFF]
+        }
+    );
+}
+
+#[test]
+fn analog_outputs_share_their_buses() {
+    let timeline = Timeline::new();
+    let spi = SharedBus::new(MockSpi::new(&timeline));
+    let mut stick_x = Mcp4922Channel::new(Mcp4922::new(spi.clone()), Channel::CHA);
+    let mut stick_y = Mcp4922Channel::new(Mcp4922::new(spi), Channel::CHB);
+    stick_x.set_value(0f64).unwrap();
+    stick_y.set_value(100f64).unwrap();
+    assert_eq!(
+        timeline
+            .records()
+            .into_iter()
+            .map(|record| record.write)
+            .collect::<Vec<_>>(),
+        vec![
+            mock::Write::Spi {
+                bytes: vec![0x30, 0x00]
+            },
+            mock::Write::Spi {
+                bytes: vec![0xBF, 0xFF]
+            },
+        ]
+    );
+
+    let i2c = MockI2c::new(&timeline);
+    i2c.attach(0x28);
+    i2c.attach(0x29);
+    let bus = SharedBus::new(i2c.clone());
+    let mut left = adafruit3502::AdafruitDS3502::new(bus.clone());
+    left.begin(0x28).unwrap();
+    let mut left = CheckedDigipot::new(left, springboard::DIGIPOT_WRITES);
+    let mut right = adafruit3502::AdafruitDS3502::new(bus);
+    right.begin(0x29).unwrap();
+    let mut right = CheckedDigipot::new(right, springboard::DIGIPOT_WRITES);
+    left.set_value(100f64).unwrap();
+    right.set_value(0f64).unwrap();
+    assert_eq!(i2c.register(0x28, 0x00), Some(127));
+    assert_eq!(i2c.register(0x29, 0x00), Some(0));
+}
+
+#[test]
+fn analog_outputs_are_looked_up_by_name() {
+    let timeline = Timeline::new();
+    let mut springboard = mock::springboard_with(&timeline, &[], &["left", "right"]);
+    // Both start at rest
+    assert_eq!(timeline.analog_writes("left"), vec![(ms(0), 0f64)]);
+    assert_eq!(timeline.analog_writes("right"), vec![(ms(0), 0f64)]);
+
+    timeline.set_time(ms(10));
+    springboard.update_analog("right", 25f64).unwrap();
+    assert_eq!(timeline.analog_writes("left").len(), 1);
+    assert_eq!(timeline.analog_writes("right")[1], (ms(10), 25f64));
+    assert!((springboard.analog("right").unwrap().value() - 25f64).abs() < std::f64::EPSILON);
+    match springboard.update_analog("trigger", 0f64) {
+        Err(WfpiError::UnknownOutput { name }) => assert_eq!(name, "trigger"),
+        _ => panic!("expected an unknown output error"),
+    }
+}
+
+#[test]
+fn dropping_the_springboard_releases_everything() {
+    let timeline = Timeline::new();
+    let mut springboard =
+        mock::springboard_with(&timeline, &springboard::default_buttons(), &["trigger"]);
+    springboard.update_button("left", true).unwrap();
+    springboard.update_analog("trigger", 80f64).unwrap();
+
+    timeline.set_time(ms(10));
+    drop(springboard);
+    assert_eq!(timeline.pin_writes("left").last(), Some(&(ms(10), true)));
+    assert_eq!(
+        timeline.analog_writes("trigger").last(),
+        Some(&(ms(10), 0f64))
+    );
+}
+
+#[test]
+fn device_errors_put_outputs_in_safe_state() {
+    let timeline = Timeline::new();
+    let bus = MockI2c::new(&timeline);
+    bus.attach(adafruit3502::DS3502_I2CADDR_DEFAULT);
+    let mut ds3502 = adafruit3502::AdafruitDS3502::new(bus.clone());
+    ds3502.begin(adafruit3502::DS3502_I2CADDR_DEFAULT).unwrap();
+    let springboard = Springboard::new(
+        springboard::default_buttons()
+            .into_iter()
+            .map(|config| {
+                let pin = MockPin::new(&config.name, &timeline);
+                Button::new(config, pin)
+            })
+            .collect(),
+        vec![Analog::new(
+            "trigger",
+            0f64,
+            Box::new(CheckedDigipot::new(ds3502, springboard::DIGIPOT_WRITES)),
+        )],
+    )
+    .unwrap();
+    let mut collector = Collector::with_clock(springboard, timeline.clone());
+
+    timeline.set_time(ms(10));
+    collector
+        .handle_signal(DeviceSignal::Myo1(true, 450))
+        .unwrap();
+    assert_eq!(timeline.pin_writes("left").last(), Some(&(ms(10), false)));
+
+    // The potentiometer drops off the bus, so the left button is let go
+    bus.detach(adafruit3502::DS3502_I2CADDR_DEFAULT);
+    timeline.set_time(ms(20));
+    assert!(collector
+        .handle_signal(DeviceSignal::Eeg(80, 50, 200))
+        .is_err());
+    assert_eq!(timeline.pin_writes("left").last(), Some(&(ms(20), true)));
+    let sending = collector.ui_state().sending;
+    assert_eq!(sent(&sending, "left"), Some(OutputValue::Button(false)));
+}
+
+#[test]
+fn dac_shuts_down_at_rest() {
+    let timeline = Timeline::new();
+    let spi = SharedBus::new(MockSpi::new(&timeline));
+    let trigger = Mcp4922Channel::new(Mcp4922::new(spi.clone()), Channel::CHA);
+    let mut trigger = Analog::new("trigger", 0f64, Box::new(trigger));
+    let stick = Mcp4922Channel::new(Mcp4922::new(spi), Channel::CHB);
+    let mut stick = Analog::new("stick_y", 50f64, Box::new(stick));
+    trigger.return_to_rest().unwrap();
+    // Shutting down pulls the output to ground, which isn't where a stick rests
+    stick.return_to_rest().unwrap();
+
+    assert_eq!(
+        timeline
+            .records()
+            .into_iter()
+            .map(|record| record.write)
+            .collect::<Vec<_>>(),
+        vec![
+            mock::Write::Spi {
+                bytes: vec![0x30, 0x00]
+            },
+            mock::Write::Spi {
+                bytes: vec![0x20, 0xFF]
+            },
+            mock::Write::Spi {
+                bytes: vec![0xB7, 0xFF]
+            },
+        ]
+    );
+}
+
+#[test]
+fn ds3502_stores_its_power_on_wiper() {
+    let mut timeline = Timeline::new();
+    let bus = MockI2c::new(&timeline);
+    let address = adafruit3502::DS3502_I2CADDR_DEFAULT;
+    bus.attach(address);
+    let mut ds3502 = adafruit3502::AdafruitDS3502::new(bus.clone());
+    ds3502.begin(address).unwrap();
+    let mut trigger = Analog::new(
+        "trigger",
+        0f64,
+        Box::new(CheckedDigipot::new(ds3502, springboard::DIGIPOT_WRITES)),
+    );
+    timeline.clear();
+
+    assert!(trigger.persist_rest(&mut timeline.clone()).unwrap());
+    // Default-writing mode, the wiper, then back to normal once the EEPROM has had time
+    assert_eq!(
+        timeline.records(),
+        vec![
+            mock::Record {
+                time: ms(0),
+                write: mock::Write::I2c {
+                    address,
+                    bytes: vec![0x02, 0x00]
+                }
+            },
+            mock::Record {
+                time: ms(0),
+                write: mock::Write::I2c {
+                    address,
+                    bytes: vec![0x00, 0x00]
+                }
+            },
+            mock::Record {
+                time: ms(100),
+                write: mock::Write::I2c {
+                    address,
+                    bytes: vec![0x02, 0x80]
+                }
+            },
+        ]
+    );
+
+    // A write that doesn't take is reported, and normal mode is still restored
+    let mut ds3502 = adafruit3502::AdafruitDS3502::new(bus.clone());
+    ds3502.begin(address).unwrap();
+    bus.lose_next_writes(address, 2);
+    match ds3502.set_wiper_default(50, &mut timeline) {
+        Err(WfpiError::WiperMismatch { expected, actual }) => {
+            assert_eq!((expected, actual), (50, 0))
+        }
+        _ => panic!("expected a mismatch"),
+    }
+    assert_eq!(bus.register(address, 0x02), Some(0x80));
+}
+
+#[test]
+fn mcp4018_has_no_power_on_wiper_to_store() {
+    let timeline = Timeline::new();
+    let bus = MockI2c::new(&timeline);
+    bus.attach(mcp4018::I2CADDR_DEFAULT);
+    let mut mcp = mcp4018::Mcp4018::new(bus.clone());
+    mcp.begin(mcp4018::I2CADDR_DEFAULT).unwrap();
+    // Where the MCP4018 powers up
+    mcp.set_wiper(0x3F).unwrap();
+    let mut trigger = Analog::new(
+        "trigger",
+        0f64,
+        Box::new(CheckedDigipot::new(mcp, springboard::DIGIPOT_WRITES)),
+    );
+    assert!(!trigger.persist_rest(&mut timeline.clone()).unwrap());
+
+    // Instead, it's set to rest whenever a springboard is made
+    assert_eq!(bus.register(mcp4018::I2CADDR_DEFAULT, 0x00), Some(0x3F));
+    let _springboard = Springboard::<MockPin>::new(vec![], vec![trigger]).unwrap();
+    assert_eq!(bus.register(mcp4018::I2CADDR_DEFAULT, 0x00), Some(0));
+}
+
+#[test]
+fn virtual_gamepad_gets_the_same_outputs() {
+    let timeline = Timeline::new();
+    let output = Springboard::virtual_device(VirtualDevice::Gamepad, MockEvents::new(&timeline))
+        .expect("mock events can't fail");
+    let mut collector = Collector::with_clock(output, timeline.clone());
+    timeline.clear();
+
+    collector
+        .handle_signal(DeviceSignal::Myo1(true, 450))
+        .unwrap();
+    timeline.set_time(ms(2000));
+    collector
+        .handle_signal(DeviceSignal::Eeg(80, 50, 200))
+        .unwrap();
+    drop(collector);
+
+    // The left button is wired to the XAC's left bumper: BTN_TL
+    assert_eq!(
+        timeline.event_values(0x136),
+        vec![(ms(0), 1), (ms(2000), 0)]
+    );
+    assert_eq!(
+        timeline.event_values(uinput::ABS_RZ),
+        vec![(ms(2000), uinput::AXIS_MAX), (ms(2000), 0)]
+    );
+}
+
+#[test]
+fn virtual_keyboard_holds_a_key_for_analog_outputs() {
+    let timeline = Timeline::new();
+    let mut output =
+        Springboard::virtual_device(VirtualDevice::Keyboard, MockEvents::new(&timeline))
+            .expect("mock events can't fail");
+    timeline.clear();
+
+    for &value in &[20f64, 60f64, 80f64, 40f64] {
+        output.update_analog("trigger", value).unwrap();
+    }
+    // KEY_X, only sent when it changes
+    assert_eq!(timeline.event_values(45), vec![(ms(0), 1), (ms(0), 0)]);
+}
+
+#[test]
+fn pointer_mode_hands_the_myos_to_the_pointer() {
+    let timeline = Timeline::new();
+    let mouse = VirtualMouse::new(MockEvents::new(&timeline));
+    let mut collector = Collector::with_clock(mock::springboard(&timeline), timeline.clone())
+        .with_pointer(Box::new(mouse));
+    timeline.clear();
+
+    collector.handle_key(Key::Char('p'));
+    collector
+        .handle_signal(DeviceSignal::Eeg(100, 50, 200))
+        .unwrap();
+    collector
+        .handle_signal(DeviceSignal::Myo1(true, 450))
+        .unwrap();
+    for time in 1..=10 {
+        timeline.set_time(ms(time * 10));
+        collector.tick();
+    }
+    let pointer = collector.ui_state().pointer;
+    assert!(pointer.unwrap().speed > 0f64);
+    collector.handle_key(Key::Char('p'));
+
+    // The buttons stay released, and the pointer moves (REL_X, REL_Y)
+    assert!(timeline.pin_writes("left").iter().all(|&(_, high)| high));
+    assert!(!timeline.event_values(0x00).is_empty());
+    let pointer = collector.ui_state().pointer;
+    assert_eq!(pointer, None);
+}
+
+#[test]
+fn analog_channel_sets_the_pointer_speed() {
+    let timeline = Timeline::new();
+    let mouse = VirtualMouse::new(MockEvents::new(&timeline));
+    let config = CollectorConfig::default();
+    let mut collector = Collector::with_clock(mock::springboard(&timeline), timeline.clone())
+        .with_config(CollectorConfig {
+            pointer: PointerConfig {
+                speed_source: SpeedSource::Analog,
+                acceleration: Duration::from_secs(0),
+                ..config.pointer
+            },
+            ..config
+        })
+        .with_pointer(Box::new(mouse));
+
+    collector.handle_key(Key::Char('p'));
+    collector.handle_signal(DeviceSignal::Analog(1023)).unwrap();
+    collector
+        .handle_signal(DeviceSignal::Myo1(true, 450))
+        .unwrap();
+    collector.tick();
+
+    let pointer = collector.ui_state().pointer.unwrap();
+    assert!((pointer.speed - config.pointer.max_speed).abs() < 1e-9);
+}
+
+#[test]
+fn scanning_mode_selects_the_highlighted_output() {
+    let (timeline, collector) = run(vec![
+        (0, Input::Key(Key::Char('s'))),
+        // The highlight has moved from the left button to the right
+        (1600, Input::Tick),
+        (1700, Input::Signal(DeviceSignal::Myo1(true, 450))),
+        (1710, Input::Signal(DeviceSignal::Myo1(false, 0))),
+        (1800, Input::Tick),
+        (5000, Input::Tick),
+    ]);
+
+    let after_start = |writes: Vec<(Duration, bool)>| {
+        writes
+            .into_iter()
+            .filter(|&(time, _)| time > ms(0))
+            .collect::<Vec<_>>()
+    };
+    // Only released, when the myo goes quiet
+    assert!(timeline.pin_writes("left").iter().all(|&(_, high)| high));
+    // Held for the XAC's shortest press
+    assert_eq!(
+        after_start(timeline.pin_writes("right")),
+        vec![(ms(1700), false), (ms(1800), true)]
+    );
+    let scan = collector.ui_state().scan;
+    assert!(!scan.unwrap().selected);
+}
+
+#[test]
+fn text_mode_types_morse_on_the_keyboard() {
+    let timeline = Timeline::new();
+    let keyboard = VirtualKeyboard::new(MockEvents::new(&timeline));
+    let mut collector = Collector::with_clock(mock::springboard(&timeline), timeline.clone())
+        .with_keyboard(Box::new(keyboard));
+    timeline.clear();
+
+    collector.handle_key(Key::Char('t'));
+    // A dot then a dash, A, then a rest long enough to end the word
+    for &(time, flexed) in &[(0, true), (250, false), (500, true), (1250, false)] {
+        timeline.set_time(ms(time));
+        collector
+            .handle_signal(DeviceSignal::Myo1(flexed, if flexed { 450 } else { 0 }))
+            .unwrap();
+    }
+    for time in 13..=30 {
+        timeline.set_time(ms(time * 100));
+        collector
+            .handle_signal(DeviceSignal::Myo1(false, 0))
+            .unwrap();
+        collector.tick();
+    }
+
+    // KEY_A then KEY_SPACE, each pressed and released, and the XAC left alone
+    assert_eq!(
+        timeline.event_values(30),
+        vec![(ms(1800), 1), (ms(1800), 0)]
+    );
+    assert_eq!(
+        timeline.event_values(57),
+        vec![(ms(2500), 1), (ms(2500), 0)]
+    );
+    assert!(timeline.pin_writes("left").iter().all(|&(_, high)| high));
+    let morse = collector.ui_state().morse;
+    assert_eq!(morse.unwrap().text, "A ");
+}
+
+#[test]
+fn mappings_decide_what_drives_each_output() {
+    let timeline = Timeline::new();
+    let mut collector = Collector::with_clock(mock::springboard(&timeline), timeline.clone())
+        .with_mappings(&[
+            Mapping {
+                input: mapping::Input::Meditation,
+                transforms: vec![Transform::Invert],
+                output: "trigger".to_string(),
+            },
+            Mapping {
+                input: mapping::Input::Flexed(Side::Right),
+                transforms: vec![Transform::Latch],
+                output: "left".to_string(),
+            },
+            Mapping {
+                input: mapping::Input::Band(EegBand::LowAlpha),
+                transforms: vec![Transform::Threshold {
+                    on: 1000f64,
+                    off: 1000f64,
+                }],
+                output: "right".to_string(),
+            },
+        ]);
+    timeline.clear();
+
+    let mut power = [0u32; 8];
+    power[EegBand::LowAlpha as usize] = 5000;
+    let script = vec![
+        (0, DeviceSignal::Eeg(80, 30, 200)),
+        (0, DeviceSignal::EegPower(power)),
+        (0, DeviceSignal::Myo1(true, 450)),
+        (100, DeviceSignal::Myo2(true, 450)),
+        (200, DeviceSignal::Myo2(false, 0)),
+        (300, DeviceSignal::Myo2(true, 450)),
+    ];
+    for (time, signal) in script {
+        timeline.set_time(ms(time));
+        collector.handle_signal(signal).unwrap();
+    }
+
+    assert_eq!(timeline.analog_writes("trigger"), vec![(ms(0), 70f64)]);
+    assert_eq!(
+        timeline.pin_writes("left"),
+        vec![(ms(100), false), (ms(300), true)]
+    );
+    assert_eq!(timeline.pin_writes("right"), vec![(ms(0), false)]);
+}
+
+#[test]
+fn every_mapped_output_is_shown_as_sent() {
+    let timeline = Timeline::new();
+    let mut buttons = springboard::default_buttons();
+    buttons.push(ButtonConfig {
+        name: "jump".to_string(),
+        pin: 5,
+        active_level: ActiveLevel::Low,
+        jack: XacJack::A,
+        timing: PressTiming::NONE,
+    });
+    let springboard = mock::springboard_with(&timeline, &buttons, &["trigger", "throttle"]);
+    let mut collector = Collector::with_clock(springboard, timeline.clone()).with_mappings(&[
+        Mapping {
+            input: mapping::Input::Flexed(Side::Left),
+            transforms: vec![],
+            output: "jump".to_string(),
+        },
+        Mapping {
+            input: mapping::Input::Meditation,
+            transforms: vec![],
+            output: "throttle".to_string(),
+        },
+    ]);
+
+    collector
+        .handle_signal(DeviceSignal::Myo1(true, 450))
+        .unwrap();
+    collector
+        .handle_signal(DeviceSignal::Eeg(50, 40, 200))
+        .unwrap();
+
+    let sending = collector.ui_state().sending;
+    assert_eq!(sent(&sending, "jump"), Some(OutputValue::Button(true)));
+    assert_eq!(sent(&sending, "throttle"), Some(OutputValue::Analog(40f64)));
+    assert_eq!(sent(&sending, "left"), Some(OutputValue::Button(false)));
+    assert_eq!(sent(&sending, "trigger"), Some(OutputValue::Analog(0f64)));
+}
+
+#[test]
+fn reconfiguring_switches_mappings_or_changes_nothing() {
+    let timeline = Timeline::new();
+    let mut collector = Collector::with_clock(mock::springboard(&timeline), timeline.clone());
+    timeline.clear();
+
+    collector
+        .handle_signal(DeviceSignal::Myo1(true, 450))
+        .unwrap();
+
+    // The left myo now presses the right button, and the left button is let go
+    timeline.set_time(ms(100));
+    let swapped = CollectorConfig {
+        mappings: vec![Mapping {
+            input: mapping::Input::Flexed(Side::Left),
+            transforms: vec![],
+            output: "right".to_string(),
+        }],
+        ..CollectorConfig::default()
+    };
+    collector.reconfigure(swapped.clone()).unwrap();
+    timeline.set_time(ms(200));
+    collector
+        .handle_signal(DeviceSignal::Myo1(true, 450))
+        .unwrap();
+
+    // A configuration naming an output that isn't there is turned down whole
+    timeline.set_time(ms(300));
+    let missing = CollectorConfig {
+        data_amount: 10,
+        mappings: vec![Mapping {
+            input: mapping::Input::Flexed(Side::Left),
+            transforms: vec![],
+            output: "jump".to_string(),
+        }],
+        ..CollectorConfig::default()
+    };
+    match collector.reconfigure(missing) {
+        Err(WfpiError::UnknownOutput { name }) => assert_eq!(name, "jump"),
+        other => panic!("expected an unknown output, got {:?}", other),
+    }
+    assert_eq!(collector.config(), &swapped);
+    collector
+        .handle_signal(DeviceSignal::Myo1(false, 0))
+        .unwrap();
+
+    assert_eq!(
+        timeline.pin_writes("left"),
+        vec![(ms(0), false), (ms(100), true)]
+    );
+    assert_eq!(
+        timeline.pin_writes("right"),
+        vec![(ms(100), true), (ms(200), false), (ms(300), true)]
+    );
+}
+
+#[test]
+fn reconfiguring_keeps_the_state_of_unchanged_mappings() {
+    let timeline = Timeline::new();
+    let latched = Mapping {
+        input: mapping::Input::Flexed(Side::Right),
+        transforms: vec![Transform::Latch],
+        output: "left".to_string(),
+    };
+    let held = Mapping {
+        input: mapping::Input::Flexed(Side::Left),
+        transforms: vec![],
+        output: "right".to_string(),
+    };
+    let mut collector = Collector::with_clock(mock::springboard(&timeline), timeline.clone())
+        .with_mappings(&[latched.clone(), held]);
+    timeline.clear();
+
+    let script = vec![
+        (0, DeviceSignal::Myo2(true, 450)),
+        (50, DeviceSignal::Myo2(false, 0)),
+        (50, DeviceSignal::Myo1(true, 450)),
+    ];
+    for (time, signal) in script {
+        timeline.set_time(ms(time));
+        collector.handle_signal(signal).unwrap();
+    }
+
+    // Reloading the same settings leaves both buttons pressed
+    timeline.set_time(ms(100));
+    collector.reconfigure(collector.config().clone()).unwrap();
+
+    // Dropping the right button's mapping lets it go, and the latch carries on
+    timeline.set_time(ms(200));
+    collector
+        .reconfigure(CollectorConfig {
+            data_amount: 10,
+            mappings: vec![latched],
+            ..collector.config().clone()
+        })
+        .unwrap();
+    timeline.set_time(ms(300));
+    collector
+        .handle_signal(DeviceSignal::Myo2(true, 450))
+        .unwrap();
+
+    assert_eq!(
+        timeline.pin_writes("left"),
+        vec![(ms(0), false), (ms(300), true)]
+    );
+    assert_eq!(
+        timeline.pin_writes("right"),
+        vec![(ms(50), false), (ms(200), true)]
+    );
+}
+
+#[test]
+fn mindwave_band_powers_are_parsed() {
+    let timeline = Timeline::new();
+    let uart = MockUart::new(&timeline);
+    let mut mindwave = Mindwave::new(uart.clone());
+
+    let mut payload = vec![0x02, 0, 0x83, 24];
+    for band in 0..8u32 {
+        let value = band * 0x01_0203;
+        payload.extend_from_slice(&[(value >> 16) as u8, (value >> 8) as u8, value as u8]);
+    }
+    let checksum = 0xFF - payload.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    uart.push(&[0xAA, 0xAA, payload.len() as u8]);
+    uart.push(&payload);
+    uart.push(&[checksum]);
+
+    mindwave.update().unwrap();
+    let power = mindwave.get_eeg_power().unwrap();
+    assert_eq!(power[EegBand::Delta as usize], 0);
+    assert_eq!(power[EegBand::MidGamma as usize], 7 * 0x01_0203);
+}
@@ -0,0 +1,251 @@
+use std::time::Duration;
+
+use wfpi::button_mode::ButtonMode;
+use wfpi::config::{Config, DEFAULT_CONFIG};
+use wfpi::eeg::EegBand;
+use wfpi::mapping::{Input, Transform};
+use wfpi::myo::{MainsFrequency, NotchFrequency, Side};
+use wfpi::pointer::{MouseButton, PointerAction, SpeedSource};
+use wfpi::shaping::Curve;
+use wfpi::springboard::{ActiveLevel, XacJack};
+use wfpi::WfpiError;
+
+/// The key an invalid configuration is rejected at
+fn invalid_key(text: &str) -> String {
+    match Config::parse(text) {
+        Err(WfpiError::InvalidConfig { key, .. }) => key,
+        Err(e) => panic!("rejected for the wrong reason: {}", e),
+        Ok(_) => panic!("accepted an invalid configuration"),
+    }
+}
+
+#[test]
+fn the_default_file_is_the_default_configuration() {
+    assert_eq!(Config::parse(DEFAULT_CONFIG).unwrap(), Config::default());
+    assert_eq!(Config::parse("").unwrap(), Config::default());
+}
+
+#[test]
+fn settings_left_out_keep_their_defaults() {
+    let config = Config::parse(
+        "[eeg]\n\
+         baud_rate = 9600\n\
+         [collector]\n\
+         data_amount = 50\n\
+         myo_stale_after_ms = 750\n\
+         [myo]\n\
+         mains_frequency = 50\n",
+    )
+    .unwrap();
+
+    let default = Config::default();
+    assert_eq!(config.eeg_baud_rate, 9600);
+    assert_eq!(config.collector.data_amount, 50);
+    assert_eq!(config.collector.myo_stale_after, Duration::from_millis(750));
+    assert_eq!(
+        config.collector.eeg_stale_after,
+        default.collector.eeg_stale_after
+    );
+    assert_eq!(config.collector.mappings, default.collector.mappings);
+    assert_eq!(config.wiring, default.wiring);
+    assert_eq!(
+        config.myo.mains_frequency,
+        MainsFrequency::Fixed(NotchFrequency::Freq50Hz)
+    );
+    assert_eq!(config.myo.left_pipeline, default.myo.left_pipeline);
+}
+
+#[test]
+fn outputs_and_mappings_are_read_from_the_file() {
+    let config = Config::parse(
+        r#"
+        [[outputs.buttons]]
+        name = "jump"
+        pin = 24
+        jack = "a"
+        min_press_ms = 80
+
+        [[outputs.analog]]
+        name = "throttle"
+        device = { type = "ds3502", address = 0x29 }
+        rest = 50
+
+        [scanning]
+        outputs = ["jump"]
+
+        [[mappings]]
+        input = { band = "low_alpha" }
+        transforms = [
+            { type = "scale", input_min = 0, input_max = 40000 },
+            { type = "threshold", on = 60, off = 40 },
+            { type = "turbo", interval_ms = 150 },
+        ]
+        output = "jump"
+
+        [[mappings]]
+        input = "meditation"
+        transforms = [{ type = "shape", curve = { exponential = 2.0 } }]
+        output = "throttle"
+        "#,
+    )
+    .unwrap();
+
+    let button = &config.wiring.buttons[0];
+    assert_eq!(button.name, "jump");
+    assert_eq!(button.pin, 24);
+    assert_eq!(button.active_level, ActiveLevel::Low);
+    assert_eq!(button.jack, XacJack::A);
+    assert_eq!(button.timing.min_press, Duration::from_millis(80));
+    assert_eq!(config.wiring.analog_outputs[0].name, "throttle");
+    assert!((config.wiring.analog_outputs[0].rest - 50f64).abs() < 1e-9);
+    assert_eq!(config.collector.scanning.outputs, &["jump"]);
+
+    let mappings = &config.collector.mappings;
+    assert_eq!(mappings.len(), 2);
+    assert_eq!(mappings[0].input, Input::Band(EegBand::LowAlpha));
+    assert_eq!(mappings[0].output, "jump");
+    assert_eq!(mappings[0].transforms.len(), 3);
+    assert_eq!(
+        mappings[0].transforms[1],
+        Transform::Threshold {
+            on: 60f64,
+            off: 40f64
+        }
+    );
+    assert_eq!(
+        mappings[0].transforms[2],
+        Transform::Mode(ButtonMode::Turbo {
+            interval: Duration::from_millis(150)
+        })
+    );
+    assert_eq!(mappings[1].input, Input::Meditation);
+}
+
+#[test]
+fn pointer_morse_and_fatigue_settings_are_read_from_the_file() {
+    let config = Config::parse(
+        r#"
+        [myo]
+        adapt_threshold_to_fatigue = true
+        fatigue_threshold_drop = 0.3
+        suggest_breaks = false
+
+        [pointer]
+        left = { type = "move", x = 0, y = -1 }
+        right = { type = "click", button = "right" }
+        acceleration_curve = "linear"
+        speed_source = "emg"
+        dwell_ms = 800
+
+        [morse]
+        side = "right"
+        unit_ms = 300
+        adaptation = 0
+        "#,
+    )
+    .unwrap();
+
+    assert!(config.myo.adapt_threshold_to_fatigue);
+    assert!((config.myo.fatigue_threshold_drop - 0.3).abs() < 1e-6);
+    assert!(!config.myo.suggest_breaks);
+
+    let pointer = config.collector.pointer;
+    assert_eq!(pointer.left, PointerAction::Move { x: 0f64, y: -1f64 });
+    assert_eq!(pointer.right, PointerAction::Click(MouseButton::Right));
+    assert_eq!(pointer.acceleration_curve, Curve::Linear);
+    assert_eq!(pointer.speed_source, SpeedSource::Emg);
+    assert_eq!(pointer.dwell, Some(Duration::from_millis(800)));
+    let default = Config::default().collector;
+    assert_eq!(pointer.sweep_period, default.pointer.sweep_period);
+
+    assert_eq!(config.collector.morse_side, Side::Right);
+    assert_eq!(config.collector.morse.unit, Duration::from_millis(300));
+    assert!(config.collector.morse.adaptation.abs() < 1e-9);
+    assert_eq!(config.collector.morse.min_unit, default.morse.min_unit);
+
+    // Only the fatigue settings wait for a restart
+    let restart = Config::default().needs_restart(&config);
+    assert_eq!(
+        restart,
+        vec![
+            "myo.adapt_threshold_to_fatigue",
+            "myo.fatigue_threshold_drop",
+            "myo.suggest_breaks"
+        ]
+    );
+}
+
+#[test]
+fn the_analog_channel_can_set_the_pointer_speed() {
+    let config = Config::parse("[pointer]\nspeed_source = \"analog\"\n").unwrap();
+    assert_eq!(config.collector.pointer.speed_source, SpeedSource::Analog);
+}
+
+#[test]
+fn errors_name_the_key_at_fault() {
+    assert_eq!(
+        invalid_key("[[mappings]]\ninput = \"attention\"\noutput = \"nowhere\"\n"),
+        "mappings[0].output"
+    );
+    assert_eq!(
+        invalid_key("[[outputs.buttons]]\nname = \"a\"\npin = 9\njack = \"a\"\n"),
+        "outputs.buttons[0].pin"
+    );
+    assert_eq!(
+        invalid_key(
+            "[[mappings]]\ninput = \"attention\"\noutput = \"left\"\n\
+             transforms = [{ type = \"invert\" }, { type = \"threshold\", on = 40, off = 60 }]\n"
+        ),
+        "mappings[0].transforms[1].off"
+    );
+    // The default scan reaches outputs this wiring doesn't have
+    assert_eq!(
+        invalid_key("[[outputs.analog]]\nname = \"brake\"\n"),
+        "scanning.outputs[2]"
+    );
+    assert_eq!(
+        invalid_key("[collector]\ndata_amount = 0\n"),
+        "collector.data_amount"
+    );
+    assert_eq!(
+        invalid_key("[myo]\nmains_frequency = 55\n"),
+        "myo.mains_frequency"
+    );
+    assert_eq!(
+        invalid_key("[myo]\nfatigue_threshold_drop = 1.5\n"),
+        "myo.fatigue_threshold_drop"
+    );
+    assert_eq!(
+        invalid_key("[pointer]\nmin_speed = 700\n"),
+        "pointer.max_speed"
+    );
+    assert_eq!(invalid_key("[morse]\nside = \"analog\"\n"), "morse.side");
+    assert_eq!(invalid_key("[morse]\nunit_ms = 50\n"), "morse.unit_ms");
+    assert_eq!(invalid_key("[morse]\nword_gap = 2.0\n"), "morse.word_gap");
+
+    // Mistyped keys and values are caught as the file is read
+    match Config::parse("[eeg]\nbaud = 9600\n") {
+        Err(WfpiError::ConfigSyntax { err }) => {
+            assert!(err.to_string().contains("baud"), "{}", err)
+        }
+        other => panic!("expected a syntax error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn device_settings_wait_for_a_restart() {
+    let running = Config::default();
+    let changed = Config::parse(
+        "[eeg]\n\
+         baud_rate = 9600\n\
+         [myo]\n\
+         left_pipeline = [{ type = \"flappy\", threshold = 150, harmonics = false }]\n\
+         [collector]\n\
+         data_amount = 50\n",
+    )
+    .unwrap();
+
+    // Pipelines and the collector's settings switch over while running
+    assert_eq!(running.needs_restart(&changed), vec!["eeg.baud_rate"]);
+    assert!(running.needs_restart(&running.clone()).is_empty());
+}
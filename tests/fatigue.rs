@@ -0,0 +1,126 @@
+//! Checks the spectrum estimate and the fatigue tracking built on it, with synthetic EMG.
+
+use std::f32::consts::PI;
+
+use wfpi::myo::{FatigueLevel, FatigueMonitor, FatigueReport, PowerSpectrum};
+
+const SAMPLE_RATE: f32 = 1000f32;
+const WINDOW: usize = 256;
+
+/// `count` samples of a sinusoid, starting `start` samples in
+fn tone(freq: f32, amplitude: f32, start: usize, count: usize) -> Vec<f32> {
+    (start..start + count)
+        .map(|n| amplitude * (2f32 * PI * freq * n as f32 / SAMPLE_RATE).sin())
+        .collect()
+}
+
+/// Feeds `windows` whole windows of a contraction at `freq`, returning the reports
+fn contract(
+    monitor: &mut FatigueMonitor,
+    freq: f32,
+    amplitude: f32,
+    windows: usize,
+) -> Vec<FatigueReport> {
+    tone(freq, amplitude, 0, windows * WINDOW)
+        .into_iter()
+        .filter_map(|sample| monitor.update(sample.round() as i32, true))
+        .collect()
+}
+
+#[test]
+fn median_frequency_follows_the_power() {
+    let mut spectrum = PowerSpectrum::new(WINDOW, SAMPLE_RATE);
+    let bin = spectrum.bin_width();
+
+    spectrum.compute(&tone(100f32, 200f32, 0, WINDOW));
+    assert!((spectrum.median_frequency(20f32, 150f32) - 100f32).abs() <= bin);
+
+    // Most of the power in the lower of two tones pulls the median down to it
+    let two_tones: Vec<_> = tone(40f32, 300f32, 0, WINDOW)
+        .iter()
+        .zip(tone(120f32, 100f32, 0, WINDOW))
+        .map(|(low, high)| low + high)
+        .collect();
+    spectrum.compute(&two_tones);
+    assert!((spectrum.median_frequency(20f32, 150f32) - 40f32).abs() <= bin);
+
+    // A constant offset carries no power at all
+    spectrum.compute(&[512f32; WINDOW]);
+    assert!(spectrum.median_frequency(20f32, 150f32).abs() < 1e-6);
+}
+
+#[test]
+fn band_amplitude_is_in_input_units() {
+    let mut spectrum = PowerSpectrum::new(WINDOW, SAMPLE_RATE);
+    let offset: Vec<_> = tone(50f32, 30f32, 0, WINDOW)
+        .iter()
+        .map(|x| x + 512f32)
+        .collect();
+    spectrum.compute(&offset);
+
+    let amplitude = spectrum.band_amplitude(44f32, 56f32);
+    assert!((amplitude - 30f32).abs() < 3f32, "{}", amplitude);
+    assert!(spectrum.band_amplitude(90f32, 110f32) < 1f32);
+}
+
+#[test]
+fn fatigue_rises_as_the_median_frequency_falls() {
+    let mut monitor = FatigueMonitor::new(SAMPLE_RATE);
+
+    // The first ten windows make the baseline
+    let baseline = contract(&mut monitor, 100f32, 100f32, 10);
+    assert_eq!(baseline.len(), 10);
+    assert!(baseline[..9]
+        .iter()
+        .all(|report| report.level == FatigueLevel::Calibrating));
+    assert_eq!(baseline[9].level, FatigueLevel::Fresh);
+    assert!((baseline[9].baseline_median_frequency - 100f32).abs() <= 4f32);
+
+    // Holding the same contraction stays fresh
+    let steady = contract(&mut monitor, 100f32, 100f32, 5);
+    assert!(steady.iter().all(|r| r.level == FatigueLevel::Fresh));
+
+    // A drop of about 15% is tiring, and the amplitude rising is tracked alongside
+    let tiring = contract(&mut monitor, 85f32, 150f32, 40);
+    let last = tiring.last().unwrap();
+    assert_eq!(last.level, FatigueLevel::Tiring);
+    assert!(last.index > 0.4 && last.index < 0.8, "{}", last.index);
+    assert!(last.amplitude_ratio > 1.4, "{}", last.amplitude_ratio);
+
+    // A drop of a third or more is fully fatigued
+    let fatigued = contract(&mut monitor, 65f32, 150f32, 40);
+    let last = fatigued.last().unwrap();
+    assert_eq!(last.level, FatigueLevel::Fatigued);
+    assert!((last.index - 1f32).abs() < 1e-6);
+    assert_eq!(monitor.report(), *last);
+
+    // The levels only ever moved one way
+    let levels: Vec<_> = steady
+        .iter()
+        .chain(&tiring)
+        .chain(&fatigued)
+        .map(|r| r.level as u8)
+        .collect();
+    assert!(levels.windows(2).all(|pair| pair[0] <= pair[1]));
+}
+
+#[test]
+fn only_whole_contractions_are_analysed() {
+    let mut monitor = FatigueMonitor::new(SAMPLE_RATE);
+
+    // Relaxing before a window is full throws it away
+    let samples = tone(100f32, 100f32, 0, 2 * WINDOW);
+    for (n, sample) in samples.iter().enumerate() {
+        let contracted = n % WINDOW < WINDOW - 1;
+        assert_eq!(monitor.update(sample.round() as i32, contracted), None);
+    }
+    assert_eq!(monitor.report().level, FatigueLevel::Calibrating);
+
+    // Forgetting the baseline starts the calibration over
+    contract(&mut monitor, 100f32, 100f32, 10);
+    assert_eq!(monitor.report().level, FatigueLevel::Fresh);
+    monitor.reset();
+    assert_eq!(monitor.report(), FatigueReport::default());
+    let restarted = contract(&mut monitor, 100f32, 100f32, 1);
+    assert_eq!(restarted[0].level, FatigueLevel::Calibrating);
+}
@@ -0,0 +1,134 @@
+//! Checks the mains frequency detection and the notch filters it tunes, with synthetic hum.
+
+use std::f32::consts::PI;
+
+use wfpi::emg_process::Stage;
+use wfpi::myo::{EMGFilters, Filter2nd, MainsDetector, NotchFrequency, SampleFrequency};
+
+const SAMPLE_RATE: f32 = 1000f32;
+/// The detector decides once every two seconds of readings
+const WINDOW: usize = 2000;
+
+/// A repeatable stand-in for muscle activity and ADC noise, from -1 to 1
+struct Noise(u32);
+
+impl Noise {
+    fn next(&mut self) -> f32 {
+        self.0 = self.0.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        ((self.0 >> 16) & 0x7fff) as f32 / 16383.5 - 1f32
+    }
+}
+
+fn sine(freq: f32, amplitude: f32, n: usize) -> f32 {
+    amplitude * (2f32 * PI * freq * n as f32 / SAMPLE_RATE).sin()
+}
+
+/// Raw readings of hum at `mains` Hz with its 2nd and 3rd harmonics, on top of noise
+fn hum(mains: f32, noise: &mut Noise, n: usize) -> f32 {
+    512f32
+        + sine(mains, 80f32, n)
+        + sine(2f32 * mains, 25f32, n)
+        + sine(3f32 * mains, 15f32, n)
+        + 60f32 * noise.next()
+}
+
+/// Feeds one decision's worth of readings, returning any change in the detected frequency
+fn listen(
+    detector: &mut MainsDetector,
+    mut reading: impl FnMut(usize) -> f32,
+) -> Option<NotchFrequency> {
+    let changes: Vec<_> = (0..WINDOW)
+        .filter_map(|n| detector.update(reading(n)))
+        .collect();
+    assert!(changes.len() <= 1);
+    changes.first().cloned()
+}
+
+/// The largest output of `stage` for a sinusoid at `freq` Hz, once the filter has settled
+fn gain(stage: &mut dyn Stage, freq: f32) -> f32 {
+    stage.reset();
+    (0..2000)
+        .map(|n| stage.process(f64::from(sine(freq, 1f32, n))) as f32)
+        .skip(1000)
+        .fold(0f32, |max, output| max.max(output.abs()))
+}
+
+#[test]
+fn hum_is_told_apart_at_50_and_60hz() {
+    let mut noise = Noise(1);
+
+    let mut detector = MainsDetector::new(SAMPLE_RATE);
+    assert_eq!(
+        listen(&mut detector, |n| hum(50f32, &mut noise, n)),
+        Some(NotchFrequency::Freq50Hz)
+    );
+    // An unchanged decision isn't reported again
+    assert_eq!(listen(&mut detector, |n| hum(50f32, &mut noise, n)), None);
+
+    let mut detector = MainsDetector::new(SAMPLE_RATE);
+    assert_eq!(
+        listen(&mut detector, |n| hum(60f32, &mut noise, n)),
+        Some(NotchFrequency::Freq60Hz)
+    );
+}
+
+#[test]
+fn a_change_of_mind_needs_confirming() {
+    let mut noise = Noise(3);
+    let mut detector = MainsDetector::new(SAMPLE_RATE);
+    assert_eq!(
+        listen(&mut detector, |n| hum(60f32, &mut noise, n)),
+        Some(NotchFrequency::Freq60Hz)
+    );
+
+    // A single window of 50Hz is passing interference
+    assert_eq!(listen(&mut detector, |n| hum(50f32, &mut noise, n)), None);
+    assert_eq!(listen(&mut detector, |n| hum(60f32, &mut noise, n)), None);
+
+    // Three in a row settle it
+    assert_eq!(listen(&mut detector, |n| hum(50f32, &mut noise, n)), None);
+    assert_eq!(listen(&mut detector, |n| hum(50f32, &mut noise, n)), None);
+    assert_eq!(
+        listen(&mut detector, |n| hum(50f32, &mut noise, n)),
+        Some(NotchFrequency::Freq50Hz)
+    );
+}
+
+#[test]
+fn notch_removes_its_frequency_only() {
+    let mut notch = Filter2nd::notch(100f32, SampleFrequency::Freq1000Hz, 10f32);
+    assert!(gain(&mut notch, 100f32) < 0.05);
+    assert!(gain(&mut notch, 30f32) > 0.95);
+    assert!(gain(&mut notch, 200f32) > 0.95);
+}
+
+#[test]
+fn anti_hum_filters_follow_the_mains_frequency() {
+    for &(mains, other) in &[
+        (NotchFrequency::Freq50Hz, 60f32),
+        (NotchFrequency::Freq60Hz, 50f32),
+    ] {
+        let mains_hz = mains as u32 as f32;
+        let mut filters = EMGFilters::new(SampleFrequency::Freq1000Hz, mains, true, false, false);
+        assert!(gain(&mut filters, mains_hz) < 0.2, "{}Hz", mains_hz);
+        assert!(gain(&mut filters, other) > 0.8, "{}Hz", other);
+
+        // The harmonics only go with the harmonic notches on
+        assert!(gain(&mut filters, 2f32 * mains_hz) > 0.5);
+        filters.set_harmonic_filters(true);
+        assert!(gain(&mut filters, 2f32 * mains_hz) < 0.1);
+        assert!(gain(&mut filters, 3f32 * mains_hz) < 0.1);
+    }
+
+    // Retuning moves the notch
+    let mut filters = EMGFilters::new(
+        SampleFrequency::Freq1000Hz,
+        NotchFrequency::Freq60Hz,
+        true,
+        false,
+        false,
+    );
+    filters.set_notch_frequency(NotchFrequency::Freq50Hz);
+    assert!(gain(&mut filters, 50f32) < 0.2);
+    assert!(gain(&mut filters, 60f32) > 0.8);
+}
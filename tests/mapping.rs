@@ -0,0 +1,145 @@
+//! Checks how readings run through the transforms of a mapping to their outputs.
+
+use std::time::Duration;
+
+use wfpi::button_mode::ButtonMode;
+use wfpi::mapping::{Input, Mapper, Mapping, Transform};
+use wfpi::myo::Side;
+use wfpi::shaping::Curve;
+
+fn ms(time: u64) -> Duration {
+    Duration::from_millis(time)
+}
+
+fn mapper(input: Input, transforms: &[Transform]) -> Mapper {
+    Mapper::new(&[Mapping {
+        input,
+        transforms: transforms.to_vec(),
+        output: "out".to_string(),
+    }])
+}
+
+/// Runs each value through `mapper` in turn, and returns what the output is given.
+fn run(mapper: &mut Mapper, input: Input, values: &[f64]) -> Vec<f64> {
+    values
+        .iter()
+        .map(|&value| mapper.update(input, value, ms(0))[0].1)
+        .collect()
+}
+
+#[test]
+fn threshold_has_hysteresis() {
+    let mut mapper = mapper(
+        Input::Attention,
+        &[Transform::Threshold {
+            on: 60f64,
+            off: 40f64,
+        }],
+    );
+    assert_eq!(
+        run(
+            &mut mapper,
+            Input::Attention,
+            &[50f64, 60f64, 50f64, 40f64, 50f64]
+        ),
+        vec![0f64, 100f64, 100f64, 0f64, 0f64]
+    );
+}
+
+#[test]
+fn transforms_apply_in_order() {
+    let mut mapper = mapper(
+        Input::Analog,
+        &[
+            Transform::Scale {
+                input_min: 0f64,
+                input_max: 1000f64,
+                output_min: 0f64,
+                output_max: 100f64,
+            },
+            Transform::Curve(Curve::Exponential(0f64)),
+            Transform::Invert,
+        ],
+    );
+    assert_eq!(
+        run(&mut mapper, Input::Analog, &[0f64, 250f64, 2000f64]),
+        vec![100f64, 75f64, 0f64]
+    );
+    // Other inputs don't reach it
+    assert!(mapper.update(Input::Attention, 50f64, ms(0)).is_empty());
+}
+
+#[test]
+fn latch_flips_on_each_rise() {
+    let mut mapper = mapper(Input::Flexed(Side::Left), &[Transform::Latch]);
+    assert_eq!(
+        run(
+            &mut mapper,
+            Input::Flexed(Side::Left),
+            &[100f64, 0f64, 100f64, 100f64, 0f64, 100f64]
+        ),
+        vec![100f64, 100f64, 0f64, 0f64, 0f64, 100f64]
+    );
+    mapper.reset();
+    assert_eq!(
+        run(&mut mapper, Input::Flexed(Side::Left), &[0f64]),
+        vec![0f64]
+    );
+}
+
+#[test]
+fn button_modes_carry_on_between_readings() {
+    let mut mapper = mapper(
+        Input::Flexed(Side::Left),
+        &[
+            Transform::Mode(ButtonMode::Pulse {
+                length: Duration::from_millis(100),
+            }),
+            Transform::Invert,
+        ],
+    );
+    // Nothing to carry on before the first reading
+    assert!(mapper.tick(ms(0)).is_empty());
+    assert_eq!(
+        mapper.update(Input::Flexed(Side::Left), 100f64, ms(0)),
+        vec![("out".to_string(), 0f64)]
+    );
+    assert_eq!(mapper.tick(ms(50)), vec![("out".to_string(), 0f64)]);
+    // The pulse ends while the flex is still held, and the transforms after it follow
+    assert_eq!(mapper.tick(ms(100)), vec![("out".to_string(), 100f64)]);
+}
+
+#[test]
+fn the_highest_of_several_mappings_wins() {
+    let mut mapper = Mapper::new(&[
+        Mapping {
+            input: Input::Flexed(Side::Left),
+            transforms: vec![],
+            output: "jump".to_string(),
+        },
+        Mapping {
+            input: Input::Attention,
+            transforms: vec![Transform::Threshold {
+                on: 80f64,
+                off: 80f64,
+            }],
+            output: "jump".to_string(),
+        },
+    ]);
+    assert_eq!(
+        mapper.update(Input::Attention, 90f64, ms(0)),
+        vec![("jump".to_string(), 100f64)]
+    );
+    assert_eq!(
+        mapper.update(Input::Flexed(Side::Left), 0f64, ms(0)),
+        vec![("jump".to_string(), 100f64)]
+    );
+    assert_eq!(
+        mapper.update(Input::Attention, 50f64, ms(0)),
+        vec![("jump".to_string(), 0f64)]
+    );
+    assert_eq!(
+        mapper.outputs(|input| input == Input::Attention),
+        vec!["jump"]
+    );
+}
@@ -0,0 +1,109 @@
+//! Checks how flexes are decoded into text, and how the timing follows the user.
+
+use std::time::Duration;
+
+use wfpi::morse::{MorseConfig, MorseDecoder, TextKey};
+
+const CONFIG: MorseConfig = MorseConfig {
+    unit: Duration::from_millis(100),
+    min_unit: Duration::from_millis(50),
+    max_unit: Duration::from_millis(1000),
+    adaptation: 0f64,
+    min_flex: Duration::from_millis(20),
+    letter_gap: 2f64,
+    word_gap: 5f64,
+};
+
+fn ms(time: u64) -> Duration {
+    Duration::from_millis(time)
+}
+
+/// Plays `code` into `decoder` every 10ms with standard timing for a dot `unit` long,
+/// starting at `start`, and returns what's typed and when it ended, in milliseconds.
+fn play(decoder: &mut MorseDecoder, code: &str, unit: u64, start: u64) -> (Vec<TextKey>, u64) {
+    let mut keys = vec![];
+    let mut time = start;
+    let mut hold = |decoder: &mut MorseDecoder, active: bool, length: u64, time: &mut u64| {
+        for t in (*time..*time + length).step_by(10) {
+            keys.extend(decoder.update(active, ms(t)));
+        }
+        *time += length;
+    };
+    for c in code.chars() {
+        match c {
+            '.' => hold(decoder, true, unit, &mut time),
+            '-' => hold(decoder, true, 3 * unit, &mut time),
+            // Between letters, on top of the gap after every symbol
+            ' ' => hold(decoder, false, 2 * unit, &mut time),
+            // Between words
+            '/' => hold(decoder, false, 6 * unit, &mut time),
+            _ => unreachable!(),
+        }
+        if c == '.' || c == '-' {
+            hold(decoder, false, unit, &mut time);
+        }
+    }
+    (keys, time)
+}
+
+#[test]
+fn decodes_letters_and_words() {
+    let mut decoder = MorseDecoder::new(CONFIG);
+    let (keys, _) = play(&mut decoder, "... --- ... / .- .-.-. /", 100, 0);
+    assert_eq!(
+        keys,
+        vec![
+            TextKey::Char('S'),
+            TextKey::Char('O'),
+            TextKey::Char('S'),
+            TextKey::Space,
+            TextKey::Char('A'),
+            TextKey::Enter,
+        ]
+    );
+    assert_eq!(decoder.status().text, "");
+}
+
+#[test]
+fn the_error_prosign_deletes_and_the_preview_follows() {
+    let mut decoder = MorseDecoder::new(CONFIG);
+    let (keys, _) = play(&mut decoder, ".. - ........ /", 100, 0);
+    assert_eq!(keys.last(), Some(&TextKey::Backspace));
+    assert_eq!(decoder.status().text, "I");
+}
+
+#[test]
+fn timing_adapts_to_a_slower_rhythm() {
+    // Too slow for the starting timing, which reads the dots as dashes
+    let mut decoder = MorseDecoder::new(CONFIG);
+    let (keys, _) = play(&mut decoder, "... /", 300, 0);
+    assert_eq!(keys[0], TextKey::Char('T'));
+
+    let config = MorseConfig {
+        adaptation: 0.5f64,
+        ..CONFIG
+    };
+    let mut decoder = MorseDecoder::new(config);
+    let (keys, end) = play(&mut decoder, "-.-. .- /", 180, 0);
+    assert_eq!(
+        keys,
+        vec![TextKey::Char('C'), TextKey::Char('A'), TextKey::Space]
+    );
+    // Slowing down further, as the user tires
+    let (keys, _) = play(&mut decoder, "... /", 300, end);
+    assert_eq!(keys, vec![TextKey::Char('S'), TextKey::Space]);
+    assert!(decoder.unit() > ms(250));
+}
+
+#[test]
+fn twitches_are_ignored() {
+    let mut decoder = MorseDecoder::new(CONFIG);
+    decoder.update(true, ms(0));
+    decoder.update(false, ms(10));
+    let mut keys = vec![];
+    for t in (10..2000).step_by(10) {
+        keys.extend(decoder.update(false, ms(t)));
+    }
+    assert!(keys.is_empty());
+    assert_eq!(decoder.status().symbols, "");
+}
@@ -0,0 +1,167 @@
+//! Checks how flexes and attention move and click the pointer.
+
+use std::time::Duration;
+
+use wfpi::myo::Side;
+use wfpi::pointer::{
+    MouseButton, Pointer, PointerAction, PointerConfig, PointerOutput, SpeedSource,
+};
+use wfpi::shaping::Curve;
+use wfpi::Result;
+
+const CONFIG: PointerConfig = PointerConfig {
+    left: PointerAction::Move { x: 1f64, y: 0f64 },
+    right: PointerAction::Click(MouseButton::Right),
+    min_speed: 100f64,
+    max_speed: 100f64,
+    acceleration: Duration::from_secs(0),
+    acceleration_curve: Curve::Linear,
+    speed_source: SpeedSource::Constant,
+    emg_full_speed: 400,
+    dwell: None,
+    sweep_period: Duration::from_secs(4),
+};
+
+#[derive(Default)]
+struct Recorder {
+    moved: (i32, i32),
+    clicks: Vec<MouseButton>,
+}
+
+impl PointerOutput for Recorder {
+    fn move_by(&mut self, dx: i32, dy: i32) -> Result<()> {
+        self.moved.0 += dx;
+        self.moved.1 += dy;
+        Ok(())
+    }
+
+    fn click(&mut self, button: MouseButton) -> Result<()> {
+        self.clicks.push(button);
+        Ok(())
+    }
+}
+
+fn ms(time: u64) -> Duration {
+    Duration::from_millis(time)
+}
+
+/// Updates `pointer` every 10ms from `from` to `to`, in milliseconds.
+fn run(pointer: &mut Pointer, output: &mut Recorder, from: u64, to: u64) {
+    for time in (from..=to).step_by(10) {
+        pointer.update(ms(time), output).unwrap();
+    }
+}
+
+#[test]
+fn held_flexes_move_the_pointer() {
+    let mut pointer = Pointer::new(CONFIG);
+    let mut output = Recorder::default();
+    run(&mut pointer, &mut output, 0, 100);
+    assert_eq!(output.moved, (0, 0));
+
+    pointer.set_channel(Side::Left, true, 300);
+    run(&mut pointer, &mut output, 100, 1100);
+    assert_eq!(output.moved, (100, 0));
+
+    pointer.set_channel(Side::Left, false, 0);
+    run(&mut pointer, &mut output, 1100, 2000);
+    assert_eq!(output.moved, (100, 0));
+    assert!(output.clicks.is_empty());
+}
+
+#[test]
+fn speed_builds_up_and_follows_attention() {
+    let config = PointerConfig {
+        max_speed: 300f64,
+        acceleration: Duration::from_secs(1),
+        speed_source: SpeedSource::Attention,
+        ..CONFIG
+    };
+    let mut pointer = Pointer::new(config);
+    let mut output = Recorder::default();
+    pointer.set_attention(50f64);
+    pointer.set_channel(Side::Left, true, 300);
+
+    pointer.update(ms(0), &mut output).unwrap();
+    assert!((pointer.status(ms(0)).speed - 100f64).abs() < 1e-9);
+    run(&mut pointer, &mut output, 0, 500);
+    assert!((pointer.status(ms(500)).speed - 150f64).abs() < 1e-9);
+    run(&mut pointer, &mut output, 500, 2000);
+    // Halfway between the lowest and highest speeds at half attention
+    assert!((pointer.status(ms(2000)).speed - 200f64).abs() < 1e-9);
+}
+
+#[test]
+fn analog_channel_sets_the_speed() {
+    let config = PointerConfig {
+        max_speed: 300f64,
+        acceleration: Duration::from_secs(0),
+        speed_source: SpeedSource::Analog,
+        ..CONFIG
+    };
+    let mut pointer = Pointer::new(config);
+    let mut output = Recorder::default();
+    pointer.set_channel(Side::Left, true, 300);
+
+    pointer.set_analog(0f64);
+    pointer.update(ms(0), &mut output).unwrap();
+    assert!((pointer.status(ms(0)).speed - 100f64).abs() < 1e-9);
+    pointer.set_analog(1023f64);
+    pointer.update(ms(10), &mut output).unwrap();
+    assert!((pointer.status(ms(10)).speed - 300f64).abs() < 1e-9);
+}
+
+#[test]
+fn flexes_click() {
+    let mut pointer = Pointer::new(CONFIG);
+    let mut output = Recorder::default();
+    pointer.set_channel(Side::Right, true, 300);
+    pointer.set_channel(Side::Right, true, 300);
+    pointer.update(ms(0), &mut output).unwrap();
+    pointer.set_channel(Side::Right, false, 0);
+    pointer.set_channel(Side::Right, true, 300);
+    pointer.update(ms(10), &mut output).unwrap();
+
+    assert_eq!(output.clicks, vec![MouseButton::Right, MouseButton::Right]);
+    assert_eq!(output.moved, (0, 0));
+}
+
+#[test]
+fn resting_after_a_movement_dwell_clicks_once() {
+    let config = PointerConfig {
+        dwell: Some(Duration::from_secs(1)),
+        ..CONFIG
+    };
+    let mut pointer = Pointer::new(config);
+    let mut output = Recorder::default();
+    // No movement, no dwell
+    run(&mut pointer, &mut output, 0, 2000);
+    assert!(output.clicks.is_empty());
+
+    pointer.set_channel(Side::Left, true, 300);
+    run(&mut pointer, &mut output, 2000, 2100);
+    pointer.set_channel(Side::Left, false, 0);
+    run(&mut pointer, &mut output, 2100, 2600);
+    assert_eq!(pointer.status(ms(2600)).dwell, Some(0.5));
+    run(&mut pointer, &mut output, 2600, 5000);
+    assert_eq!(output.clicks, vec![MouseButton::Left]);
+    assert_eq!(pointer.status(ms(5000)).dwell, None);
+}
+
+#[test]
+fn sweep_turns_while_resting() {
+    let config = PointerConfig {
+        left: PointerAction::Sweep,
+        ..CONFIG
+    };
+    let mut pointer = Pointer::new(config);
+    let mut output = Recorder::default();
+    // A quarter turn, to pointing down
+    run(&mut pointer, &mut output, 0, 1000);
+    assert!((pointer.status(ms(1000)).heading - 90f64).abs() < 1e-6);
+
+    pointer.set_channel(Side::Left, true, 300);
+    run(&mut pointer, &mut output, 1000, 2000);
+    assert_eq!(output.moved, (0, 100));
+    assert!((pointer.status(ms(2000)).heading - 90f64).abs() < 1e-6);
+}
@@ -0,0 +1,122 @@
+//! Checks the EMG signal quality judgement against the ways a loose electrode shows up.
+
+use std::f32::consts::PI;
+
+use wfpi::myo::{QualityMonitor, QualityReport, SignalQuality};
+
+const SAMPLE_RATE: f32 = 1000f32;
+const WINDOW: usize = 256;
+
+/// A repeatable stand-in for the broadband noise of a contraction, from -1 to 1
+struct Noise(u32);
+
+impl Noise {
+    fn next(&mut self) -> f32 {
+        self.0 = self.0.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        ((self.0 >> 16) & 0x7fff) as f32 / 16383.5 - 1f32
+    }
+}
+
+fn hum(freq: f32, amplitude: f32, n: usize) -> f32 {
+    amplitude * (2f32 * PI * freq * n as f32 / SAMPLE_RATE).sin()
+}
+
+/// Feeds one window of readings, returning the report it ends with
+fn judge(monitor: &mut QualityMonitor, reading: impl FnMut(usize) -> f32) -> QualityReport {
+    let reports: Vec<_> = (0..WINDOW)
+        .map(reading)
+        .filter_map(|raw| monitor.update(raw.round().max(0f32).min(1023f32) as u16))
+        .collect();
+    assert_eq!(reports.len(), 1);
+    reports[0]
+}
+
+#[test]
+fn nothing_is_judged_before_a_whole_window() {
+    let mut monitor = QualityMonitor::new(SAMPLE_RATE);
+    let mut noise = Noise(1);
+    for _ in 0..WINDOW - 1 {
+        assert_eq!(
+            monitor.update((512f32 + 100f32 * noise.next()) as u16),
+            None
+        );
+    }
+    assert_eq!(monitor.report().status, SignalQuality::Unknown);
+    assert!(!monitor.report().is_usable());
+
+    assert!(monitor.update(512).is_some());
+    assert!(monitor.report().is_usable());
+}
+
+#[test]
+fn muscle_signal_is_good_however_strong() {
+    let mut monitor = QualityMonitor::new(SAMPLE_RATE);
+    let mut noise = Noise(2);
+
+    let resting = judge(&mut monitor, |_| 512f32 + 20f32 * noise.next());
+    assert_eq!(resting.status, SignalQuality::Good);
+
+    // A hard contraction swings widely, but across the whole band
+    let flexed = judge(&mut monitor, |n| {
+        512f32 + 400f32 * noise.next() + hum(50f32, 10f32, n)
+    });
+    assert_eq!(flexed.status, SignalQuality::Good);
+    assert!(flexed.index > 0.5, "{}", flexed.index);
+}
+
+#[test]
+fn rail_stuck_input_is_saturated() {
+    let mut monitor = QualityMonitor::new(SAMPLE_RATE);
+    assert_eq!(
+        judge(&mut monitor, |_| 1023f32).status,
+        SignalQuality::Saturated
+    );
+    assert_eq!(
+        judge(&mut monitor, |_| 0f32).status,
+        SignalQuality::Saturated
+    );
+
+    // A signal clipping at the top rail for a fifth of the time
+    let mut noise = Noise(3);
+    let clipped = judge(&mut monitor, |n| {
+        if n % 5 == 0 {
+            1023f32
+        } else {
+            512f32 + 100f32 * noise.next()
+        }
+    });
+    assert_eq!(clipped.status, SignalQuality::Saturated);
+    assert!((clipped.saturation_ratio - 0.2).abs() < 0.01);
+    assert!(!clipped.is_usable());
+}
+
+#[test]
+fn constant_input_is_a_flatline() {
+    let mut monitor = QualityMonitor::new(SAMPLE_RATE);
+    let flat = judge(&mut monitor, |_| 512f32);
+    assert_eq!(flat.status, SignalQuality::Flatline);
+    assert_eq!(flat.range, 0);
+
+    // A count of ADC jitter is still flat
+    let jitter = judge(&mut monitor, |n| 600f32 + (n % 2) as f32);
+    assert_eq!(jitter.status, SignalQuality::Flatline);
+    assert!(jitter.index < 0.5);
+}
+
+#[test]
+fn mains_hum_swamping_the_signal_is_noisy() {
+    let mut monitor = QualityMonitor::new(SAMPLE_RATE);
+    let mut noise = Noise(4);
+
+    for &freq in &[50f32, 60f32] {
+        let report = judge(&mut monitor, |n| {
+            512f32 + hum(freq, 300f32, n) + 20f32 * noise.next()
+        });
+        assert_eq!(report.status, SignalQuality::Noisy, "{}Hz", freq);
+        assert!(report.mains_ratio > 1f32);
+    }
+
+    // Faint hum is tolerated, however little else there is
+    let faint = judge(&mut monitor, |n| 512f32 + hum(50f32, 10f32, n));
+    assert_eq!(faint.status, SignalQuality::Good);
+}
@@ -0,0 +1,106 @@
+//! Checks how the scan moves and turns switch presses into selections.
+
+use std::time::Duration;
+
+use wfpi::scanning::{ScanConfig, ScanEvent, ScanMethod, Scanner};
+
+/// Scans through three outputs a second apart
+fn auto_scan() -> ScanConfig {
+    ScanConfig {
+        outputs: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        method: ScanMethod::Auto,
+        interval: Duration::from_secs(1),
+        auto_repeat: None,
+    }
+}
+
+fn ms(time: u64) -> Duration {
+    Duration::from_millis(time)
+}
+
+#[test]
+fn highlight_moves_on_a_timer_and_wraps() {
+    let mut scanner = Scanner::new(auto_scan());
+    scanner.reset(ms(0));
+    assert_eq!(scanner.highlighted(), Some("a"));
+    scanner.update(false, false, ms(999));
+    assert_eq!(scanner.highlighted(), Some("a"));
+    scanner.update(false, false, ms(1000));
+    assert_eq!(scanner.highlighted(), Some("b"));
+    // A stall doesn't lose the rhythm
+    scanner.update(false, false, ms(3500));
+    assert_eq!(scanner.highlighted(), Some("a"));
+    scanner.update(false, false, ms(4000));
+    assert_eq!(scanner.highlighted(), Some("b"));
+}
+
+#[test]
+fn selecting_holds_the_highlighted_output_and_freezes_the_scan() {
+    let mut scanner = Scanner::new(auto_scan());
+    scanner.reset(ms(0));
+    scanner.update(false, false, ms(1200));
+    assert_eq!(
+        scanner.update(false, true, ms(1300)),
+        vec![ScanEvent::Press("b".to_string())]
+    );
+    assert!(scanner.update(false, true, ms(5000)).is_empty());
+    assert_eq!(scanner.highlighted(), Some("b"));
+    assert_eq!(
+        scanner.update(false, false, ms(5100)),
+        vec![ScanEvent::Release("b".to_string())]
+    );
+    // A full interval on the output just used
+    scanner.update(false, false, ms(6000));
+    assert_eq!(scanner.highlighted(), Some("b"));
+    scanner.update(false, false, ms(6100));
+    assert_eq!(scanner.highlighted(), Some("c"));
+}
+
+#[test]
+fn auto_repeat_presses_again_while_held() {
+    let config = ScanConfig {
+        auto_repeat: Some(ms(500)),
+        ..auto_scan()
+    };
+    let mut scanner = Scanner::new(config);
+    scanner.reset(ms(0));
+    let mut events = scanner.update(false, true, ms(0));
+    for time in (100..=1000).step_by(100) {
+        events.extend(scanner.update(false, true, ms(time)));
+    }
+    assert_eq!(
+        events,
+        vec![
+            ScanEvent::Press("a".to_string()),
+            ScanEvent::Release("a".to_string()),
+            ScanEvent::Press("a".to_string()),
+            ScanEvent::Release("a".to_string()),
+            ScanEvent::Press("a".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn step_switch_moves_the_highlight() {
+    let config = ScanConfig {
+        method: ScanMethod::Step,
+        auto_repeat: Some(ms(300)),
+        ..auto_scan()
+    };
+    let mut scanner = Scanner::new(config);
+    scanner.reset(ms(0));
+    // No timer
+    scanner.update(false, false, ms(5000));
+    assert_eq!(scanner.highlighted(), Some("a"));
+
+    scanner.update(true, false, ms(5000));
+    assert_eq!(scanner.highlighted(), Some("b"));
+    // Held, it repeats
+    scanner.update(true, false, ms(5300));
+    assert_eq!(scanner.highlighted(), Some("c"));
+    scanner.update(false, false, ms(5400));
+    assert_eq!(
+        scanner.update(false, true, ms(5500)),
+        vec![ScanEvent::Press("c".to_string())]
+    );
+}
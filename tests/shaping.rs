@@ -0,0 +1,106 @@
+//! Checks the analog output shaping stages one at a time.
+
+use std::time::Duration;
+
+use wfpi::shaping::{Curve, Shaper, Shaping};
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+fn secs(time: f64) -> Duration {
+    Duration::from_millis((time * 1000f64) as u64)
+}
+
+#[test]
+fn identity_passes_values_through() {
+    let mut shaper = Shaper::new(Shaping::IDENTITY);
+    for &value in &[0f64, 12.5, 50f64, 100f64] {
+        assert!(close(shaper.shape(value, secs(0f64)), value));
+    }
+    assert!(close(shaper.shape(150f64, secs(0f64)), 100f64));
+    assert!(close(shaper.shape(-5f64, secs(0f64)), 0f64));
+}
+
+#[test]
+fn input_range_deadzone_and_output_range() {
+    let shaping = Shaping {
+        input_min: 20f64,
+        input_max: 80f64,
+        deadzone: 0.5,
+        output_min: 10f64,
+        output_max: 60f64,
+        ..Shaping::IDENTITY
+    };
+    assert!(close(shaping.map(0f64), 10f64));
+    assert!(close(shaping.map(50f64), 10f64));
+    assert!(close(shaping.map(65f64), 35f64));
+    assert!(close(shaping.map(80f64), 60f64));
+    assert!(close(shaping.map(99f64), 60f64));
+}
+
+#[test]
+fn curves_keep_their_end_points() {
+    for &curve in &[
+        Curve::Linear,
+        Curve::Exponential(3f64),
+        Curve::Exponential(-3f64),
+        Curve::Exponential(0f64),
+        Curve::Sigmoid(10f64),
+    ] {
+        assert!(close(curve.apply(0f64), 0f64), "{:?}", curve);
+        assert!(close(curve.apply(1f64), 1f64), "{:?}", curve);
+    }
+    assert!(Curve::Exponential(3f64).apply(0.5) < 0.5);
+    assert!(Curve::Exponential(-3f64).apply(0.5) > 0.5);
+    assert!(close(Curve::Sigmoid(10f64).apply(0.5), 0.5));
+    assert!(Curve::Sigmoid(10f64).apply(0.25) < 0.25);
+}
+
+#[test]
+fn smoothing_approaches_the_target_over_its_time_constant() {
+    let mut shaper = Shaper::new(Shaping {
+        smoothing: Some(secs(1f64)),
+        ..Shaping::IDENTITY
+    });
+    assert!(close(shaper.shape(0f64, secs(0f64)), 0f64));
+    let one_tau = shaper.shape(100f64, secs(1f64));
+    assert!(close(one_tau, 100f64 * (1f64 - (-1f64).exp())));
+
+    // Resetting forgets the history
+    shaper.reset();
+    assert!(close(shaper.shape(100f64, secs(1f64)), 100f64));
+}
+
+#[test]
+fn slew_rate_limits_each_step() {
+    let mut shaper = Shaper::new(Shaping {
+        slew_rate: Some(10f64),
+        ..Shaping::IDENTITY
+    });
+    assert!(close(shaper.shape(100f64, secs(0f64)), 100f64));
+    assert!(close(shaper.shape(0f64, secs(2f64)), 80f64));
+    assert!(close(shaper.shape(0f64, secs(2.5)), 75f64));
+
+    // Values written around the shaper are carried on from
+    shaper.hold(0f64, secs(3f64));
+    assert!(close(shaper.shape(100f64, secs(4f64)), 10f64));
+}
+
+#[test]
+fn ticks_carry_on_towards_the_last_input() {
+    let mut shaper = Shaper::new(Shaping {
+        slew_rate: Some(10f64),
+        ..Shaping::IDENTITY
+    });
+    assert!(close(shaper.shape(0f64, secs(0f64)), 0f64));
+    assert!(close(shaper.shape(20f64, secs(1f64)), 10f64));
+    assert!(close(shaper.tick(secs(1.5)).unwrap(), 15f64));
+    assert!(close(shaper.tick(secs(3f64)).unwrap(), 20f64));
+
+    // Once it's there, and after a held value, there's nothing left to do
+    assert_eq!(shaper.tick(secs(4f64)), None);
+    shaper.shape(100f64, secs(5f64));
+    shaper.hold(0f64, secs(5f64));
+    assert_eq!(shaper.tick(secs(6f64)), None);
+}
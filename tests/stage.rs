@@ -0,0 +1,263 @@
+//! Checks each EMG processing stage through a `Chain`, and the chains built from configs.
+
+use std::f64::consts::PI;
+
+use wfpi::emg_process::{
+    Chain, MovingAverage, PkPk, Rectify, Square, Stage, StageConfig, Threshold,
+};
+use wfpi::myo::{
+    EMGFilters, Filter2nd, Filter4th, FilterType, Flappy, NotchFrequency, SampleFrequency,
+};
+
+const RATE: SampleFrequency = SampleFrequency::Freq1000Hz;
+const MAINS: NotchFrequency = NotchFrequency::Freq60Hz;
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+fn chain_of<S: Stage + 'static>(stage: S) -> Chain {
+    Chain::builder().stage(stage).build()
+}
+
+fn run(chain: &mut Chain, input: &[f64]) -> Vec<f64> {
+    input.iter().map(|&sample| chain.process(sample)).collect()
+}
+
+fn sine(freq: f64, amplitude: f64, n: usize) -> f64 {
+    amplitude * (2f64 * PI * freq * n as f64 / 1000f64).sin()
+}
+
+/// A repeatable stand-in for surface EMG: quiet, a second's flex, then quiet again
+fn flex(count: usize) -> Vec<f64> {
+    let mut seed = 7u32;
+    (0..count)
+        .map(|n| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let noise = ((seed >> 16) & 0x7fff) as f64 / 16383.5 - 1f64;
+            let amplitude = if n >= 1000 && n < 2000 { 300f64 } else { 3f64 };
+            (512f64 + amplitude * noise).round()
+        })
+        .collect()
+}
+
+#[test]
+fn simple_stages() {
+    let mut chain = chain_of(Rectify);
+    assert_eq!(run(&mut chain, &[-3f64, 0f64, 2.5]), vec![3f64, 0f64, 2.5]);
+    assert!(close(chain.latency(), 0f64));
+
+    let mut chain = chain_of(Square);
+    assert_eq!(run(&mut chain, &[-3f64, 0.5]), vec![9f64, 0.25]);
+
+    let mut chain = chain_of(Threshold::new(4f64));
+    assert_eq!(
+        run(&mut chain, &[3.9, 4f64, 10f64]),
+        vec![0f64, 4f64, 10f64]
+    );
+    chain.set_threshold_scale(0.5);
+    assert_eq!(run(&mut chain, &[1.9, 2f64]), vec![0f64, 2f64]);
+    chain.reset();
+    assert_eq!(run(&mut chain, &[2f64]), vec![0f64]);
+}
+
+#[test]
+fn moving_average() {
+    let mut chain = chain_of(MovingAverage::new(4));
+    assert!(close(chain.latency(), 1.5));
+    let output = run(&mut chain, &[4f64, 8f64, 0f64, 4f64, 12f64]);
+    assert!(close(output[1], 6f64));
+    assert!(close(output[3], 4f64));
+    // The oldest reading drops out
+    assert!(close(output[4], 6f64));
+
+    chain.reset();
+    assert!(close(chain.process(10f64), 10f64));
+}
+
+#[test]
+fn peak_to_peak() {
+    // Windows long enough for 25Hz, twice over
+    let mut chain = chain_of(PkPk::new(1000, 25, 250));
+    assert!(close(chain.latency(), 40f64));
+
+    let input: Vec<_> = (0..200).map(|n| sine(50f64, 100f64, n)).collect();
+    let output = run(&mut chain, &input);
+    assert!((output[199] - 200f64).abs() < 1f64, "{}", output[199]);
+
+    chain.reset();
+    assert!(close(chain.process(5f64), 0f64));
+    assert!(close(chain.process(-5f64), 10f64));
+}
+
+#[test]
+fn butterworth_filters() {
+    let dc = vec![512f64; 1000];
+
+    let mut low = chain_of(Filter2nd::new(FilterType::LowPass, RATE));
+    assert!(low.latency() > 1f64 && low.latency() < 2f64);
+    assert!((run(&mut low, &dc)[999] - 512f64).abs() < 0.5);
+
+    let mut high = chain_of(Filter2nd::new(FilterType::HighPass, RATE));
+    assert!(close(high.latency(), 0f64));
+    // The rounded coefficients leave a trace of DC, under 1%
+    assert!(run(&mut high, &dc)[999].abs() < 5f64);
+
+    // Both start from rest again
+    low.reset();
+    high.reset();
+    assert!(low.process(512f64) < 100f64);
+    assert!(high.process(512f64) > 400f64);
+}
+
+#[test]
+fn anti_hum_notch_follows_the_mains_frequency() {
+    let settled_peak = |chain: &mut Chain, freq: f64| {
+        chain.reset();
+        let input: Vec<_> = (0..2000).map(|n| sine(freq, 100f64, n)).collect();
+        run(chain, &input)[1000..]
+            .iter()
+            .fold(0f64, |max, x| max.max(x.abs()))
+    };
+
+    let mut chain = chain_of(Filter4th::new(RATE, MAINS));
+    assert!(settled_peak(&mut chain, 60f64) < 20f64);
+    assert!(settled_peak(&mut chain, 50f64) > 80f64);
+
+    chain.set_notch_frequency(NotchFrequency::Freq50Hz);
+    assert!(settled_peak(&mut chain, 50f64) < 20f64);
+}
+
+#[test]
+fn chains_run_their_stages_in_order() {
+    let mut chain = Chain::builder()
+        .stage(Rectify)
+        .boxed(Box::new(Square))
+        .stage(Threshold::new(4f64))
+        .stage(MovingAverage::new(3))
+        .build();
+    assert_eq!(chain.len(), 4);
+    assert!(close(chain.latency(), 1f64));
+
+    chain.process(-3f64);
+    assert_eq!(chain.output_of(0), Some(3f64));
+    assert_eq!(chain.output_of(1), Some(9f64));
+    assert_eq!(chain.output_of(2), Some(9f64));
+    assert_eq!(chain.output_of(4), None);
+
+    // A scale reaches every stage that has a threshold
+    chain.set_threshold_scale(0.25);
+    chain.process(-1f64);
+    assert_eq!(chain.output_of(2), Some(1f64));
+
+    chain.reset();
+    assert_eq!(chain.output_of(3), Some(0f64));
+    assert!(Chain::builder().build().is_empty());
+}
+
+#[test]
+fn configs_build_the_same_stages() {
+    let config = [
+        StageConfig::EmgFilters {
+            notch: true,
+            lowpass: true,
+            highpass: true,
+            harmonics: true,
+        },
+        StageConfig::Rectify,
+        StageConfig::MovingAverage { length: 50 },
+        StageConfig::Threshold { level: 10f64 },
+    ];
+    let mut built = Chain::builder().config(&config, RATE, MAINS).build();
+
+    let mut filters = EMGFilters::new(RATE, MAINS, true, true, true);
+    filters.set_harmonic_filters(true);
+    let mut by_hand = Chain::builder()
+        .stage(filters)
+        .stage(Rectify)
+        .stage(MovingAverage::new(50))
+        .stage(Threshold::new(10f64))
+        .build();
+
+    assert_eq!(built.len(), 4);
+    assert!(close(built.latency(), by_hand.latency()));
+    let input = flex(3000);
+    assert_eq!(run(&mut built, &input), run(&mut by_hand, &input));
+}
+
+#[test]
+fn every_config_builds_one_stage() {
+    let configs = [
+        StageConfig::EmgFilters {
+            notch: true,
+            lowpass: false,
+            highpass: true,
+            harmonics: false,
+        },
+        StageConfig::Notch,
+        StageConfig::LowPass,
+        StageConfig::HighPass,
+        StageConfig::MovingAverage { length: 8 },
+        StageConfig::PeakToPeak {
+            min_frequency: 20,
+            max_frequency: 150,
+        },
+        StageConfig::Rectify,
+        StageConfig::Square,
+        StageConfig::Threshold { level: 1f64 },
+        StageConfig::Flappy {
+            threshold: 100,
+            harmonics: false,
+        },
+        StageConfig::FixedEmgFilters {
+            notch: true,
+            lowpass: true,
+            highpass: true,
+        },
+        StageConfig::FixedFlappy { threshold: 100 },
+    ];
+    for config in &configs {
+        let mut chain = Chain::builder().config(&[*config], RATE, MAINS).build();
+        assert_eq!(chain.len(), 1, "{:?}", config);
+        let output = run(&mut chain, &flex(100));
+        assert!(output.iter().all(|x| x.is_finite()), "{:?}", config);
+    }
+}
+
+#[test]
+fn flappy_pipeline_matches_the_flex_detector_it_replaced() {
+    // The default pipeline, against Flappy driven the way the myo parser used to
+    let mut chain = Chain::builder()
+        .config(
+            &[StageConfig::Flappy {
+                threshold: 100,
+                harmonics: false,
+            }],
+            RATE,
+            MAINS,
+        )
+        .build();
+    let mut flappy = Flappy::new(MAINS, false);
+
+    let mut flexes = 0;
+    for &sample in &flex(4000) {
+        let output = chain.process(sample);
+        let (flexed, envelope) = flappy.update(sample as u16);
+        assert_eq!(output > 0f64, flexed);
+        if flexed {
+            flexes += 1;
+            assert!(close(output, f64::from(envelope.max(100).max(1))));
+        }
+    }
+    // The flex was seen, and let go of after it ended
+    assert!(flexes > 500, "{}", flexes);
+    assert!(close(chain.process(512f64), 0f64));
+
+    // The fixed-point version sees the same flex
+    let mut fixed = Chain::builder()
+        .config(&[StageConfig::FixedFlappy { threshold: 100 }], RATE, MAINS)
+        .build();
+    let output = run(&mut fixed, &flex(4000));
+    assert!(output[1500] > 0f64);
+    assert!(close(output[3999], 0f64));
+}